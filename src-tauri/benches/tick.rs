@@ -0,0 +1,65 @@
+// The once a second path: serializing the tick event for the replay and the
+// webviews. `cargo bench --bench tick`
+use chrono::{DateTime, Duration, Utc};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::Serialize;
+use serde_json::{json, value::RawValue, Value};
+
+#[allow(dead_code)]
+#[path = "../src/payload.rs"]
+mod payload;
+
+use payload::PayloadWriter;
+
+// Mirrors `timer::Tick`
+#[derive(Serialize, Clone)]
+struct Tick {
+    schema_version: u32,
+    remaining_secs: i32,
+    phase_started_at: DateTime<Utc>,
+    phase_ends_at: Option<DateTime<Utc>>,
+}
+
+fn tick(remaining_secs: i32) -> Tick {
+    let now = Utc::now();
+    Tick {
+        schema_version: 1,
+        remaining_secs,
+        phase_started_at: now - Duration::seconds(1500 - remaining_secs as i64),
+        phase_ends_at: Some(now + Duration::seconds(remaining_secs as i64)),
+    }
+}
+
+fn api_version() -> Value {
+    json!({ "major": 1, "minor": 0 })
+}
+
+fn bench_tick(c: &mut Criterion) {
+    let tick = tick(1234);
+
+    // What `events::emit` did before: a `Value` tree, cloned for the replay,
+    // then turned into text for the webviews
+    c.bench_function("tick via Value", |b| {
+        let api_version = api_version();
+        b.iter(|| {
+            let mut payload = serde_json::to_value(black_box(&tick)).unwrap();
+            if let Value::Object(fields) = &mut payload {
+                fields.insert("api_version".into(), api_version.clone());
+            }
+            let replayed = payload.clone();
+            black_box((replayed, payload.to_string()))
+        })
+    });
+
+    c.bench_function("tick via PayloadWriter", |b| {
+        let mut writer = PayloadWriter::new(&json!({ "api_version": api_version() }));
+        b.iter(|| {
+            let json = writer.write(black_box(&tick)).unwrap();
+            let payload = RawValue::from_string(json.to_string()).unwrap();
+            black_box((payload.clone(), payload))
+        })
+    });
+}
+
+criterion_group!(benches, bench_tick);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+// The CLI finds the app's data directory by the bundle identifier, read here
+// from the app's config so the two can't drift apart
+use std::fs;
+
+const CONFIG: &str = "../tauri.conf.json";
+
+fn main() {
+    println!("cargo:rerun-if-changed={CONFIG}");
+    let config = fs::read_to_string(CONFIG).expect("reading the app's tauri.conf.json");
+    let config: serde_json::Value =
+        serde_json::from_str(&config).expect("parsing the app's tauri.conf.json");
+    let identifier = config["tauri"]["bundle"]["identifier"]
+        .as_str()
+        .expect("tauri.conf.json has no bundle identifier");
+    println!("cargo:rustc-env=POMODORIO_IDENTIFIER={identifier}");
+}
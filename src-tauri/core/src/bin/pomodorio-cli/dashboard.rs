@@ -0,0 +1,145 @@
+// `--dashboard`: the countdown, where it is in the cycle and today's and
+// this week's stats on one screen, driven from the keyboard
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Gauge, Paragraph},
+    Frame, Terminal,
+};
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use pomodorio_core::{cycle, stats::Stat, TimePhase};
+
+use crate::Session;
+
+const KEYS: &str = "space pause · s skip · q quit";
+
+fn color(phase: TimePhase) -> Color {
+    match phase {
+        TimePhase::Work => Color::Red,
+        TimePhase::ShortBreak => Color::Green,
+        TimePhase::LongBreak => Color::Blue,
+    }
+}
+
+fn stat_line(name: &str, stat: &Stat) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(
+            format!("{name:<6}"),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!("{} sessions, {} min", stat.sessions, stat.minutes)),
+    ])
+}
+
+fn draw(frame: &mut Frame, session: &Session) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(4),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+
+    let interval = session.lengths.long_break_interval.max(1);
+    let in_cycle = cycle::session_in_cycle(session.session_number, interval);
+    let mut title = format!(
+        "{}  ·  session {in_cycle} of {interval}",
+        session.phase.label()
+    );
+    if session.paused {
+        title.push_str("  ·  paused");
+    }
+    let phase = Paragraph::new(title)
+        .alignment(Alignment::Center)
+        .style(
+            Style::default()
+                .fg(color(session.phase))
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(Borders::ALL).title(" Pomodorio "));
+    frame.render_widget(phase, rows[0]);
+
+    let total = session.phase_secs().max(1);
+    let remaining = session.remaining_secs.clamp(0, total);
+    let countdown = Gauge::default()
+        .block(Block::default().borders(Borders::ALL))
+        .gauge_style(Style::default().fg(color(session.phase)))
+        .ratio(f64::from(total - remaining) / f64::from(total))
+        .label(format!("{:02}:{:02}", remaining / 60, remaining % 60));
+    frame.render_widget(countdown, rows[1]);
+
+    let stats = Paragraph::new(vec![
+        stat_line("Today", &session.stats.today),
+        stat_line("Week", &session.stats.week),
+        stat_line("Total", &session.stats.total),
+    ])
+    .block(Block::default().borders(Borders::ALL).title(" Stats "));
+    frame.render_widget(stats, rows[2]);
+
+    let keys = Paragraph::new(KEYS)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(keys, rows[3]);
+}
+
+// Until quit, or until the asked for Work sessions ran
+fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    session: &mut Session,
+) -> io::Result<()> {
+    let second = Duration::from_secs(1);
+    let mut last_tick = Instant::now();
+    while !session.done() {
+        terminal.draw(|frame| draw(frame, session))?;
+
+        let timeout = second.saturating_sub(last_tick.elapsed());
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    KeyCode::Char(' ') | KeyCode::Char('p') => session.toggle_pause(),
+                    KeyCode::Char('s') | KeyCode::Char('n') => {
+                        session.skip()?;
+                        last_tick = Instant::now();
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if last_tick.elapsed() >= second {
+            last_tick += second;
+            session.tick()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run(session: &mut Session) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let result = Terminal::new(CrosstermBackend::new(io::stdout()))
+        .and_then(|mut terminal| event_loop(&mut terminal, session));
+    // The terminal goes back to normal whatever happened
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    result
+}
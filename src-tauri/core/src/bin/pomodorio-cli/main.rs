@@ -0,0 +1,273 @@
+// The timer in a terminal, for machines without a desktop. Runs the same
+// cycle over the same store as the app, so stats and history carry across,
+// but it shouldn't run alongside the app: whichever writes last wins.
+//
+//     pomodorio-cli [--dashboard | --rpc] [--cycles N] [--no-notify] [--store PATH]
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::{from_value, json, Map, Value};
+use std::{
+    env,
+    io::{self, Write},
+    path::PathBuf,
+    process, thread,
+    time::Duration,
+};
+
+use pomodorio_core::{
+    cycle::{self, Lengths},
+    history::SessionRecord,
+    stats::Stats,
+    store, TimePhase,
+};
+
+mod dashboard;
+mod rpc;
+
+const USAGE: &str =
+    "usage: pomodorio-cli [--dashboard | --rpc] [--cycles N] [--no-notify] [--store PATH]";
+
+#[derive(PartialEq, Clone, Copy)]
+enum Mode {
+    // A single countdown line
+    Line,
+    // Full screen with stats and keys
+    Dashboard,
+    // Driven by JSON-RPC on stdin and stdout
+    Rpc,
+}
+
+struct Options {
+    mode: Mode,
+    // Work sessions to run before exiting, all day when unset
+    cycles: Option<u32>,
+    notify: bool,
+    store: PathBuf,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        mode: Mode::Line,
+        cycles: None,
+        notify: true,
+        store: store::store_path().ok_or("no data directory, pass --store")?,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dashboard" => options.mode = Mode::Dashboard,
+            "--rpc" => options.mode = Mode::Rpc,
+            "--cycles" => {
+                let count = args.next().and_then(|count| count.parse().ok());
+                options.cycles = Some(count.ok_or("--cycles takes a count")?);
+            }
+            "--no-notify" => options.notify = false,
+            "--store" => options.store = args.next().ok_or("--store takes a path")?.into(),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                process::exit(0);
+            }
+            other => return Err(format!("unknown argument {other}\n{USAGE}")),
+        }
+    }
+    Ok(options)
+}
+
+// Like the app's `get_from_store`, missing or unreadable values read as the default
+fn get<T: DeserializeOwned + Default>(stored: &Map<String, Value>, key: &str) -> T {
+    stored
+        .get(key)
+        .and_then(|value| from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn notify(phase: TimePhase, mode: Mode) {
+    // The terminal bell, for when the desktop notification goes nowhere.
+    // In RPC mode stdout carries the protocol, the client hears of it there.
+    if mode != Mode::Rpc {
+        print!("\x07");
+        let _ = io::stdout().flush();
+    }
+    let result = notify_rust::Notification::new()
+        .summary("Phase changed")
+        .body(phase.prompt())
+        .show();
+    // The dashboard has the terminal, printing would garble it
+    if let (Err(err), false) = (result, mode == Mode::Dashboard) {
+        eprintln!("Couldn't show a notification: {err}");
+    }
+}
+
+// Stats left from a previous day or week, as at the app's launch. Also
+// before every credit, a run can go past midnight.
+fn roll_over(stored: &mut Map<String, Value>) {
+    let last_opened = stored
+        .get("last_opened")
+        .cloned()
+        .map(from_value::<DateTime<Utc>>);
+    if let Some(Ok(last_opened)) = last_opened {
+        let mut stats: Stats = get(stored, "stats");
+        if stats.roll_over(last_opened, Utc::now()) {
+            stored.insert("stats".into(), json!(stats));
+        }
+    }
+}
+
+// The cycle as it runs, written back to the store as each phase ends
+struct Session {
+    options: Options,
+    lengths: Lengths,
+    session_number: i32,
+    phase: TimePhase,
+    started: DateTime<Utc>,
+    remaining_secs: i32,
+    paused: bool,
+    work_sessions: u32,
+    // As of the last phase end, for the dashboard
+    stats: Stats,
+}
+
+impl Session {
+    fn open(options: Options) -> io::Result<Self> {
+        let mut stored = store::load(&options.store)?;
+        roll_over(&mut stored);
+        let mut session = Self {
+            lengths: get(&stored, "settings"),
+            session_number: 0,
+            phase: TimePhase::Work,
+            started: Utc::now(),
+            remaining_secs: 0,
+            // Scripts say when to start
+            paused: options.mode == Mode::Rpc,
+            work_sessions: 0,
+            stats: get(&stored, "stats"),
+            options,
+        };
+        session.enter(get(&stored, "session_number"));
+        Ok(session)
+    }
+
+    fn enter(&mut self, session_number: i32) {
+        self.session_number = session_number;
+        self.phase = cycle::phase_for_session(session_number, self.lengths.long_break_interval);
+        self.started = Utc::now();
+        self.remaining_secs = self.phase_secs();
+    }
+
+    fn phase_secs(&self) -> i32 {
+        self.lengths.minutes_for(self.phase).max(0) * 60
+    }
+
+    // Ran the Work sessions it was asked for
+    fn done(&self) -> bool {
+        let done = self
+            .options
+            .cycles
+            .is_some_and(|cycles| self.work_sessions >= cycles);
+        self.phase == TimePhase::Work && done
+    }
+
+    // One second passed
+    fn tick(&mut self) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+        self.remaining_secs -= 1;
+        if self.remaining_secs <= 0 {
+            self.end(true)?;
+        }
+        Ok(())
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    // Skipped phases go in the history uncredited, as in the app
+    fn skip(&mut self) -> io::Result<()> {
+        self.end(false)
+    }
+
+    fn end(&mut self, completed: bool) -> io::Result<()> {
+        let minutes = if completed {
+            self.lengths.minutes_for(self.phase)
+        } else {
+            (Utc::now() - self.started).num_minutes() as i32
+        };
+
+        // Re-read, so what the app or a previous run changed meanwhile isn't lost
+        let mut stored = store::load(&self.options.store)?;
+        roll_over(&mut stored);
+        let mut stats: Stats = get(&stored, "stats");
+        if completed && self.phase == TimePhase::Work {
+            stats.credit(minutes);
+            self.work_sessions += 1;
+        }
+        stored.insert("stats".into(), json!(stats));
+        let mut history: Vec<SessionRecord> = get(&stored, "history");
+        history.push(SessionRecord {
+            start: self.started,
+            end: Utc::now(),
+            phase: self.phase,
+            minutes,
+            completed,
+            tag: None,
+            profile: None,
+            task: None,
+            note: None,
+        });
+        stored.insert("history".into(), json!(history));
+        let session_number = cycle::next(self.session_number);
+        stored.insert("session_number".into(), json!(session_number));
+        stored.insert("last_opened".into(), json!(Utc::now()));
+        store::save(&self.options.store, &stored)?;
+
+        self.stats = stats;
+        self.enter(session_number);
+        if self.options.notify {
+            notify(self.phase, self.options.mode);
+        }
+        Ok(())
+    }
+}
+
+fn count_down(session: &mut Session) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    while !session.done() {
+        let (phase, remaining) = (session.phase, session.remaining_secs);
+        write!(
+            stdout,
+            "\r{:<12} {:02}:{:02}",
+            phase.label(),
+            remaining / 60,
+            remaining % 60
+        )?;
+        stdout.flush()?;
+        thread::sleep(Duration::from_secs(1));
+        session.tick()?;
+        if session.phase != phase {
+            writeln!(stdout, "\r{:<12} done ", phase.label())?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(2);
+    });
+    let mode = options.mode;
+    if mode == Mode::Line {
+        println!("Using {}", options.store.display());
+    }
+    let result = Session::open(options).and_then(|mut session| match mode {
+        Mode::Line => count_down(&mut session),
+        Mode::Dashboard => dashboard::run(&mut session),
+        Mode::Rpc => rpc::run(&mut session),
+    });
+    if let Err(err) = result {
+        eprintln!("\n{err}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,283 @@
+// `--rpc`: JSON-RPC 2.0 over stdin and stdout, one message per line, so
+// editors and scripts can embed the timer without sockets. The timer waits
+// for `start`, and stops once stdin closes.
+//
+//     → {"jsonrpc":"2.0","id":1,"method":"subscribe"}
+//     ← {"jsonrpc":"2.0","id":1,"result":true}
+//     → {"jsonrpc":"2.0","id":2,"method":"start"}
+//     ← {"jsonrpc":"2.0","id":2,"result":{"phase":"Work","remaining_secs":1500,...}}
+//     ← {"jsonrpc":"2.0","method":"running","params":{"running":true}}
+//     ← {"jsonrpc":"2.0","method":"tick","params":{"phase":"Work","remaining_secs":1499}}
+//
+// Methods: status, start, pause, skip, subscribe, unsubscribe. Subscribers
+// are sent tick, running and switch-phase notifications.
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    io::{self, BufRead, Write},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use pomodorio_core::TimePhase;
+
+use crate::Session;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    // Left out for notifications, which get no response
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+}
+
+// Mirrors the control API's status, less what the CLI doesn't have
+#[derive(Serialize)]
+struct Status {
+    phase: TimePhase,
+    remaining_secs: i32,
+    running: bool,
+    session_number: i32,
+    sessions_today: i32,
+}
+
+fn status(session: &Session) -> Status {
+    Status {
+        phase: session.phase,
+        remaining_secs: session.remaining_secs,
+        running: !session.paused,
+        session_number: session.session_number,
+        sessions_today: session.stats.today.sessions,
+    }
+}
+
+fn send(stdout: &mut io::Stdout, message: Value) -> io::Result<()> {
+    serde_json::to_writer(&mut *stdout, &message)?;
+    stdout.write_all(b"\n")?;
+    stdout.flush()
+}
+
+fn error(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.into() },
+    })
+}
+
+fn call(
+    session: &mut Session,
+    subscribed: &mut bool,
+    method: &str,
+) -> Result<Value, (i64, String)> {
+    match method {
+        "status" => {}
+        "start" => session.paused = false,
+        "pause" => session.paused = true,
+        "skip" => session
+            .skip()
+            .map_err(|err| (INTERNAL_ERROR, err.to_string()))?,
+        "subscribe" | "unsubscribe" => {
+            *subscribed = method == "subscribe";
+            return Ok(json!(true));
+        }
+        _ => return Err((METHOD_NOT_FOUND, format!("unknown method {method}"))),
+    }
+    Ok(json!(status(session)))
+}
+
+// The response to one line of input, if it needs one
+fn handle(session: &mut Session, subscribed: &mut bool, line: &str) -> Option<Value> {
+    let value = match serde_json::from_str::<Value>(line) {
+        Ok(value) => value,
+        Err(err) => return Some(error(Value::Null, PARSE_ERROR, err.to_string())),
+    };
+    // Echoed back when it can be read, even if the rest of the request can't
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    let request = serde_json::from_value::<Request>(value).ok();
+    let Some(request) = request.filter(|request| request.jsonrpc == "2.0") else {
+        return Some(error(id, INVALID_REQUEST, "not a JSON-RPC 2.0 request"));
+    };
+    let outcome = call(session, subscribed, &request.method);
+    let id = request.id?;
+    Some(match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error(id, code, message),
+    })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+pub fn run(session: &mut Session) -> io::Result<()> {
+    // stdin blocks, read on a thread so the timer keeps ticking meanwhile
+    let (lines, incoming) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if lines.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut stdout = io::stdout();
+    let mut subscribed = false;
+    let second = Duration::from_secs(1);
+    let mut last_tick = Instant::now();
+    while !session.done() {
+        let (session_number, paused) = (session.session_number, session.paused);
+        match incoming.recv_timeout(second.saturating_sub(last_tick.elapsed())) {
+            Ok(line) if line.trim().is_empty() => {}
+            Ok(line) => {
+                if let Some(response) = handle(session, &mut subscribed, &line) {
+                    send(&mut stdout, response)?;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // Nothing left to take calls from
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        let mut ticked = false;
+        if last_tick.elapsed() >= second {
+            last_tick += second;
+            ticked = !session.paused;
+            session.tick()?;
+        }
+
+        if !subscribed {
+            continue;
+        }
+        if session.session_number != session_number {
+            let params = json!({
+                "phase": session.phase,
+                "session_number": session.session_number,
+            });
+            send(&mut stdout, notification("switch-phase", params))?;
+        }
+        if session.paused != paused {
+            send(
+                &mut stdout,
+                notification("running", json!({ "running": !session.paused })),
+            )?;
+        }
+        if ticked {
+            let params = json!({
+                "phase": session.phase,
+                "remaining_secs": session.remaining_secs,
+            });
+            send(&mut stdout, notification("tick", params))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use pomodorio_core::{cycle::Lengths, stats::Stats};
+    use std::env;
+
+    use crate::{Mode, Options};
+
+    // Paused at the start of Work, as `--rpc` opens. None of the methods
+    // called here end a phase, so the store is never touched.
+    fn session() -> Session {
+        Session {
+            options: Options {
+                mode: Mode::Rpc,
+                cycles: None,
+                notify: false,
+                store: env::temp_dir().join("pomodorio-cli-rpc-test.dat"),
+            },
+            lengths: Lengths::default(),
+            session_number: 0,
+            phase: TimePhase::Work,
+            started: Utc::now(),
+            remaining_secs: 1500,
+            paused: true,
+            work_sessions: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    fn respond(session: &mut Session, line: &str) -> Option<Value> {
+        handle(session, &mut false, line)
+    }
+
+    #[test]
+    fn unreadable_json_is_a_parse_error() {
+        let response = respond(&mut session(), "{\"jsonrpc\":").unwrap();
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+    }
+
+    #[test]
+    fn invalid_requests_echo_the_id_they_came_with() {
+        let mut session = session();
+        let request = r#"{"jsonrpc":"1.0","id":7,"method":"status"}"#;
+        let response = respond(&mut session, request).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+
+        let response = respond(&mut session, r#"{"jsonrpc":"2.0","id":"a"}"#).unwrap();
+        assert_eq!(response["id"], "a");
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+
+        let response = respond(&mut session, "[1, 2]").unwrap();
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+    }
+
+    #[test]
+    fn notifications_are_carried_out_without_a_response() {
+        let mut session = session();
+        assert_eq!(
+            respond(&mut session, r#"{"jsonrpc":"2.0","method":"start"}"#),
+            None
+        );
+        assert!(!session.paused);
+    }
+
+    #[test]
+    fn unknown_methods_are_not_found() {
+        let mut session = session();
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"stop"}"#;
+        let response = respond(&mut session, request).unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let mut subscribed = false;
+        assert!(call(&mut session, &mut subscribed, "stop").is_err());
+    }
+
+    #[test]
+    fn calls_answer_with_the_status_or_the_subscription() {
+        let mut session = session();
+        let mut subscribed = false;
+        assert_eq!(
+            call(&mut session, &mut subscribed, "subscribe"),
+            Ok(json!(true))
+        );
+        assert!(subscribed);
+
+        let status = call(&mut session, &mut subscribed, "pause").unwrap();
+        assert_eq!(status["running"], json!(false));
+        assert_eq!(status["remaining_secs"], 1500);
+        assert_eq!(
+            call(&mut session, &mut subscribed, "unsubscribe"),
+            Ok(json!(true))
+        );
+        assert!(!subscribed);
+    }
+}
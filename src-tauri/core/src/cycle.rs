@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+use crate::TimePhase;
+
+// The part of the settings the cycle runs on, read out of the full settings
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct Lengths {
+    pub work_time: i32,
+    pub short_break_time: i32,
+    pub long_break_time: i32,
+    pub long_break_interval: i32,
+}
+
+impl Default for Lengths {
+    fn default() -> Self {
+        Self {
+            work_time: 25,
+            short_break_time: 5,
+            long_break_time: 20,
+            long_break_interval: 4,
+        }
+    }
+}
+
+impl Lengths {
+    pub fn minutes_for(&self, phase: TimePhase) -> i32 {
+        match phase {
+            TimePhase::Work => self.work_time,
+            TimePhase::ShortBreak => self.short_break_time,
+            TimePhase::LongBreak => self.long_break_time,
+        }
+    }
+}
+
+// Even session numbers are Work, odd ones the break after it. Every
+// `long_break_interval`th Work session is followed by a long break.
+pub fn phase_for_session(session_number: i32, long_break_interval: i32) -> TimePhase {
+    if session_number % 2 == 1 {
+        if (session_number + 1) % (long_break_interval.max(1) * 2) == 0 {
+            TimePhase::LongBreak
+        } else {
+            TimePhase::ShortBreak
+        }
+    } else {
+        TimePhase::Work
+    }
+}
+
+// Work sessions since the last long break, counting the current one
+pub fn session_in_cycle(session_number: i32, long_break_interval: i32) -> i32 {
+    let mut work_sessions = 0;
+    for number in (0..=session_number).rev() {
+        match phase_for_session(number, long_break_interval) {
+            TimePhase::LongBreak if number != session_number => break,
+            TimePhase::Work => work_sessions += 1,
+            _ => {}
+        }
+    }
+    work_sessions
+}
+
+// Next and skip both move on one phase
+pub fn next(session_number: i32) -> i32 {
+    session_number + 1
+}
+
+// None before the first Work session, there's nothing to go back to
+pub fn previous(session_number: i32) -> Option<i32> {
+    (session_number > 0).then(|| session_number - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[derive(Clone, Debug)]
+    enum Step {
+        Next,
+        Skip,
+        Previous,
+        SetInterval(i32),
+    }
+
+    fn step() -> impl Strategy<Value = Step> {
+        prop_oneof![
+            3 => Just(Step::Next),
+            1 => Just(Step::Skip),
+            2 => Just(Step::Previous),
+            1 => (1..=8).prop_map(Step::SetInterval),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn long_break_follows_every_nth_work_session(interval in 1..=12, cycles in 1..=20) {
+            let phases: Vec<TimePhase> = (0..cycles * interval * 2)
+                .map(|number| phase_for_session(number, interval))
+                .collect();
+            let mut work_since_long = 0;
+            for (number, phase) in phases.iter().enumerate() {
+                match phase {
+                    TimePhase::Work => {
+                        prop_assert_eq!(number % 2, 0);
+                        work_since_long += 1;
+                    }
+                    TimePhase::ShortBreak => prop_assert!(work_since_long < interval),
+                    TimePhase::LongBreak => {
+                        prop_assert_eq!(work_since_long, interval);
+                        work_since_long = 0;
+                    }
+                }
+            }
+            let long_breaks = phases.iter().filter(|phase| **phase == TimePhase::LongBreak);
+            prop_assert_eq!(long_breaks.count() as i32, cycles);
+        }
+
+        #[test]
+        fn any_sequence_of_steps_stays_in_cycle(
+            interval in 1..=8,
+            steps in prop::collection::vec(step(), 0..200),
+        ) {
+            let mut interval = interval;
+            let mut session_number = 0;
+            for step in steps {
+                let before = session_number;
+                match step {
+                    Step::Next | Step::Skip => session_number = next(session_number),
+                    Step::Previous => match previous(session_number) {
+                        Some(number) => {
+                            prop_assert_eq!(next(number), before);
+                            session_number = number;
+                        }
+                        None => prop_assert_eq!(session_number, 0),
+                    },
+                    // Only the phases from here on follow the new interval
+                    Step::SetInterval(value) => interval = value,
+                }
+                prop_assert!(session_number >= 0);
+
+                let phase = phase_for_session(session_number, interval);
+                prop_assert_eq!(phase == TimePhase::Work, session_number % 2 == 0);
+                let in_cycle = session_in_cycle(session_number, interval);
+                prop_assert!((1..=interval).contains(&in_cycle));
+                if phase == TimePhase::LongBreak {
+                    prop_assert_eq!(in_cycle, interval);
+                }
+            }
+        }
+    }
+}
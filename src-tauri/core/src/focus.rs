@@ -0,0 +1,116 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::history::DailyTotal;
+
+// Weights of the focus score parts, adding up to 100
+const COMPLETION_WEIGHT: f64 = 40.0;
+const UNINTERRUPTED_WEIGHT: f64 = 20.0;
+const BREAKS_WEIGHT: f64 = 30.0;
+const OVERTIME_WEIGHT: f64 = 10.0;
+// Focused minutes in a day past which the overtime part starts dropping,
+// reaching zero at twice as much
+const FOCUS_DAY_MINUTES: i32 = 360;
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct DayScore {
+    pub date: NaiveDate,
+    // 0 to 100
+    pub score: i32,
+    pub completion_rate: f64,
+    // Completed Work sessions that were paused along the way
+    pub interruptions: i32,
+    // Breaks that ran out rather than being skipped, None without breaks
+    pub breaks_honored: Option<f64>,
+    pub overtime_minutes: i32,
+}
+
+// Days without a Work session have no score
+pub fn scores<'a>(days: impl IntoIterator<Item = &'a DailyTotal>) -> Vec<DayScore> {
+    days.into_iter()
+        .filter(|day| day.started > 0)
+        .map(|day| {
+            let completion_rate = day.sessions as f64 / day.started as f64;
+            let uninterrupted = if day.sessions > 0 {
+                1.0 - day.interrupted as f64 / day.sessions as f64
+            } else {
+                0.0
+            };
+            let breaks_honored =
+                (day.breaks > 0).then(|| day.breaks_taken as f64 / day.breaks as f64);
+            let overtime_minutes = (day.minutes - FOCUS_DAY_MINUTES).max(0);
+            let rested = 1.0 - (overtime_minutes as f64 / FOCUS_DAY_MINUTES as f64).min(1.0);
+            let score = COMPLETION_WEIGHT * completion_rate
+                + UNINTERRUPTED_WEIGHT * uninterrupted
+                + BREAKS_WEIGHT * breaks_honored.unwrap_or(1.0)
+                + OVERTIME_WEIGHT * rested;
+            DayScore {
+                date: day.date,
+                score: score.round() as i32,
+                completion_rate,
+                interruptions: day.interrupted,
+                breaks_honored,
+                overtime_minutes,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        history::{daily_totals, SessionRecord},
+        TimePhase,
+    };
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn work(end: &str, minutes: i32) -> SessionRecord {
+        SessionRecord::ended(TimePhase::Work, end, minutes)
+    }
+
+    fn skipped(record: SessionRecord) -> SessionRecord {
+        SessionRecord {
+            completed: false,
+            ..record
+        }
+    }
+
+    fn short_break(end: &str) -> SessionRecord {
+        SessionRecord::ended(TimePhase::ShortBreak, end, 5)
+    }
+
+    #[test]
+    fn focus_score_weighs_completion_pauses_breaks_and_overtime() {
+        let paused = SessionRecord {
+            start: "2024-03-04T09:00:00Z".parse().unwrap(),
+            ..work("2024-03-04T09:40:00Z", 25)
+        };
+        let records = [
+            paused,
+            work("2024-03-04T10:30:00Z", 25),
+            skipped(work("2024-03-04T11:05:00Z", 5)),
+            short_break("2024-03-04T09:45:00Z"),
+            skipped(short_break("2024-03-04T10:35:00Z")),
+            // Only a break, no score that day
+            short_break("2024-03-05T10:00:00Z"),
+            // 7 hours of focus, an hour over
+            work("2024-03-06T12:00:00Z", 420),
+        ];
+        let scores: Vec<_> = scores(daily_totals(&records, &[]).values())
+            .iter()
+            .map(|day| (day.date, day.score, day.interruptions, day.overtime_minutes))
+            .collect();
+        // 40 * 2/3 + 20 * 1/2 + 30 * 1/2 + 10 = 61.67, and 40 + 20 + 30 + 10 * 5/6 = 98.33
+        assert_eq!(
+            scores,
+            [
+                (date("2024-03-04"), 62, 1, 0),
+                (date("2024-03-06"), 98, 0, 60)
+            ]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::TimePhase;
+
+// One finished phase. The aggregate stats stay the source of truth for
+// the unfiltered numbers, this is what filtered queries are computed from.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionRecord {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub phase: TimePhase,
+    pub minutes: i32,
+    // Ran out on its own rather than being skipped
+    pub completed: bool,
+    pub tag: Option<String>,
+    pub profile: Option<String>,
+    // What was being worked on, finer grained than the tag
+    #[serde(default)]
+    pub task: Option<String>,
+    // Written afterwards with `annotate_session`
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+// Wall time a Work session may run past its length before it counts as paused
+const PAUSE_SLACK_MINUTES: i64 = 1;
+
+// What the sessions ending on one UTC day add up to, what per-day reports
+// read. Stored in place of the sessions once they're pruned.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct DailyTotal {
+    pub date: NaiveDate,
+    // Completed Work sessions and their minutes
+    pub sessions: i32,
+    pub minutes: i32,
+    // The rest is missing from totals stored before it was kept.
+    // Work sessions, completed or not.
+    #[serde(default)]
+    pub started: i32,
+    // Completed Work sessions that were paused along the way
+    #[serde(default)]
+    pub interrupted: i32,
+    #[serde(default)]
+    pub breaks: i32,
+    // Breaks that ran out rather than being skipped
+    #[serde(default)]
+    pub breaks_taken: i32,
+    // Completed Work sessions per tag
+    #[serde(default)]
+    pub tags: BTreeMap<String, i32>,
+}
+
+impl DailyTotal {
+    pub fn new(date: NaiveDate) -> Self {
+        Self {
+            date,
+            sessions: 0,
+            minutes: 0,
+            started: 0,
+            interrupted: 0,
+            breaks: 0,
+            breaks_taken: 0,
+            tags: BTreeMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, record: &SessionRecord) {
+        if record.phase != TimePhase::Work {
+            self.breaks += 1;
+            self.breaks_taken += record.completed as i32;
+            return;
+        }
+        self.started += 1;
+        if !record.completed {
+            return;
+        }
+        self.sessions += 1;
+        self.minutes += record.minutes;
+        let wall = (record.end - record.start).num_minutes();
+        if wall > record.minutes as i64 + PAUSE_SLACK_MINUTES {
+            self.interrupted += 1;
+        }
+        if let Some(tag) = &record.tag {
+            *self.tags.entry(tag.clone()).or_default() += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &DailyTotal) {
+        self.sessions += other.sessions;
+        self.minutes += other.minutes;
+        // Older totals only counted the completed ones
+        self.started += other.started.max(other.sessions);
+        self.interrupted += other.interrupted;
+        self.breaks += other.breaks;
+        self.breaks_taken += other.breaks_taken;
+        for (tag, sessions) in &other.tags {
+            *self.tags.entry(tag.clone()).or_default() += sessions;
+        }
+    }
+}
+
+// Per day, what `records` add up to together with `kept` totals, those of
+// the pruned sessions
+pub fn daily_totals(
+    records: &[SessionRecord],
+    kept: &[DailyTotal],
+) -> BTreeMap<NaiveDate, DailyTotal> {
+    let mut days = BTreeMap::new();
+    for total in kept {
+        days.entry(total.date)
+            .or_insert_with(|| DailyTotal::new(total.date))
+            .merge(total);
+    }
+    for record in records {
+        let date = record.end.date_naive();
+        days.entry(date)
+            .or_insert_with(|| DailyTotal::new(date))
+            .add(record);
+    }
+    days
+}
+
+// For tests building histories, here and in the app. The rest is set with
+// struct update syntax, e.g. `SessionRecord { completed: false, ..record }`.
+#[cfg(any(test, feature = "test-support"))]
+impl SessionRecord {
+    // A completed `phase` of `minutes` ending at `end`, e.g. "2024-03-04T09:25:00Z"
+    pub fn ended(phase: TimePhase, end: &str, minutes: i32) -> Self {
+        let end: DateTime<Utc> = end.parse().unwrap();
+        let start = end - chrono::Duration::minutes(minutes as i64);
+        Self::from_start(phase, start, minutes)
+    }
+
+    // The same, starting at `start`
+    pub fn started(phase: TimePhase, start: &str, minutes: i32) -> Self {
+        Self::from_start(phase, start.parse().unwrap(), minutes)
+    }
+
+    fn from_start(phase: TimePhase, start: DateTime<Utc>, minutes: i32) -> Self {
+        Self {
+            start,
+            end: start + chrono::Duration::minutes(minutes as i64),
+            phase,
+            minutes,
+            completed: true,
+            tag: None,
+            profile: None,
+            task: None,
+            note: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn daily_totals_add_the_records_to_the_kept_totals() {
+        let paused = SessionRecord {
+            start: "2024-03-04T09:00:00Z".parse().unwrap(),
+            tag: Some("writing".into()),
+            ..SessionRecord::ended(TimePhase::Work, "2024-03-04T09:40:00Z", 25)
+        };
+        let records = [
+            paused,
+            SessionRecord {
+                completed: false,
+                ..SessionRecord::ended(TimePhase::Work, "2024-03-04T10:10:00Z", 10)
+            },
+            SessionRecord::ended(TimePhase::ShortBreak, "2024-03-04T09:45:00Z", 5),
+            // Ended past midnight, counts for the next day
+            SessionRecord::ended(TimePhase::Work, "2024-03-05T00:10:00Z", 25),
+        ];
+        // Stored before totals kept more than completed Work
+        let kept: Vec<DailyTotal> = serde_json::from_value(serde_json::json!([
+            { "date": "2024-03-04", "sessions": 2, "minutes": 50 },
+        ]))
+        .unwrap();
+
+        let days = daily_totals(&records, &kept);
+        assert_eq!(days.len(), 2);
+        let monday = &days[&date("2024-03-04")];
+        assert_eq!(
+            (monday.sessions, monday.minutes, monday.started),
+            (3, 75, 4)
+        );
+        assert_eq!(
+            (monday.interrupted, monday.breaks, monday.breaks_taken),
+            (1, 1, 1)
+        );
+        assert_eq!(monday.tags, BTreeMap::from([("writing".into(), 1)]));
+        let tuesday = &days[&date("2024-03-05")];
+        assert_eq!((tuesday.sessions, tuesday.started), (1, 1));
+    }
+}
@@ -0,0 +1,49 @@
+// What the app and `pomodorio-cli` share: the cycle, the stats and the
+// shapes of what's kept in the store. Nothing here depends on Tauri.
+use serde::{Deserialize, Serialize};
+
+pub mod cycle;
+pub mod focus;
+pub mod history;
+pub mod stats;
+pub mod store;
+
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum TimePhase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Default for TimePhase {
+    fn default() -> Self {
+        Self::Work
+    }
+}
+
+impl TimePhase {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimePhase::Work => "Work",
+            TimePhase::ShortBreak => "Short break",
+            TimePhase::LongBreak => "Long break",
+        }
+    }
+
+    pub fn class(&self) -> &'static str {
+        match self {
+            TimePhase::Work => "work",
+            TimePhase::ShortBreak => "short-break",
+            TimePhase::LongBreak => "long-break",
+        }
+    }
+
+    // What the phase change notification says on entering it
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            TimePhase::Work => "Time to get back to work!",
+            TimePhase::ShortBreak => "Have a little rest!",
+            TimePhase::LongBreak => "Take some extra time to relax!",
+        }
+    }
+}
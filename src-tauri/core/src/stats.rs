@@ -0,0 +1,68 @@
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Stat {
+    pub minutes: i32,
+    pub sessions: i32,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Stats {
+    pub today: Stat,
+    pub week: Stat,
+    pub total: Stat,
+}
+
+impl Stats {
+    // Starts today and this week over when `now` is past the ones
+    // `last_opened` was in. Days are UTC days, weeks ISO weeks.
+    pub fn roll_over(&mut self, last_opened: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        let new_day = now.date_naive() != last_opened.date_naive();
+        let new_week = now.iso_week() != last_opened.iso_week();
+        if new_day {
+            self.today = Stat::default();
+        }
+        if new_week {
+            self.week = Stat::default();
+        }
+        new_day || new_week
+    }
+
+    // A completed Work session of `minutes`
+    pub fn credit(&mut self, minutes: i32) {
+        for stat in [&mut self.today, &mut self.week, &mut self.total] {
+            stat.minutes += minutes;
+            stat.sessions += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn roll_over_resets_the_day_and_the_week_independently() {
+        let mut stats = Stats::default();
+        stats.credit(25);
+
+        // Same day
+        assert!(!stats.roll_over(at("2024-03-05T08:00:00Z"), at("2024-03-05T22:00:00Z")));
+        assert_eq!(stats.today.sessions, 1);
+
+        // Tuesday to Wednesday
+        assert!(stats.roll_over(at("2024-03-05T22:00:00Z"), at("2024-03-06T09:00:00Z")));
+        assert_eq!((stats.today.sessions, stats.week.sessions), (0, 1));
+
+        // Friday to the next Monday starts both over
+        stats.credit(25);
+        assert!(stats.roll_over(at("2024-03-08T17:00:00Z"), at("2024-03-11T09:00:00Z")));
+        assert_eq!((stats.today.sessions, stats.week.sessions), (0, 0));
+        assert_eq!((stats.total.minutes, stats.total.sessions), (50, 2));
+    }
+}
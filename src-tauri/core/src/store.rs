@@ -0,0 +1,71 @@
+use serde_json::{Map, Value};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+// The bundle identifier in tauri.conf.json, Tauri names the data directory
+// after it. build.rs reads it from there.
+pub const IDENTIFIER: &str = env!("POMODORIO_IDENTIFIER");
+// JSON object of key to value, in the data directory
+pub const STORE_FILE: &str = ".store.dat";
+
+// The directory Tauri keeps the app's data in on this platform
+pub fn data_dir() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(target_os = "macos") {
+        home()?.join("Library/Application Support")
+    } else if cfg!(windows) {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else {
+        match env::var_os("XDG_DATA_HOME").map(PathBuf::from) {
+            Some(path) if path.is_absolute() => path,
+            _ => home()?.join(".local/share"),
+        }
+    };
+    Some(base.join(IDENTIFIER))
+}
+
+pub fn store_path() -> Option<PathBuf> {
+    Some(data_dir()?.join(STORE_FILE))
+}
+
+// Empty when there's no store yet, the keys read from it have defaults
+pub fn load(path: &Path) -> io::Result<Map<String, Value>> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Map::new()),
+        Err(err) => Err(err),
+    }
+}
+
+// Written next to the store and renamed over it, so it's never left half written
+pub fn save(path: &Path, store: &Map<String, Value>) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temporary = path.with_extension("dat.tmp");
+    fs::write(&temporary, serde_json::to_vec(store)?)?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn save_keeps_what_load_read() {
+        let dir = env::temp_dir().join(format!("pomodorio-core-store-{}", std::process::id()));
+        let path = dir.join(STORE_FILE);
+        assert!(load(&path).unwrap().is_empty());
+
+        let Value::Object(stored) = json!({ "session_number": 3, "window_mode": "full" }) else {
+            unreachable!()
+        };
+        save(&path, &stored).unwrap();
+        assert_eq!(load(&path).unwrap(), stored);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::{path::Path, process::Command};
+use tauri::AppHandle;
+
+use crate::{modify_settings, read_settings, Error, TimePhase};
+
+// Only web pages, never file:// or custom handlers that could run something
+const URL_SCHEMES: [&str; 2] = ["https://", "http://"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionTrigger {
+    Work,
+    ShortBreak,
+    LongBreak,
+    // Either kind of break
+    AnyBreak,
+}
+
+impl ActionTrigger {
+    fn matches(self, phase: TimePhase) -> bool {
+        match self {
+            ActionTrigger::Work => phase == TimePhase::Work,
+            ActionTrigger::ShortBreak => phase == TimePhase::ShortBreak,
+            ActionTrigger::LongBreak => phase == TimePhase::LongBreak,
+            ActionTrigger::AnyBreak => phase != TimePhase::Work,
+        }
+    }
+}
+
+// Opens something when a phase starts, e.g. the task board at Work start
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PhaseAction {
+    pub on: ActionTrigger,
+    // An http(s) URL, or the path of an app listed in `allowed_apps`
+    pub open: String,
+}
+
+enum Target<'a> {
+    Url(&'a str),
+    App(&'a Path),
+}
+
+fn validate<'a>(action: &'a PhaseAction, allowed_apps: &[String]) -> Result<Target<'a>, Error> {
+    let open = action.open.trim();
+    let lowercase = open.to_lowercase();
+    if URL_SCHEMES
+        .iter()
+        .any(|scheme| lowercase.starts_with(scheme))
+    {
+        if open.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(Error::InvalidAction(format!("{open} isn't a valid URL")));
+        }
+        return Ok(Target::Url(open));
+    }
+
+    if !allowed_apps.iter().any(|app| app == open) {
+        return Err(Error::InvalidAction(format!(
+            "{open} is neither an http(s) URL nor an allowed app"
+        )));
+    }
+    let path = Path::new(open);
+    if !path.is_absolute() || !path.exists() {
+        return Err(Error::InvalidAction(format!("{open} doesn't exist")));
+    }
+    Ok(Target::App(path))
+}
+
+// Nothing goes through a shell, the target is always a single argument
+fn open(target: Target) -> std::io::Result<()> {
+    let mut command = match target {
+        Target::Url(url) if cfg!(target_os = "macos") => {
+            let mut command = Command::new("open");
+            command.arg(url);
+            command
+        }
+        Target::Url(url) if cfg!(windows) => {
+            let mut command = Command::new("rundll32");
+            command.args(["url.dll,FileProtocolHandler", url]);
+            command
+        }
+        Target::Url(url) => {
+            let mut command = Command::new("xdg-open");
+            command.arg(url);
+            command
+        }
+        // .app bundles are directories, `open` launches them
+        Target::App(path) if cfg!(target_os = "macos") && path.is_dir() => {
+            let mut command = Command::new("open");
+            command.arg("-a").arg(path);
+            command
+        }
+        Target::App(path) => Command::new(path),
+    };
+    command.spawn().map(|_| ())
+}
+
+// Actions from the config file or the environment never went through
+// `set_phase_actions`, so they're checked again right before running
+pub fn on_phase_change(app: &AppHandle, phase: TimePhase) {
+    let settings = read_settings(app);
+    for action in &settings.phase_actions {
+        if !action.on.matches(phase) {
+            continue;
+        }
+        let result = validate(action, &settings.allowed_apps).and_then(|target| Ok(open(target)?));
+        if let Err(err) = result {
+            eprintln!(
+                "Skipping the {:?} action for {}: {err}",
+                action.on, action.open
+            );
+        }
+    }
+}
+
+#[tauri::command]
+pub fn set_phase_actions(actions: Vec<PhaseAction>, app: AppHandle) -> Result<(), Error> {
+    let allowed_apps = read_settings(&app).allowed_apps;
+    for action in &actions {
+        validate(action, &allowed_apps)?;
+    }
+    modify_settings(&app, |settings| settings.phase_actions = actions)
+}
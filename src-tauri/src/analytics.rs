@@ -0,0 +1,551 @@
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Mutex};
+use tauri::{AppHandle, Manager};
+
+use pomodorio_core::focus::{self, DayScore};
+
+use crate::{
+    days_off, events,
+    history::{self, DailyTotal, SessionRecord},
+    read_settings, with_store, TimePhase,
+};
+
+// Shades of the contribution graph, 0 for days without a session
+const HEATMAP_LEVELS: i32 = 4;
+
+fn load(app: &AppHandle) -> Vec<SessionRecord> {
+    let mut records = Vec::new();
+    with_store(app, |store| {
+        records = history::load(store);
+        Ok(())
+    });
+    records
+}
+
+// Days are UTC days, like the stats. Pruned days come from their totals.
+fn load_days(app: &AppHandle) -> BTreeMap<NaiveDate, DailyTotal> {
+    let mut days = BTreeMap::new();
+    with_store(app, |store| {
+        days = history::load_days(store);
+        Ok(())
+    });
+    days
+}
+
+fn completed_work(records: &[SessionRecord]) -> impl Iterator<Item = &SessionRecord> {
+    records
+        .iter()
+        .filter(|record| record.completed && record.phase == TimePhase::Work)
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct HeatmapDay {
+    pub date: NaiveDate,
+    pub sessions: i32,
+    pub minutes: i32,
+    // 0 to 4, relative to the busiest day in range
+    pub level: i32,
+    // Why the day was marked off, if it was
+    pub day_off: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Heatmap {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    // One column per week, Sunday first. Days outside the range are null.
+    pub weeks: Vec<[Option<HeatmapDay>; 7]>,
+    pub total_sessions: i32,
+    pub max_sessions: i32,
+}
+
+fn heatmap(days: &BTreeMap<NaiveDate, DailyTotal>, start: NaiveDate, end: NaiveDate) -> Heatmap {
+    let max_sessions = days
+        .range(start..=end)
+        .map(|(_, day)| day.sessions)
+        .max()
+        .unwrap_or(0);
+
+    let mut weeks = Vec::new();
+    let mut total_sessions = 0;
+    let first_sunday = start - Days::new(start.weekday().num_days_from_sunday() as u64);
+    for week_start in first_sunday.iter_weeks().take_while(|date| *date <= end) {
+        let mut week: [Option<HeatmapDay>; 7] = Default::default();
+        for (slot, date) in week.iter_mut().zip(week_start.iter_days()) {
+            if date < start || date > end {
+                continue;
+            }
+            let (sessions, minutes) = days
+                .get(&date)
+                .map_or((0, 0), |day| (day.sessions, day.minutes));
+            total_sessions += sessions;
+            let level = if sessions == 0 {
+                0
+            } else {
+                (sessions * HEATMAP_LEVELS + max_sessions - 1) / max_sessions
+            };
+            *slot = Some(HeatmapDay {
+                date,
+                sessions,
+                minutes,
+                level,
+                day_off: None,
+            });
+        }
+        weeks.push(week);
+    }
+
+    Heatmap {
+        start,
+        end,
+        weeks,
+        total_sessions,
+        max_sessions,
+    }
+}
+
+// Completed Work sessions per day of `year`, or of the past year up to
+// today without one
+#[tauri::command]
+pub fn get_heatmap(year: Option<i32>, app: AppHandle) -> Heatmap {
+    let today = Utc::now().date_naive();
+    let (start, end) = match year.and_then(|year| {
+        Some((
+            NaiveDate::from_ymd_opt(year, 1, 1)?,
+            NaiveDate::from_ymd_opt(year, 12, 31)?,
+        ))
+    }) {
+        Some(range) => range,
+        None => (today - Days::new(364), today),
+    };
+    let mut heatmap = heatmap(&load_days(&app), start, end);
+    let days_off = days_off::read(&app);
+    for day in heatmap.weeks.iter_mut().flatten().flatten() {
+        day.day_off = days_off.get(&day.date).cloned();
+    }
+    heatmap
+}
+
+// Averages over the days before and including `end`, days without sessions
+// counting as zero
+#[derive(Serialize, Clone, Copy, Default, Debug)]
+pub struct Window {
+    pub minutes_per_day: f64,
+    pub sessions: i32,
+    // Completed Work sessions out of all started ones, None without any
+    pub completion_rate: Option<f64>,
+}
+
+fn window(days: &BTreeMap<NaiveDate, DailyTotal>, end: NaiveDate, length: u64) -> Window {
+    let start = end - Days::new(length - 1);
+    let (mut started, mut sessions, mut minutes) = (0, 0, 0);
+    for (_, day) in days.range(start..=end) {
+        started += day.started;
+        sessions += day.sessions;
+        minutes += day.minutes;
+    }
+    Window {
+        minutes_per_day: minutes as f64 / length as f64,
+        sessions,
+        completion_rate: (started > 0).then(|| sessions as f64 / started as f64),
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct TrendPoint {
+    pub date: NaiveDate,
+    pub minutes: i32,
+    pub rolling_7d_minutes: f64,
+    pub day_off: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WeekOverWeek {
+    pub minutes_per_day: f64,
+    pub sessions: i32,
+    pub completion_rate: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Trends {
+    pub last_7_days: Window,
+    pub last_30_days: Window,
+    // The last 7 days minus the 7 before them, positive is better
+    pub week_over_week: WeekOverWeek,
+    // The last 30 days, oldest first, for a chart
+    pub daily: Vec<TrendPoint>,
+}
+
+fn trends(days: &BTreeMap<NaiveDate, DailyTotal>, today: NaiveDate) -> Trends {
+    let last_7_days = window(days, today, 7);
+    let previous_7_days = window(days, today - Days::new(7), 7);
+
+    let daily = (0..30)
+        .rev()
+        .map(|days_ago| {
+            let date = today - Days::new(days_ago);
+            TrendPoint {
+                date,
+                minutes: days.get(&date).map_or(0, |day| day.minutes),
+                rolling_7d_minutes: window(days, date, 7).minutes_per_day,
+                day_off: None,
+            }
+        })
+        .collect();
+
+    Trends {
+        last_7_days,
+        last_30_days: window(days, today, 30),
+        week_over_week: WeekOverWeek {
+            minutes_per_day: last_7_days.minutes_per_day - previous_7_days.minutes_per_day,
+            sessions: last_7_days.sessions - previous_7_days.sessions,
+            completion_rate: last_7_days
+                .completion_rate
+                .zip(previous_7_days.completion_rate)
+                .map(|(last, previous)| last - previous),
+        },
+        daily,
+    }
+}
+
+#[tauri::command]
+pub fn get_trends(app: AppHandle) -> Trends {
+    let mut trends = trends(&load_days(&app), Utc::now().date_naive());
+    let days_off = days_off::read(&app);
+    for point in &mut trends.daily {
+        point.day_off = days_off.get(&point.date).cloned();
+    }
+    trends
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Range {
+    Today,
+    Week,
+    Month,
+    All,
+}
+
+impl Range {
+    // Same windows as the stats: the UTC day and ISO week
+    pub fn contains(self, date: NaiveDate, today: NaiveDate) -> bool {
+        match self {
+            Range::Today => date == today,
+            Range::Week => date.iso_week() == today.iso_week(),
+            Range::Month => date.year() == today.year() && date.month() == today.month(),
+            Range::All => true,
+        }
+    }
+}
+
+// Sessions more than the long break plus this apart start a new block
+const BLOCK_SLACK_MINUTES: i64 = 10;
+const BLOCK_HOURS: [i32; 4] = [1, 2, 3, 4];
+
+#[derive(Serialize, Debug)]
+pub struct DeepWorkBlock {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub sessions: i32,
+    // Focused minutes, the breaks in between left out
+    pub minutes: i32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BlockCount {
+    pub hours: i32,
+    pub blocks: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DeepWork {
+    pub blocks: Vec<DeepWorkBlock>,
+    pub longest_minutes: i32,
+    // Blocks of at least 1, 2, 3 and 4 hours: "3 blocks of 2+ hours"
+    pub at_least: Vec<BlockCount>,
+}
+
+fn deep_work(records: &[SessionRecord], max_gap: chrono::Duration) -> DeepWork {
+    let mut sessions: Vec<_> = completed_work(records).collect();
+    sessions.sort_by_key(|record| record.start);
+
+    let mut blocks: Vec<DeepWorkBlock> = Vec::new();
+    for record in sessions {
+        match blocks.last_mut() {
+            Some(block) if record.start - block.end <= max_gap => {
+                block.end = block.end.max(record.end);
+                block.sessions += 1;
+                block.minutes += record.minutes;
+            }
+            _ => blocks.push(DeepWorkBlock {
+                start: record.start,
+                end: record.end,
+                sessions: 1,
+                minutes: record.minutes,
+            }),
+        }
+    }
+
+    DeepWork {
+        longest_minutes: blocks.iter().map(|block| block.minutes).max().unwrap_or(0),
+        at_least: BLOCK_HOURS
+            .iter()
+            .map(|&hours| BlockCount {
+                hours,
+                blocks: blocks
+                    .iter()
+                    .filter(|block| block.minutes >= hours * 60)
+                    .count(),
+            })
+            .collect(),
+        blocks,
+    }
+}
+
+// Back-to-back completed Work sessions, only breaks in between, as blocks
+#[tauri::command]
+pub fn get_deep_work_blocks(range: Range, app: AppHandle) -> DeepWork {
+    let today = Utc::now().date_naive();
+    let records: Vec<_> = load(&app)
+        .into_iter()
+        .filter(|record| range.contains(record.end.date_naive(), today))
+        .collect();
+    let long_break = read_settings(&app).long_break_time as i64;
+    deep_work(
+        &records,
+        chrono::Duration::minutes(long_break + BLOCK_SLACK_MINUTES),
+    )
+}
+
+// Last score sent with `focus-score`, so it's only emitted when it changes
+#[derive(Default)]
+pub struct LastFocusScore(Mutex<Option<DayScore>>);
+
+#[tauri::command]
+pub fn get_focus_score(range: Range, app: AppHandle) -> Vec<DayScore> {
+    let today = Utc::now().date_naive();
+    let days = load_days(&app);
+    focus::scores(days.values().filter(|day| range.contains(day.date, today)))
+}
+
+// After every phase change, today's score as it stands
+pub fn publish_focus_score(app: &AppHandle) {
+    let today = Utc::now().date_naive();
+    let score = focus::scores(load_days(app).get(&today)).pop();
+    let last = app.state::<LastFocusScore>();
+    let mut last = last.0.lock().unwrap();
+    if *last != score {
+        events::emit(app, "focus-score", &score);
+        *last = score;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    // A completed Work session of `minutes` ending at `end`, e.g. "2024-03-03T12:00:00Z"
+    fn work(end: &str, minutes: i32) -> SessionRecord {
+        SessionRecord::ended(TimePhase::Work, end, minutes)
+    }
+
+    fn skipped(record: SessionRecord) -> SessionRecord {
+        SessionRecord {
+            completed: false,
+            ..record
+        }
+    }
+
+    fn short_break(end: &str) -> SessionRecord {
+        SessionRecord {
+            phase: TimePhase::ShortBreak,
+            ..work(end, 5)
+        }
+    }
+
+    fn days(records: &[SessionRecord]) -> BTreeMap<NaiveDate, DailyTotal> {
+        history::daily_totals(records, &[])
+    }
+
+    fn day(heatmap: &Heatmap, week: usize, weekday: usize) -> &HeatmapDay {
+        heatmap.weeks[week][weekday].as_ref().unwrap()
+    }
+
+    #[test]
+    fn heatmap_counts_sessions_on_the_utc_day_they_end() {
+        // Saturday 2 March to Tuesday 5 March 2024
+        let records = [
+            work("2024-03-02T23:59:00Z", 25),
+            // Started on Saturday, ended on Sunday
+            work("2024-03-03T00:15:00Z", 25),
+            work("2024-03-03T12:00:00Z", 50),
+            skipped(work("2024-03-04T09:00:00Z", 10)),
+            short_break("2024-03-04T09:30:00Z"),
+        ];
+        let heatmap = heatmap(&days(&records), date("2024-02-28"), date("2024-03-05"));
+
+        // Weeks of 25 February and 3 March, Sunday first
+        assert_eq!(heatmap.weeks.len(), 2);
+        assert!(heatmap.weeks[0][..3].iter().all(Option::is_none));
+        assert!(heatmap.weeks[1][3..].iter().all(Option::is_none));
+
+        assert_eq!(day(&heatmap, 0, 3).date, date("2024-02-28"));
+        let saturday = day(&heatmap, 0, 6);
+        assert_eq!((saturday.sessions, saturday.minutes), (1, 25));
+        let sunday = day(&heatmap, 1, 0);
+        assert_eq!(
+            (sunday.date, sunday.sessions, sunday.minutes),
+            (date("2024-03-03"), 2, 75)
+        );
+        // Skipped Work and breaks don't count
+        assert_eq!(day(&heatmap, 1, 1).sessions, 0);
+
+        assert_eq!(heatmap.total_sessions, 3);
+        assert_eq!(heatmap.max_sessions, 2);
+        let levels: Vec<i32> = [(0, 3), (0, 6), (1, 0), (1, 1)]
+            .iter()
+            .map(|&(week, weekday)| day(&heatmap, week, weekday).level)
+            .collect();
+        assert_eq!(levels, [0, 2, 4, 0]);
+    }
+
+    #[test]
+    fn heatmap_without_sessions_is_all_level_0() {
+        let heatmap = heatmap(&days(&[]), date("2024-01-01"), date("2024-12-31"));
+        assert_eq!(heatmap.max_sessions, 0);
+        assert_eq!(heatmap.weeks.len(), 53);
+        let days: Vec<_> = heatmap.weeks.iter().flatten().flatten().collect();
+        assert_eq!(days.len(), 366);
+        assert!(days.iter().all(|day| day.level == 0));
+    }
+
+    fn trend_records() -> Vec<SessionRecord> {
+        vec![
+            // Wednesday 13 March back to the week before
+            work("2024-03-13T10:00:00Z", 25),
+            work("2024-03-12T10:00:00Z", 25),
+            // Started on Sunday, ended on Monday
+            work("2024-03-11T00:10:00Z", 25),
+            work("2024-03-09T10:00:00Z", 25),
+            work("2024-03-04T10:00:00Z", 50),
+            skipped(work("2024-03-04T11:00:00Z", 10)),
+        ]
+    }
+
+    #[test]
+    fn trends_compare_the_last_7_days_with_the_7_before() {
+        let trends = trends(&days(&trend_records()), date("2024-03-13"));
+
+        assert_eq!(trends.last_7_days.sessions, 4);
+        assert_eq!(trends.last_7_days.minutes_per_day, 100.0 / 7.0);
+        assert_eq!(trends.last_7_days.completion_rate, Some(1.0));
+        assert_eq!(trends.last_30_days.sessions, 5);
+        assert_eq!(trends.last_30_days.completion_rate, Some(5.0 / 6.0));
+
+        // 4 March to 6 March: one of two sessions completed
+        assert_eq!(trends.week_over_week.sessions, 3);
+        assert_eq!(
+            trends.week_over_week.minutes_per_day,
+            100.0 / 7.0 - 50.0 / 7.0
+        );
+        assert_eq!(trends.week_over_week.completion_rate, Some(0.5));
+
+        assert_eq!(trends.daily.len(), 30);
+        assert_eq!(trends.daily[0].date, date("2024-02-13"));
+        let monday = &trends.daily[27];
+        assert_eq!((monday.date, monday.minutes), (date("2024-03-11"), 25));
+        assert_eq!(trends.daily[26].minutes, 0);
+        assert_eq!(trends.daily[29].rolling_7d_minutes, 100.0 / 7.0);
+    }
+
+    #[test]
+    fn trends_without_sessions_have_no_completion_rate() {
+        let trends = trends(&days(&[]), date("2024-03-13"));
+        assert_eq!(trends.last_7_days.completion_rate, None);
+        assert_eq!(trends.week_over_week.completion_rate, None);
+        assert!(trends.daily.iter().all(|point| point.minutes == 0));
+    }
+
+    #[test]
+    fn trends_count_pruned_days_from_their_totals() {
+        let pruned = DailyTotal {
+            sessions: 2,
+            minutes: 50,
+            started: 4,
+            ..DailyTotal::new(date("2024-03-04"))
+        };
+        let days = history::daily_totals(&trend_records()[..5], &[pruned]);
+        let trends = trends(&days, date("2024-03-13"));
+
+        assert_eq!(trends.last_30_days.sessions, 7);
+        assert_eq!(trends.last_30_days.completion_rate, Some(7.0 / 9.0));
+        assert_eq!(trends.daily[20].minutes, 100);
+    }
+
+    #[test]
+    fn deep_work_joins_sessions_up_to_the_gap() {
+        let records = [
+            work("2024-03-04T02:25:00Z", 25),
+            skipped(work("2024-03-04T01:10:00Z", 10)),
+            // exactly 30 minutes after the one before, across midnight
+            work("2024-03-04T00:50:00Z", 25),
+            work("2024-03-03T23:55:00Z", 25),
+            work("2024-03-03T23:25:00Z", 25),
+        ];
+        let deep_work = deep_work(&records, chrono::Duration::minutes(30));
+
+        let blocks: Vec<_> = deep_work
+            .blocks
+            .iter()
+            .map(|block| {
+                (
+                    block.start.to_rfc3339(),
+                    block.end.to_rfc3339(),
+                    block.sessions,
+                    block.minutes,
+                )
+            })
+            .collect();
+        assert_eq!(
+            blocks,
+            [
+                (
+                    "2024-03-03T23:00:00+00:00".to_string(),
+                    "2024-03-04T00:50:00+00:00".to_string(),
+                    3,
+                    75
+                ),
+                (
+                    "2024-03-04T02:00:00+00:00".to_string(),
+                    "2024-03-04T02:25:00+00:00".to_string(),
+                    1,
+                    25
+                ),
+            ]
+        );
+        assert_eq!(deep_work.longest_minutes, 75);
+        let at_least: Vec<_> = deep_work
+            .at_least
+            .iter()
+            .map(|count| (count.hours, count.blocks))
+            .collect();
+        assert_eq!(at_least, [(1, 1), (2, 0), (3, 0), (4, 0)]);
+    }
+
+    #[test]
+    fn deep_work_without_sessions_is_empty() {
+        let deep_work = deep_work(
+            &[short_break("2024-03-04T10:00:00Z")],
+            chrono::Duration::minutes(30),
+        );
+        assert!(deep_work.blocks.is_empty());
+        assert_eq!(deep_work.longest_minutes, 0);
+        assert!(deep_work.at_least.iter().all(|count| count.blocks == 0));
+    }
+}
@@ -0,0 +1,94 @@
+use serde::Serialize;
+use std::{
+    ops::Range,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{events, get_from_store, get_remaining, with_store, SessionNumber, Stats, TimePhase};
+
+// Minimum gap between polite announcements, so screen readers aren't flooded
+const POLITE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    // Interrupts whatever is being read, used for phase changes
+    Assertive,
+    // Waits its turn and is dropped when announcements come too fast
+    Polite,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Announcement {
+    pub text: String,
+    pub priority: Priority,
+}
+
+#[derive(Default)]
+pub struct Announcer(Mutex<Option<Instant>>);
+
+pub fn announce(app: &AppHandle, text: String, priority: Priority) {
+    let announcer = app.state::<Announcer>();
+    let mut last = announcer.0.lock().unwrap();
+    let now = Instant::now();
+
+    if priority == Priority::Polite
+        && last.map_or(false, |last| now.duration_since(last) < POLITE_INTERVAL)
+    {
+        return;
+    }
+    *last = Some(now);
+    events::emit_once(app, "state-announcement", Announcement { text, priority });
+}
+
+fn plural(count: i32, word: &str) -> String {
+    if count == 1 {
+        format!("{count} {word}")
+    } else {
+        format!("{count} {word}s")
+    }
+}
+
+pub fn on_phase_change(app: &AppHandle, phase: TimePhase) {
+    let session_number = *app.state::<SessionNumber>().0.lock().unwrap();
+    let mut minutes = 0;
+    let mut sessions_today = 0;
+    with_store(app, |store| {
+        minutes = get_remaining(app, store).unwrap_or_default();
+        if let Ok(stats) = get_from_store::<Stats>(store, "stats") {
+            sessions_today = stats.today.sessions;
+        }
+        Ok(())
+    });
+
+    let text = match phase {
+        TimePhase::Work => format!(
+            "Work session {} started. {} to go.",
+            session_number / 2 + 1,
+            plural(minutes, "minute")
+        ),
+        TimePhase::ShortBreak | TimePhase::LongBreak => format!(
+            "{} started, {} to relax. {} completed today.",
+            phase.label(),
+            plural(minutes, "minute"),
+            plural(sessions_today, "session")
+        ),
+    };
+    announce(app, text, Priority::Assertive);
+}
+
+// Called by the backend timer with the seconds left it counted past
+pub fn on_tick(app: &AppHandle, phase: TimePhase, passed: &Range<i32>) {
+    for (secs, left) in [(300, "5 minutes"), (60, "1 minute")] {
+        if !passed.contains(&secs) {
+            continue;
+        }
+        let text = match phase {
+            TimePhase::Work => format!("{left} left in this work session."),
+            _ => format!("{left} left in this break."),
+        };
+        announce(app, text, Priority::Polite);
+    }
+}
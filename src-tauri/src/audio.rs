@@ -0,0 +1,523 @@
+#[cfg(feature = "audio")]
+use rodio::{source::SineWave, Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        mpsc::{channel, Sender},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+#[cfg(feature = "audio")]
+use std::{fs::File, io::BufReader, sync::mpsc::RecvTimeoutError, time::Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    modify_settings, power, presentation, read_settings, silent, volume, Error, TimePhase,
+};
+
+pub const SOUND_DIR: &str = "sounds";
+const SOUND_EXTENSIONS: [&str; 4] = ["wav", "mp3", "ogg", "flac"];
+const MAX_SOUND_BYTES: u64 = 10 * 1024 * 1024;
+
+// Ambient fades out over the last seconds of Work and comes back when Work resumes
+const WORK_FADE_OUT_SECS: i32 = 10;
+const WORK_FADE_IN: Duration = Duration::from_secs(3);
+// Skipping straight to a break still shouldn't cut the sound abruptly
+const BREAK_FADE_OUT: Duration = Duration::from_secs(1);
+#[cfg(feature = "audio")]
+const FADE_STEP: Duration = Duration::from_millis(50);
+// Two descending tones, for alerts that have no sound file to play
+#[cfg(feature = "audio")]
+const CHIME_TONES: [f32; 2] = [880.0, 660.0];
+#[cfg(feature = "audio")]
+const CHIME_TONE: Duration = Duration::from_millis(180);
+#[cfg(feature = "audio")]
+const CHIME_GAIN: f32 = 0.25;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundEvent {
+    WorkStart,
+    BreakStart,
+    LastMinute,
+    SessionComplete,
+}
+
+impl SoundEvent {
+    fn file_stem(&self) -> &'static str {
+        match self {
+            SoundEvent::WorkStart => "work-start",
+            SoundEvent::BreakStart => "break-start",
+            SoundEvent::LastMinute => "last-minute",
+            SoundEvent::SessionComplete => "session-complete",
+        }
+    }
+}
+
+// User-picked sound files, already copied into the app data dir
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NotificationSounds {
+    pub work_start: Option<String>,
+    pub break_start: Option<String>,
+    pub last_minute: Option<String>,
+    // Part of the celebration when a Work session completes
+    pub session_complete: Option<String>,
+}
+
+impl NotificationSounds {
+    fn get(&self, event: SoundEvent) -> Option<&String> {
+        match event {
+            SoundEvent::WorkStart => self.work_start.as_ref(),
+            SoundEvent::BreakStart => self.break_start.as_ref(),
+            SoundEvent::LastMinute => self.last_minute.as_ref(),
+            SoundEvent::SessionComplete => self.session_complete.as_ref(),
+        }
+    }
+
+    fn set(&mut self, event: SoundEvent, path: Option<String>) {
+        match event {
+            SoundEvent::WorkStart => self.work_start = path,
+            SoundEvent::BreakStart => self.break_start = path,
+            SoundEvent::LastMinute => self.last_minute = path,
+            SoundEvent::SessionComplete => self.session_complete = path,
+        }
+    }
+}
+
+// Looping background sound played during Work
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AmbientSound {
+    pub path: Option<String>,
+    pub volume: f32,
+}
+
+impl Default for AmbientSound {
+    fn default() -> Self {
+        Self {
+            path: None,
+            volume: 0.5,
+        }
+    }
+}
+
+// Briefly lower the system volume while a phase-change sound plays
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Ducking {
+    pub enabled: bool,
+    // Fraction of the current volume other audio is ducked to
+    pub level: f32,
+    pub duration_ms: u64,
+}
+
+impl Default for Ducking {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: 0.4,
+            duration_ms: 2500,
+        }
+    }
+}
+
+// Set while the system volume is ducked, so overlapping chimes don't restore the ducked level
+static DUCKED: AtomicBool = AtomicBool::new(false);
+
+// Built without audio they're sent nowhere, so nothing reads them
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+enum AudioCommand {
+    // Sound file and the gain it's played at
+    Play(PathBuf, f32),
+    SetAmbient(Option<PathBuf>, f32),
+    // Fade the ambient sound to a fraction of its configured volume
+    FadeAmbient { to: f32, over: Duration },
+    Chime,
+}
+
+#[cfg(feature = "audio")]
+struct Fade {
+    from: f32,
+    to: f32,
+    started: Instant,
+    duration: Duration,
+}
+
+#[cfg(feature = "audio")]
+struct Ambient {
+    sink: Sink,
+    path: PathBuf,
+    volume: f32,
+    level: f32,
+    fade: Option<Fade>,
+}
+
+#[cfg(feature = "audio")]
+impl Ambient {
+    fn start(
+        handle: &OutputStreamHandle,
+        path: PathBuf,
+        volume: f32,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let sink = Sink::try_new(handle)?;
+        let source = Decoder::new(BufReader::new(File::open(&path)?))?;
+        sink.append(source.buffered().repeat_infinite());
+        // Starts silent, the next fade brings it in
+        sink.set_volume(0.0);
+        sink.pause();
+        Ok(Self {
+            sink,
+            path,
+            volume,
+            level: 0.0,
+            fade: None,
+        })
+    }
+
+    fn fade_to(&mut self, to: f32, over: Duration) {
+        if to > 0.0 {
+            self.sink.play();
+        }
+        self.fade = Some(Fade {
+            from: self.level,
+            to,
+            started: Instant::now(),
+            duration: over,
+        });
+    }
+
+    fn step(&mut self) {
+        let Some(fade) = &self.fade else {
+            return;
+        };
+        let progress = if fade.duration.is_zero() {
+            1.0
+        } else {
+            (fade.started.elapsed().as_secs_f32() / fade.duration.as_secs_f32()).min(1.0)
+        };
+        self.level = fade.from + (fade.to - fade.from) * progress;
+        self.sink.set_volume(self.level * self.volume);
+
+        if progress >= 1.0 {
+            if self.level <= 0.0 {
+                self.sink.pause();
+            }
+            self.fade = None;
+        }
+    }
+}
+
+// rodio's output stream isn't Send, so it lives on its own thread. Started
+// by the first sound, until then the output device is left alone.
+#[derive(Default)]
+pub struct Audio(Mutex<Option<Sender<AudioCommand>>>);
+
+// Built without audio, nothing is listening
+#[cfg(not(feature = "audio"))]
+impl Audio {
+    fn spawn() -> Sender<AudioCommand> {
+        channel().0
+    }
+}
+
+#[cfg(feature = "audio")]
+impl Audio {
+    fn spawn() -> Sender<AudioCommand> {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let (_stream, handle) = match OutputStream::try_default() {
+                Ok(output) => output,
+                Err(err) => {
+                    eprintln!("No audio output available: {err}");
+                    return;
+                }
+            };
+
+            let mut ambient: Option<Ambient> = None;
+            loop {
+                // Wake up often only while a fade is in progress
+                let fading = ambient
+                    .as_ref()
+                    .map_or(false, |ambient| ambient.fade.is_some());
+                let timeout = if fading {
+                    FADE_STEP
+                } else {
+                    Duration::from_secs(3600)
+                };
+
+                match receiver.recv_timeout(timeout) {
+                    Ok(AudioCommand::Play(path, gain)) => {
+                        if let Err(err) = play_file(&handle, &path, gain) {
+                            eprintln!("Failed to play {}: {err}", path.display());
+                        }
+                    }
+                    Ok(AudioCommand::SetAmbient(path, volume)) => match path {
+                        Some(path)
+                            if ambient
+                                .as_ref()
+                                .map_or(true, |ambient| ambient.path != path) =>
+                        {
+                            ambient = match Ambient::start(&handle, path.clone(), volume) {
+                                Ok(ambient) => Some(ambient),
+                                Err(err) => {
+                                    eprintln!("Failed to play {}: {err}", path.display());
+                                    None
+                                }
+                            };
+                        }
+                        Some(_) => {
+                            if let Some(ambient) = ambient.as_mut() {
+                                ambient.volume = volume;
+                            }
+                        }
+                        None => ambient = None,
+                    },
+                    Ok(AudioCommand::FadeAmbient { to, over }) => {
+                        if let Some(ambient) = ambient.as_mut() {
+                            ambient.fade_to(to, over);
+                        }
+                    }
+                    Ok(AudioCommand::Chime) => {
+                        if let Err(err) = play_chime(&handle) {
+                            eprintln!("Failed to play the chime: {err}");
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                if let Some(ambient) = ambient.as_mut() {
+                    ambient.step();
+                }
+            }
+        });
+        sender
+    }
+}
+
+#[cfg(feature = "audio")]
+fn play_file(
+    handle: &OutputStreamHandle,
+    path: &Path,
+    gain: f32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sink = Sink::try_new(handle)?;
+    sink.set_volume(gain);
+    sink.append(Decoder::new(BufReader::new(File::open(path)?))?);
+    sink.detach();
+    Ok(())
+}
+
+#[cfg(feature = "audio")]
+fn play_chime(handle: &OutputStreamHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let sink = Sink::try_new(handle)?;
+    sink.set_volume(CHIME_GAIN);
+    for tone in CHIME_TONES {
+        sink.append(SineWave::new(tone).take_duration(CHIME_TONE));
+    }
+    sink.detach();
+    Ok(())
+}
+
+fn send(app: &AppHandle, command: AudioCommand) {
+    // Nothing chimes into a presentation, a call or silent mode. The
+    // ambient sound was asked for, silent mode leaves it playing.
+    let one_shot = matches!(command, AudioCommand::Play(..) | AudioCommand::Chime);
+    if one_shot && (presentation::active(app) || silent::active(app)) {
+        return;
+    }
+    let audio = app.state::<Audio>();
+    let mut sender = audio.0.lock().unwrap();
+    // Stopping or fading a sound that never started needs no thread
+    let idle = matches!(
+        command,
+        AudioCommand::SetAmbient(None, _) | AudioCommand::FadeAmbient { .. }
+    );
+    if sender.is_none() && idle {
+        return;
+    }
+    let _ = sender.get_or_insert_with(Audio::spawn).send(command);
+}
+
+pub fn play(app: &AppHandle, path: PathBuf) {
+    send(app, AudioCommand::Play(path, 1.0));
+}
+
+// Play over ducked system audio. Our own gain is raised by the same
+// factor, so the chime keeps its loudness while everything else drops.
+fn play_ducked(app: &AppHandle, path: PathBuf, ducking: Ducking) {
+    if DUCKED.swap(true, Ordering::SeqCst) {
+        play(app, path);
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let level = ducking.level.clamp(0.05, 1.0);
+        let Some(previous) = volume::output_volume() else {
+            eprintln!("Couldn't read the output volume, playing without ducking");
+            DUCKED.store(false, Ordering::SeqCst);
+            play(&app, path);
+            return;
+        };
+
+        volume::set_output_volume(previous * level);
+        send(&app, AudioCommand::Play(path, 1.0 / level));
+        thread::sleep(Duration::from_millis(ducking.duration_ms));
+        volume::set_output_volume(previous);
+        DUCKED.store(false, Ordering::SeqCst);
+    });
+}
+
+pub fn play_event(app: &AppHandle, event: SoundEvent) {
+    if let Some(path) = read_settings(app).sounds.get(event) {
+        play(app, PathBuf::from(path));
+    }
+}
+
+// Makes sure a phase change is heard when it can't be seen. A configured
+// phase sound already plays from `on_phase_change`, so the chime only stands
+// in when there's none.
+pub fn play_alert(app: &AppHandle, sounds: &NotificationSounds, phase: TimePhase) {
+    let event = match phase {
+        TimePhase::Work => SoundEvent::WorkStart,
+        TimePhase::ShortBreak | TimePhase::LongBreak => SoundEvent::BreakStart,
+    };
+    if sounds.get(event).is_none() {
+        send(app, AudioCommand::Chime);
+    }
+}
+
+fn validate_sound(source: &Path) -> Result<String, Error> {
+    let extension = source
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+        .filter(|extension| SOUND_EXTENSIONS.contains(&extension.as_str()))
+        .ok_or_else(|| {
+            Error::InvalidSound(format!(
+                "Supported formats: {}",
+                SOUND_EXTENSIONS.join(", ")
+            ))
+        })?;
+
+    let metadata = fs::metadata(source)?;
+    if !metadata.is_file() {
+        return Err(Error::InvalidSound("Not a file".into()));
+    }
+    if metadata.len() > MAX_SOUND_BYTES {
+        return Err(Error::InvalidSound(
+            "Sound files are limited to 10 MB".into(),
+        ));
+    }
+
+    // Make sure we can actually decode it before accepting it
+    #[cfg(feature = "audio")]
+    Decoder::new(BufReader::new(File::open(source)?))
+        .map_err(|err| Error::InvalidSound(err.to_string()))?;
+    Ok(extension)
+}
+
+// Copy a user-picked sound into the app data dir and use it for `event`
+#[tauri::command]
+pub fn set_notification_sound(
+    event: SoundEvent,
+    path: Option<String>,
+    app: AppHandle,
+) -> Result<Option<String>, Error> {
+    let stored = match path {
+        Some(path) => {
+            let source = PathBuf::from(path);
+            let extension = validate_sound(&source)?;
+
+            let dir = app
+                .path_resolver()
+                .app_data_dir()
+                .ok_or_else(|| Error::InvalidSound("No app data directory".into()))?
+                .join(SOUND_DIR);
+            fs::create_dir_all(&dir)?;
+
+            let target = dir.join(format!("{}.{extension}", event.file_stem()));
+            fs::copy(&source, &target)?;
+            Some(target.to_string_lossy().into_owned())
+        }
+        None => None,
+    };
+
+    modify_settings(&app, |settings| settings.sounds.set(event, stored.clone()))?;
+    Ok(stored)
+}
+
+pub fn on_phase_change(app: &AppHandle, phase: TimePhase) {
+    let settings = read_settings(app);
+    let event = match phase {
+        TimePhase::Work => SoundEvent::WorkStart,
+        TimePhase::ShortBreak | TimePhase::LongBreak => SoundEvent::BreakStart,
+    };
+    if let Some(path) = settings.sounds.get(event) {
+        if settings.ducking.enabled {
+            play_ducked(app, PathBuf::from(path), settings.ducking.clone());
+        } else {
+            play(app, PathBuf::from(path));
+        }
+    }
+
+    set_ambient(app, settings.ambient);
+    let fade = match phase {
+        TimePhase::Work => AudioCommand::FadeAmbient {
+            to: 1.0,
+            over: WORK_FADE_IN,
+        },
+        _ => AudioCommand::FadeAmbient {
+            to: 0.0,
+            over: BREAK_FADE_OUT,
+        },
+    };
+    send(app, fade);
+}
+
+// No ambient sound while saving battery or presenting
+fn set_ambient(app: &AppHandle, ambient: AmbientSound) {
+    let path = ambient
+        .path
+        .filter(|_| !power::low_power(app) && !presentation::active(app))
+        .map(PathBuf::from);
+    send(app, AudioCommand::SetAmbient(path, ambient.volume));
+}
+
+// Picks the ambient sound back up, or stops it, in the middle of Work
+pub fn refresh_ambient(app: &AppHandle) {
+    set_ambient(app, read_settings(app).ambient);
+    send(
+        app,
+        AudioCommand::FadeAmbient {
+            to: 1.0,
+            over: WORK_FADE_IN,
+        },
+    );
+}
+
+// With the seconds left the timer counted past, it may have slept through several
+pub fn on_tick(app: &AppHandle, phase: TimePhase, passed: &Range<i32>) {
+    if passed.contains(&60) {
+        play_event(app, SoundEvent::LastMinute);
+    }
+    if phase == TimePhase::Work && passed.contains(&WORK_FADE_OUT_SECS) {
+        // Over what's left of Work, less than usual after a sleep
+        let left = passed.start.clamp(0, WORK_FADE_OUT_SECS);
+        send(
+            app,
+            AudioCommand::FadeAmbient {
+                to: 0.0,
+                over: Duration::from_secs(left as u64),
+            },
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{from_value, json, Map, Value};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::Store;
+
+use crate::{config::merge, events, history::SessionRecord, Settings, Stat, Stats, WindowMode};
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Repair {
+    // Dotted path of what was fixed, e.g. "settings.work_time"
+    pub key: String,
+    pub fix: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct DataRepaired {
+    repairs: Vec<Repair>,
+}
+
+fn repair(key: impl Into<String>, fix: impl Into<String>) -> Repair {
+    Repair {
+        key: key.into(),
+        fix: fix.into(),
+    }
+}
+
+fn fits<T: DeserializeOwned>(value: &Value) -> bool {
+    from_value::<T>(value.clone()).is_ok()
+}
+
+fn nested(path: &[String], value: Value) -> Value {
+    path.iter().rev().fold(value, |value, key| {
+        Value::Object(Map::from_iter([(key.clone(), value)]))
+    })
+}
+
+// Drops what keeps `fields` (at `path` under `root`) from deserializing as
+// `T`, going as deep as it can so one bad field doesn't take its neighbours
+// with it. `#[serde(default)]` fills the dropped ones back in.
+fn prune<T: DeserializeOwned>(
+    root: &Value,
+    path: &[String],
+    fields: &mut Map<String, Value>,
+    dropped: &mut Vec<String>,
+) {
+    let keys: Vec<String> = fields.keys().cloned().collect();
+    for key in keys {
+        let path: Vec<String> = path.iter().cloned().chain([key.clone()]).collect();
+        let fits_alone = |value: &Value| {
+            let mut check = root.clone();
+            merge(&mut check, &nested(&path, value.clone()));
+            fits::<T>(&check)
+        };
+        if fits_alone(&fields[&key]) {
+            continue;
+        }
+        if let Some(Value::Object(inner)) = fields.get_mut(&key) {
+            prune::<T>(root, &path, inner, dropped);
+            if fits_alone(&fields[&key]) {
+                continue;
+            }
+        }
+        fields.remove(&key);
+        dropped.push(path.join("."));
+    }
+}
+
+fn repair_settings(value: &mut Value, repairs: &mut Vec<Repair>) {
+    if fits::<Settings>(value) {
+        return;
+    }
+    let defaults = json!(Settings::default());
+    if let Value::Object(fields) = value {
+        let mut dropped = Vec::new();
+        prune::<Settings>(&defaults, &[], fields, &mut dropped);
+        for path in dropped {
+            repairs.push(repair(
+                format!("settings.{path}"),
+                "invalid, back to the default",
+            ));
+        }
+    }
+    // Not an object, or fields that only clash with each other
+    if !fits::<Settings>(value) {
+        *value = defaults;
+        repairs.push(repair("settings", "unreadable, reset to the defaults"));
+    }
+}
+
+fn repair_stats(value: &mut Value, repairs: &mut Vec<Repair>) {
+    let mut stats = Stats::default();
+    let slots = [
+        ("today", &mut stats.today),
+        ("week", &mut stats.week),
+        ("total", &mut stats.total),
+    ];
+    for (name, slot) in slots {
+        match value
+            .get(name)
+            .and_then(|stat| from_value::<Stat>(stat.clone()).ok())
+        {
+            Some(stat) if stat.minutes < 0 || stat.sessions < 0 => {
+                slot.minutes = stat.minutes.max(0);
+                slot.sessions = stat.sessions.max(0);
+                repairs.push(repair(
+                    format!("stats.{name}"),
+                    "negative counts set to zero",
+                ));
+            }
+            Some(stat) => *slot = stat,
+            None => repairs.push(repair(format!("stats.{name}"), "unreadable, reset to zero")),
+        }
+    }
+    *value = json!(stats);
+}
+
+fn repair_history(value: &mut Value, repairs: &mut Vec<Repair>) {
+    let Value::Array(records) = value else {
+        *value = json!([]);
+        repairs.push(repair("history", "not a list, cleared"));
+        return;
+    };
+    let before = records.len();
+    records.retain(fits::<SessionRecord>);
+    let dropped = before - records.len();
+    if dropped > 0 {
+        repairs.push(repair(
+            "history",
+            format!("dropped {dropped} unreadable sessions"),
+        ));
+    }
+}
+
+fn repair_count(key: &str, value: &mut Value, repairs: &mut Vec<Repair>) {
+    if !matches!(value.as_i64(), Some(count) if (0..=i32::MAX as i64).contains(&count)) {
+        *value = json!(0);
+        repairs.push(repair(key, "not a count, reset to 0"));
+    }
+}
+
+fn repair_window_mode(value: &mut Value, repairs: &mut Vec<Repair>) {
+    if !fits::<WindowMode>(value) {
+        *value = json!(WindowMode::default());
+        repairs.push(repair("window_mode", "unknown, back to the default"));
+    }
+}
+
+// Checks what the rest of the app reads with `get_from_store` against the
+// current types and fixes it in place. Missing keys are left to the
+// store's defaults. Runs at launch before anything else reads the store.
+pub fn repair_store(store: &mut Store<Wry>) -> Vec<Repair> {
+    let mut repairs = Vec::new();
+    for key in [
+        "settings",
+        "stats",
+        "history",
+        "session_number",
+        "break_debt",
+        "window_mode",
+    ] {
+        let Some(mut value) = store.get(key).cloned() else {
+            continue;
+        };
+        let before = repairs.len();
+        match key {
+            "settings" => repair_settings(&mut value, &mut repairs),
+            "stats" => repair_stats(&mut value, &mut repairs),
+            "history" => repair_history(&mut value, &mut repairs),
+            "window_mode" => repair_window_mode(&mut value, &mut repairs),
+            _ => repair_count(key, &mut value, &mut repairs),
+        }
+        if repairs.len() > before {
+            if let Err(err) = store.insert(key.into(), value) {
+                eprintln!("Couldn't store the repaired {key}: {err}");
+            }
+        }
+    }
+    for fixed in &repairs {
+        eprintln!("Repaired {}: {}", fixed.key, fixed.fix);
+    }
+    repairs
+}
+
+// Replayed, so a page loading later still hears about it
+pub fn report(app: &AppHandle, repairs: Vec<Repair>) {
+    if !repairs.is_empty() {
+        events::emit(app, "data-repaired", DataRepaired { repairs });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimePhase;
+
+    #[test]
+    fn repairs_keep_what_is_valid() {
+        let mut repairs = Vec::new();
+        let mut settings = json!({
+            "work_time": "long",
+            "short_break_time": 7,
+            "low_battery": { "enabled": true, "threshold_percent": "low" },
+        });
+        repair_settings(&mut settings, &mut repairs);
+        let settings: Settings = from_value(settings).unwrap();
+        assert_eq!(settings.work_time, Settings::default().work_time);
+        assert_eq!(settings.short_break_time, 7);
+        assert!(settings.low_battery.enabled);
+
+        let mut stats = json!({
+            "today": { "minutes": 50, "sessions": 2 },
+            "week": { "minutes": -5, "sessions": 1 },
+            "total": "lots",
+        });
+        repair_stats(&mut stats, &mut repairs);
+        let stats: Stats = from_value(stats).unwrap();
+        assert_eq!(
+            (
+                stats.today.minutes,
+                stats.week.minutes,
+                stats.total.sessions
+            ),
+            (50, 0, 0)
+        );
+
+        let mut history = json!([
+            SessionRecord::ended(TimePhase::Work, "2024-03-04T09:25:00Z", 25),
+            { "start": "yesterday" },
+        ]);
+        repair_history(&mut history, &mut repairs);
+        assert_eq!(history.as_array().unwrap().len(), 1);
+
+        let keys: Vec<&str> = repairs.iter().map(|repair| repair.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "settings.low_battery.threshold_percent",
+                "settings.work_time",
+                "stats.week",
+                "stats.total",
+                "history",
+            ]
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use auto_launch::{AutoLaunch, AutoLaunchBuilder};
+use tauri::AppHandle;
+
+use crate::Error;
+
+// Login item on macOS, registry Run key on Windows, XDG autostart entry on Linux
+fn launcher(app: &AppHandle) -> Result<AutoLaunch, Error> {
+    let path = std::env::current_exe()?;
+    Ok(AutoLaunchBuilder::new()
+        .set_app_name(&app.package_info().name)
+        .set_app_path(&path.to_string_lossy())
+        .set_use_launch_agent(true)
+        .build()?)
+}
+
+// `start_minimized` decides whether the autostarted window shows up
+#[tauri::command]
+pub fn set_launch_at_login(enabled: bool, app: AppHandle) -> Result<(), Error> {
+    let launcher = launcher(&app)?;
+    if enabled {
+        launcher.enable()?;
+    } else {
+        launcher.disable()?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_launch_at_login(app: AppHandle) -> Result<bool, Error> {
+    Ok(launcher(&app)?.is_enabled()?)
+}
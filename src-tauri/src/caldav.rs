@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    outbound::{self, OutboundRequest},
+    read_settings,
+    timebox::TimeboxPlan,
+    TimePhase,
+};
+
+const ICS_TYPE: &str = "text/calendar; charset=utf-8";
+// "user:password", usually an app password
+const CREDENTIALS_SECRET: &str = "caldav";
+
+// Puts the Work sessions of a timebox plan into a CalDAV calendar as
+// tentative events, confirmed as they're done and removed if they aren't
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct CalDav {
+    // The calendar collection, e.g. https://example.com/remote.php/dav/calendars/me/work/
+    pub calendar_url: Option<String>,
+}
+
+struct Block {
+    uid: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+// Blocks of the running plan still waiting to be done
+#[derive(Default)]
+pub struct PublishedBlocks(Mutex<Vec<Block>>);
+
+fn ics_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn event(block: &Block, done: bool) -> String {
+    let (summary, status) = if done {
+        ("Pomodoro ✓", "CONFIRMED")
+    } else {
+        ("Pomodoro", "TENTATIVE")
+    };
+    [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//Pomodorio//EN",
+        "BEGIN:VEVENT",
+        &format!("UID:{}", block.uid),
+        &format!("DTSTAMP:{}", ics_time(Utc::now())),
+        &format!("DTSTART:{}", ics_time(block.start)),
+        &format!("DTEND:{}", ics_time(block.end)),
+        &format!("SUMMARY:{summary}"),
+        &format!("STATUS:{status}"),
+        "END:VEVENT",
+        "END:VCALENDAR",
+        "",
+    ]
+    .join("\r\n")
+}
+
+fn request(
+    calendar_url: &str,
+    method: &str,
+    block: &Block,
+    body: Option<String>,
+) -> OutboundRequest {
+    OutboundRequest {
+        integration: "caldav".into(),
+        method: method.into(),
+        url: format!("{}/{}.ics", calendar_url.trim_end_matches('/'), block.uid),
+        content_type: body.is_some().then(|| ICS_TYPE.to_string()),
+        body: body.map(|body| json!(body)),
+        bearer_secret: None,
+        basic_secret: Some(CREDENTIALS_SECRET.into()),
+    }
+}
+
+fn calendar_url(app: &AppHandle) -> Option<String> {
+    read_settings(app).integrations.caldav.calendar_url
+}
+
+// A new plan replaces whatever is left of the last one
+pub fn publish(app: &AppHandle, plan: &TimeboxPlan) {
+    withdraw(app);
+    let Some(url) = calendar_url(app) else {
+        return;
+    };
+
+    let batch = format!("{:08x}", rand::random::<u32>());
+    let blocks: Vec<Block> = plan
+        .phases
+        .iter()
+        .filter(|planned| planned.phase == TimePhase::Work)
+        .enumerate()
+        .map(|(index, planned)| Block {
+            uid: format!("pomodorio-{batch}-{index}"),
+            start: planned.start,
+            end: planned.end,
+        })
+        .collect();
+    for block in &blocks {
+        outbound::send(app, request(&url, "PUT", block, Some(event(block, false))));
+    }
+    *app.state::<PublishedBlocks>().0.lock().unwrap() = blocks;
+}
+
+// After a Work session was credited, the earliest block left is the one done
+pub fn on_session_complete(app: &AppHandle) {
+    let block = {
+        let blocks = app.state::<PublishedBlocks>();
+        let mut blocks = blocks.0.lock().unwrap();
+        if blocks.is_empty() {
+            return;
+        }
+        blocks.remove(0)
+    };
+    if let Some(url) = calendar_url(app) {
+        outbound::send(app, request(&url, "PUT", &block, Some(event(&block, true))));
+    }
+}
+
+// The plan ended or was cancelled, what wasn't done comes off the calendar
+pub fn withdraw(app: &AppHandle) {
+    let blocks = std::mem::take(&mut *app.state::<PublishedBlocks>().0.lock().unwrap());
+    let Some(url) = calendar_url(app) else {
+        return;
+    };
+    for block in &blocks {
+        outbound::send(app, request(&url, "DELETE", block, None));
+    }
+}
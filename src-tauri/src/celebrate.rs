@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use tauri::{api::notification::Notification, AppHandle, Manager};
+
+use crate::{
+    audio, events, get_from_store, presentation, read_settings, silent, with_store, Stats,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Celebration {
+    // Tells the frontend to throw confetti
+    pub confetti: bool,
+    // Plays the `session_complete` sound, if one is set
+    pub sound: bool,
+    // Total session counts that also get a notification
+    pub milestones: Vec<i32>,
+}
+
+impl Default for Celebration {
+    fn default() -> Self {
+        Self {
+            confetti: true,
+            sound: true,
+            milestones: vec![10, 25, 50, 100, 250, 500, 1000],
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct SessionComplete {
+    minutes: i32,
+    today_sessions: i32,
+    total_sessions: i32,
+    confetti: bool,
+    // The milestone just reached, if any
+    milestone: Option<i32>,
+}
+
+// Every channel of the celebration fires from here, once per Work session
+// that counted towards the stats
+pub fn on_session_complete(app: &AppHandle, minutes: i32) {
+    let celebration = read_settings(app).celebration;
+    let mut stats = Stats::default();
+    with_store(app, |store| {
+        stats = get_from_store(store, "stats").unwrap_or_default();
+        Ok(())
+    });
+
+    let total = stats.total.sessions;
+    let milestone = celebration.milestones.contains(&total).then_some(total);
+    events::emit_once(
+        app,
+        "session-complete",
+        SessionComplete {
+            minutes,
+            today_sessions: stats.today.sessions,
+            total_sessions: total,
+            confetti: celebration.confetti,
+            milestone,
+        },
+    );
+
+    if celebration.sound {
+        audio::play_event(app, audio::SoundEvent::SessionComplete);
+    }
+    if let Some(milestone) =
+        milestone.filter(|_| !presentation::active(app) && !silent::active(app))
+    {
+        let shown = Notification::new(app.config().tauri.bundle.identifier.clone())
+            .title("Milestone reached")
+            .body(format!("That's {milestone} pomodoros!"))
+            .show();
+        if let Err(err) = shown {
+            eprintln!("Couldn't show the milestone notification: {err}");
+        }
+    }
+}
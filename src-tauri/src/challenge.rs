@@ -0,0 +1,149 @@
+use chrono::{DateTime, Local, NaiveDate, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use std::fs;
+use tauri::{AppHandle, Manager};
+
+use crate::{events, history, with_store, TimePhase};
+
+const CHALLENGE_KEY: &str = "daily_challenge";
+// Replaces the built-in list when present in the app config dir
+const CHALLENGES_FILE: &str = "challenges.json";
+const BUILT_IN: &str = include_str!("../challenges.json");
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Goal {
+    // Completed Work sessions
+    Sessions { count: usize },
+    // Completed Work sessions ending before `hour` o'clock
+    SessionsBefore { count: usize, hour: u32 },
+    Minutes { minutes: i32 },
+    // At least `count` breaks and none of them cut short
+    HonorBreaks { count: usize },
+    // At least `count` Work sessions and none of them skipped
+    NoSkips { count: usize },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Challenge {
+    pub id: String,
+    pub title: String,
+    pub goal: Goal,
+}
+
+// Today's pick, kept in the store so relaunching doesn't reroll it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DailyChallenge {
+    pub date: NaiveDate,
+    pub challenge: Challenge,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+fn challenges(app: &AppHandle) -> Vec<Challenge> {
+    let custom = app
+        .path_resolver()
+        .app_config_dir()
+        .map(|dir| dir.join(CHALLENGES_FILE))
+        .and_then(|path| fs::read_to_string(path).ok());
+    if let Some(custom) = custom {
+        match serde_json::from_str(&custom) {
+            Ok(challenges) => return challenges,
+            Err(err) => eprintln!("Ignoring {CHALLENGES_FILE}: {err}"),
+        }
+    }
+    serde_json::from_str(BUILT_IN).expect("The built-in challenges are valid")
+}
+
+// The same day always gets the same challenge
+fn pick(challenges: Vec<Challenge>, date: NaiveDate) -> Option<Challenge> {
+    if challenges.is_empty() {
+        return None;
+    }
+    let index = date.to_string().bytes().fold(0usize, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte as usize)
+    }) % challenges.len();
+    challenges.into_iter().nth(index)
+}
+
+fn met(goal: &Goal, records: &[history::SessionRecord]) -> bool {
+    let work = records
+        .iter()
+        .filter(|record| record.phase == TimePhase::Work);
+    let completed = || work.clone().filter(|record| record.completed);
+    match *goal {
+        Goal::Sessions { count } => completed().count() >= count,
+        Goal::SessionsBefore { count, hour } => {
+            completed()
+                .filter(|record| record.end.with_timezone(&Local).hour() < hour)
+                .count()
+                >= count
+        }
+        Goal::Minutes { minutes } => {
+            completed().map(|record| record.minutes).sum::<i32>() >= minutes
+        }
+        Goal::HonorBreaks { count } => {
+            let mut breaks = records
+                .iter()
+                .filter(|record| record.phase != TimePhase::Work);
+            breaks.clone().count() >= count && breaks.all(|record| record.completed)
+        }
+        Goal::NoSkips { count } => {
+            completed().count() >= count && work.clone().all(|record| record.completed)
+        }
+    }
+}
+
+// Picks the day's challenge on the first call of the day and checks it
+// against today's history. Call at launch and whenever a phase ends.
+pub fn refresh(app: &AppHandle) -> Option<DailyChallenge> {
+    let today = Local::now().date_naive();
+    let available = challenges(app);
+
+    let mut newly_completed = false;
+    let mut daily = None;
+    with_store(app, |store| {
+        let mut current = store
+            .get(CHALLENGE_KEY)
+            .and_then(|value| from_value::<DailyChallenge>(value.clone()).ok())
+            .filter(|daily| daily.date == today)
+            .or_else(|| {
+                pick(available, today).map(|challenge| DailyChallenge {
+                    date: today,
+                    challenge,
+                    completed_at: None,
+                })
+            });
+
+        if let Some(current) = current
+            .as_mut()
+            .filter(|current| current.completed_at.is_none())
+        {
+            let records: Vec<_> = history::load(store)
+                .into_iter()
+                .filter(|record| record.end.with_timezone(&Local).date_naive() == today)
+                .collect();
+            if met(&current.challenge.goal, &records) {
+                current.completed_at = Some(Utc::now());
+                newly_completed = true;
+            }
+        }
+        if let Some(current) = &current {
+            store.insert(CHALLENGE_KEY.into(), json!(current))?;
+        }
+        daily = current;
+        Ok(())
+    });
+
+    events::emit(app, "daily-challenge", &daily);
+    if let Some(daily) = daily.as_ref().filter(|_| newly_completed) {
+        events::emit_once(app, "challenge-completed", daily);
+    }
+    daily
+}
+
+// Also rolls the challenge over when the app has been open since yesterday
+#[tauri::command]
+pub fn get_daily_challenge(app: AppHandle) -> Option<DailyChallenge> {
+    refresh(&app)
+}
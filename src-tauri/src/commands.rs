@@ -0,0 +1,111 @@
+use serde::Serialize;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Invoke, Manager, Wry};
+
+// Calls taking this long are logged even with `log_commands` off
+const SLOW: Duration = Duration::from_millis(200);
+
+thread_local! {
+    // A command is running on this thread under `logged`
+    static IN_CALL: Cell<bool> = Cell::new(false);
+    // What it answered with, if it was an error
+    static FAILURE: RefCell<Option<String>> = RefCell::new(None);
+}
+
+#[derive(Serialize, Clone, Default, Debug)]
+pub struct CommandStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+    pub last_error: Option<String>,
+}
+
+// Since launch, by command name
+#[derive(Default)]
+pub struct CommandMetrics(Mutex<BTreeMap<String, CommandStats>>);
+
+// `log_commands` as of the last settings change, every call checks it
+#[derive(Default)]
+pub struct LogCommands(AtomicBool);
+
+pub fn set_logging(app: &AppHandle, log_commands: bool) {
+    app.state::<LogCommands>()
+        .0
+        .store(log_commands, Ordering::Relaxed);
+}
+
+// Called as an `Error` is serialized for the frontend. Commands answer on
+// the thread `logged` runs them on, so it's seen there.
+pub fn record_error(message: &str) {
+    if IN_CALL.with(Cell::get) {
+        FAILURE.with(|failure| *failure.borrow_mut() = Some(message.to_string()));
+    }
+}
+
+pub fn record(app: &AppHandle, name: &str, elapsed: Duration, failure: Option<String>) {
+    let ms = elapsed.as_millis() as u64;
+    {
+        let metrics = app.state::<CommandMetrics>();
+        let mut metrics = metrics.0.lock().unwrap();
+        let stats = metrics.entry(name.to_string()).or_default();
+        stats.calls += 1;
+        stats.total_ms += ms;
+        stats.max_ms = stats.max_ms.max(ms);
+        if let Some(failure) = &failure {
+            stats.errors += 1;
+            stats.last_error = Some(failure.clone());
+        }
+    }
+
+    match failure {
+        Some(failure) => eprintln!("{name} failed after {ms} ms: {failure}"),
+        None if elapsed >= SLOW || app.state::<LogCommands>().0.load(Ordering::Relaxed) => {
+            eprintln!("{name} took {ms} ms")
+        }
+        None => {}
+    }
+}
+
+// Wraps the generated invoke handler, so every command is timed and its
+// outcome counted without each one doing it. Commands run on the async
+// runtime's blocking pool rather than the main thread, so a slow disk,
+// keychain prompt or server never holds up window events. They're written
+// as synchronous commands: each answers before `logged` returns, which is
+// what it times. `run_timing_selftest` is the exception, it sleeps for up to
+// a minute and answers later from the runtime, so only its start is timed.
+pub fn logged<F>(handler: F) -> impl Fn(Invoke<Wry>) + Send + Sync + 'static
+where
+    F: Fn(Invoke<Wry>) + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    move |invoke| {
+        let handler = handler.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let app = invoke.message.window().app_handle();
+            let name = invoke.message.command().to_string();
+            FAILURE.with(|failure| failure.borrow_mut().take());
+            IN_CALL.with(|in_call| in_call.set(true));
+            let started = Instant::now();
+            handler(invoke);
+            IN_CALL.with(|in_call| in_call.set(false));
+            let failure = FAILURE.with(|failure| failure.borrow_mut().take());
+            record(&app, &name, started.elapsed(), failure);
+        });
+    }
+}
+
+#[tauri::command]
+pub fn get_command_metrics(
+    metrics: tauri::State<CommandMetrics>,
+) -> BTreeMap<String, CommandStats> {
+    metrics.0.lock().unwrap().clone()
+}
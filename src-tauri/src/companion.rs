@@ -0,0 +1,227 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+#[cfg(feature = "http-api")]
+use qrcode::{Color, QrCode};
+use serde::Serialize;
+#[cfg(feature = "http-api")]
+use serde_json::json;
+#[cfg(feature = "http-api")]
+use std::{net::TcpStream, thread, time::Duration};
+use tauri::AppHandle;
+#[cfg(feature = "http-api")]
+use tungstenite::Message;
+
+#[cfg(feature = "http-api")]
+use crate::{
+    control::{read_request, respond, status_snapshot},
+    get_from_store,
+    render::Canvas,
+    Stat, Stats, TimePhase,
+};
+use crate::{
+    share::{lan_address, stored_token, SHARE_PORT},
+    with_store, Error,
+};
+
+// Its own token, the companion shows more than the guest page does
+const TOKEN_KEY: &str = "companion_token";
+#[cfg(feature = "http-api")]
+const PUSH_INTERVAL: Duration = Duration::from_secs(1);
+// Pixels per QR module, and the quiet zone around the code in modules
+#[cfg(feature = "http-api")]
+const QR_SCALE: u32 = 8;
+#[cfg(feature = "http-api")]
+const QR_MARGIN: u32 = 4;
+
+// What the owner's phone gets: the guest view plus today's numbers
+#[cfg(feature = "http-api")]
+#[derive(Serialize, Clone, Debug)]
+struct CompanionStatus {
+    phase: TimePhase,
+    label: &'static str,
+    class: &'static str,
+    remaining_secs: i32,
+    running: bool,
+    tag: Option<String>,
+    today: Stat,
+}
+
+#[cfg(feature = "http-api")]
+fn companion_status(app: &AppHandle) -> CompanionStatus {
+    let status = status_snapshot(app);
+    let mut today = Stat::default();
+    with_store(app, |store| {
+        if let Ok(stats) = get_from_store::<Stats>(store, "stats") {
+            today = stats.today;
+        }
+        Ok(())
+    });
+    CompanionStatus {
+        phase: status.phase,
+        label: status.phase.label(),
+        class: status.class,
+        remaining_secs: status.remaining_secs,
+        running: status.running,
+        tag: status.tag,
+        today,
+    }
+}
+
+pub fn companion_token(app: &AppHandle) -> String {
+    stored_token(app, TOKEN_KEY)
+}
+
+// Self-contained, the first status is rendered in and the socket keeps it
+// current. Without a socket it falls back to reloading.
+#[cfg(feature = "http-api")]
+fn page(status: &CompanionStatus) -> String {
+    // A tag can't close the script early
+    let status = json!(status).to_string().replace('<', "\\u003c");
+    format!(
+        r#"<!doctype html><html><head><meta charset="utf-8">
+<meta name="viewport" content="width=device-width,initial-scale=1"><title>Pomodorio</title>
+<style>
+body{{font-family:sans-serif;text-align:center;margin:0;padding-top:15vh;background:#1e1e1e;color:#eee}}
+body.work{{background:#8b2e2e}}body.short-break,body.long-break{{background:#2e6b4a}}
+#time{{font-size:22vw;font-variant-numeric:tabular-nums;margin:.2em 0}}
+#today{{opacity:.8}}
+</style></head><body>
+<h2 id="label"></h2><div id="time"></div><p id="today"></p>
+<script>
+let status = {status};
+let seen = Date.now();
+function pad(n) {{ return String(n).padStart(2, "0"); }}
+function render() {{
+  let left = status.remaining_secs;
+  if (status.running) left = Math.max(0, left - Math.floor((Date.now() - seen) / 1000));
+  document.body.className = status.class;
+  document.getElementById("label").textContent = status.label + (status.running ? "" : " (paused)") + (status.tag ? " · " + status.tag : "");
+  document.getElementById("time").textContent = pad(Math.floor(left / 60)) + ":" + pad(left % 60);
+  document.getElementById("today").textContent = status.today.sessions + " today · " + status.today.minutes + " min";
+}}
+function connect() {{
+  let socket = new WebSocket("ws://" + location.host + location.pathname + "/ws");
+  socket.onmessage = (event) => {{ status = JSON.parse(event.data); seen = Date.now(); render(); }};
+  socket.onclose = () => setTimeout(connect, 5000);
+}}
+render();
+setInterval(render, 1000);
+if (window.WebSocket) connect(); else setTimeout(() => location.reload(), 15000);
+</script></body></html>"#
+    )
+}
+
+// Pushes the status every second until the phone goes away
+#[cfg(feature = "http-api")]
+fn serve_socket(app: AppHandle, stream: TcpStream) {
+    thread::spawn(move || {
+        let _ = stream.set_read_timeout(None);
+        let mut socket = match tungstenite::accept(stream) {
+            Ok(socket) => socket,
+            Err(err) => {
+                eprintln!("Companion socket handshake failed: {err}");
+                return;
+            }
+        };
+        loop {
+            let status = json!(companion_status(&app)).to_string();
+            if socket.send(Message::Text(status)).is_err() {
+                return;
+            }
+            thread::sleep(PUSH_INTERVAL);
+        }
+    });
+}
+
+// Called by the share server for paths under /companion/. The socket
+// takes over the stream, so it gets it before anything is read from it.
+#[cfg(feature = "http-api")]
+pub fn handle(app: &AppHandle, path: &str, mut stream: TcpStream) -> std::io::Result<()> {
+    let page_path = format!("/companion/{}", companion_token(app));
+    if path == format!("{page_path}/ws") {
+        serve_socket(app.clone(), stream);
+        return Ok(());
+    }
+
+    read_request(&mut stream)?;
+    if path == page_path {
+        respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            &page(&companion_status(app)),
+        )
+    } else {
+        respond(&mut stream, "404 Not Found", "text/plain", "Not found")
+    }
+}
+
+#[tauri::command]
+pub fn get_companion_link(app: AppHandle) -> Option<String> {
+    if !cfg!(feature = "http-api") {
+        return None;
+    }
+    let host = lan_address()?;
+    Some(format!(
+        "http://{host}:{SHARE_PORT}/companion/{}",
+        companion_token(&app)
+    ))
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PairingQr {
+    pub url: String,
+    // data:image/png;base64 URL, ready for an <img>
+    pub image: String,
+}
+
+#[cfg(feature = "http-api")]
+fn render_qr(text: &str) -> Result<Vec<u8>, Error> {
+    let code = QrCode::new(text).map_err(|err| Error::Pairing(err.to_string()))?;
+    let modules = code.width() as u32;
+    let size = (modules + 2 * QR_MARGIN) * QR_SCALE;
+    let mut canvas = Canvas::new(size, size, [255, 255, 255, 255]);
+    for (index, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            let (x, y) = (index as u32 % modules, index as u32 / modules);
+            canvas.fill_rect(
+                (x + QR_MARGIN) * QR_SCALE,
+                (y + QR_MARGIN) * QR_SCALE,
+                QR_SCALE,
+                QR_SCALE,
+                [0, 0, 0, 255],
+            );
+        }
+    }
+    canvas
+        .encode_png()
+        .map_err(|err| Error::Pairing(err.to_string()))
+}
+
+#[cfg(not(feature = "http-api"))]
+fn render_qr(_text: &str) -> Result<Vec<u8>, Error> {
+    Err(Error::NotInBuild("The companion"))
+}
+
+// The companion link, token included, as a code to scan with the phone
+#[tauri::command]
+pub fn get_pairing_qr(app: AppHandle) -> Result<PairingQr, Error> {
+    if !cfg!(feature = "http-api") {
+        return Err(Error::NotInBuild("The companion"));
+    }
+    let url = get_companion_link(app)
+        .ok_or_else(|| Error::Pairing("not connected to a local network".into()))?;
+    let png = render_qr(&url)?;
+    Ok(PairingQr {
+        image: format!("data:image/png;base64,{}", STANDARD.encode(png)),
+        url,
+    })
+}
+
+#[tauri::command]
+pub fn reset_companion_link(app: AppHandle) -> Option<String> {
+    with_store(&app, |store| {
+        store.delete(TOKEN_KEY)?;
+        Ok(())
+    });
+    get_companion_link(app)
+}
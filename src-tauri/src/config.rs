@@ -0,0 +1,222 @@
+use serde_json::{json, Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::{Duration, SystemTime},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{events, read_settings, settings_history, subsystems, tray, with_store, Settings};
+
+const CONFIG_FILE: &str = "pomodorio.toml";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const ENV_PREFIX: &str = "POMODORIO_";
+
+// Settings from the optional config file and the environment, laid over
+// the stored ones on every read and never written back. Anything they leave
+// out is still up to the store, so they only have to name what they pin.
+#[derive(Default)]
+pub struct ConfigOverrides {
+    file: Mutex<Option<Value>>,
+    // Read once at launch, wins over the file
+    env: Mutex<Option<Value>>,
+}
+
+fn config_path(app: &AppHandle) -> Option<PathBuf> {
+    Some(app.path_resolver().app_config_dir()?.join(CONFIG_FILE))
+}
+
+pub fn merge(base: &mut Value, overlay: &Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+// Puts the stored value back wherever the overlay sits, the inverse of `merge`
+fn unmerge(settings: &mut Value, stored: &Value, overlay: &Value) {
+    let (Value::Object(settings), Value::Object(overlay)) = (settings, overlay) else {
+        return;
+    };
+    for (key, value) in overlay {
+        let stored = stored.get(key);
+        match (settings.get_mut(key), stored) {
+            (Some(inner @ Value::Object(_)), Some(stored)) if value.is_object() => {
+                unmerge(inner, stored, value)
+            }
+            (_, Some(stored)) => {
+                settings.insert(key.clone(), stored.clone());
+            }
+            (_, None) => {
+                settings.remove(key);
+            }
+        }
+    }
+}
+
+pub fn apply(app: &AppHandle, settings: &mut Value) {
+    let overrides = app.state::<ConfigOverrides>();
+    for layer in [&overrides.file, &overrides.env] {
+        if let Some(layer) = &*layer.lock().unwrap() {
+            merge(settings, layer);
+        }
+    }
+}
+
+// Settings read through `apply` and sent back, as the settings page does, with
+// the overrides taken out again so they never end up in the store
+pub fn strip(app: &AppHandle, settings: &mut Value, stored: &Value) {
+    let overrides = app.state::<ConfigOverrides>();
+    for layer in [&overrides.file, &overrides.env] {
+        if let Some(layer) = &*layer.lock().unwrap() {
+            unmerge(settings, stored, layer);
+        }
+    }
+}
+
+fn fits(single: &Value) -> Result<(), serde_json::Error> {
+    let mut check = json!(Settings::default());
+    merge(&mut check, single);
+    serde_json::from_value::<Settings>(check).map(|_| ())
+}
+
+// `POMODORIO_WORK_TIME=50` sets `work_time`, a double underscore reaches
+// into nested settings: `POMODORIO_LAN_SYNC__ENABLED=true`. Values are read
+// as JSON and fall back to a plain string.
+fn env_override(name: &str, value: &str) -> Option<Value> {
+    let path = name.strip_prefix(ENV_PREFIX)?.to_lowercase();
+    let mut value = serde_json::from_str(value).unwrap_or_else(|_| json!(value));
+    for key in path.rsplit("__") {
+        let mut object = Map::new();
+        object.insert(key.to_string(), value);
+        value = Value::Object(object);
+    }
+    Some(value)
+}
+
+// Variables that don't fit their setting are left out, rather than making
+// every settings read fail
+fn env_overrides() -> Option<Value> {
+    let mut overrides = Value::Object(Map::new());
+    for (name, value) in std::env::vars() {
+        let Some(single) = env_override(&name, &value) else {
+            continue;
+        };
+        match fits(&single) {
+            Ok(()) => merge(&mut overrides, &single),
+            Err(err) => eprintln!("Ignoring {name}: {err}"),
+        }
+    }
+    overrides
+        .as_object()
+        .map_or(false, |object| !object.is_empty())
+        .then_some(overrides)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+// Keys in the file that don't fit their setting are left out one by one,
+// the rest of the file still applies
+fn fitting(overrides: Value, path: &Path) -> Value {
+    let Value::Object(overrides) = overrides else {
+        return Value::Object(Map::new());
+    };
+    let kept = overrides.into_iter().filter(|(key, value)| {
+        let single = Value::Object(Map::from_iter([(key.clone(), value.clone())]));
+        match fits(&single) {
+            Ok(()) => true,
+            Err(err) => {
+                eprintln!("Ignoring {key} in {}: {err}", path.display());
+                false
+            }
+        }
+    });
+    Value::Object(kept.collect())
+}
+
+// A file that doesn't parse keeps the last good overrides in place
+fn reload(app: &AppHandle, path: &Path) {
+    let overrides = match fs::read_to_string(path) {
+        Ok(text) => match toml::from_str::<Value>(&text) {
+            Ok(overrides) => Some(fitting(overrides, path)),
+            Err(err) => {
+                eprintln!("Ignoring {}: {err}", path.display());
+                return;
+            }
+        },
+        Err(_) => None,
+    };
+    *app.state::<ConfigOverrides>().file.lock().unwrap() = overrides;
+}
+
+// Loads the overrides right away, so the rest of setup already sees them,
+// then polls the file for changes
+pub fn spawn(app: AppHandle) {
+    *app.state::<ConfigOverrides>().env.lock().unwrap() = env_overrides();
+    let Some(path) = config_path(&app) else {
+        return;
+    };
+    reload(&app, &path);
+
+    thread::spawn(move || {
+        let mut last_modified = modified(&path);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = modified(&path);
+            if current == last_modified {
+                continue;
+            }
+            last_modified = current;
+
+            let before = json!(read_settings(&app));
+            reload(&app, &path);
+            let after = read_settings(&app);
+            with_store(&app, |store| {
+                settings_history::record(store, &before, &json!(after), "config-file");
+                Ok(())
+            });
+            events::emit(&app, "settings-changed", after);
+            tray::refresh(&app);
+            subsystems::start_enabled(&app);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_file_key_of_the_wrong_type_leaves_the_others_applied() {
+        let file = toml::from_str("work_time = \"25\"\nshort_break_time = 3\n").unwrap();
+        let kept = fitting(file, Path::new(CONFIG_FILE));
+        assert_eq!(kept, json!({ "short_break_time": 3 }));
+    }
+
+    #[test]
+    fn env_overrides_are_taken_out_of_settings_sent_back() {
+        let stored = json!(Settings::default());
+        let mut overlay = env_override("POMODORIO_WORK_TIME", "50").unwrap();
+        merge(
+            &mut overlay,
+            &env_override("POMODORIO_LAN_SYNC__ENABLED", "true").unwrap(),
+        );
+        // As the settings page gets them and saves them again
+        let mut settings = stored.clone();
+        merge(&mut settings, &overlay);
+        settings["short_break_time"] = json!(7);
+
+        unmerge(&mut settings, &stored, &overlay);
+        let mut expected = stored.clone();
+        expected["short_break_time"] = json!(7);
+        assert_eq!(settings, expected);
+    }
+}
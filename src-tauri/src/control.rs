@@ -0,0 +1,182 @@
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    events, get_from_store, read_settings,
+    silent::{self, SilentUntil},
+    timer::Timer,
+    with_store, ActiveTag, Phase, SessionNumber, Stats, TimePhase,
+};
+
+// Loopback port for the local control API
+pub const CONTROL_PORT: u16 = 47_832;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StatusSnapshot {
+    // Preformatted line and CSS class, so Waybar can consume the JSON as is
+    pub text: String,
+    pub class: &'static str,
+    pub phase: TimePhase,
+    pub remaining_secs: i32,
+    pub running: bool,
+    pub session_number: i32,
+    pub sessions_today: i32,
+    pub tag: Option<String>,
+    // Sounds and notifications are off until then
+    pub silent_until: Option<SilentUntil>,
+}
+
+pub fn status_snapshot(app: &AppHandle) -> StatusSnapshot {
+    let phase = *app.state::<Phase>().0.lock().unwrap();
+    let session_number = *app.state::<SessionNumber>().0.lock().unwrap();
+    let tag = app.state::<ActiveTag>().0.lock().unwrap().clone();
+    let (remaining_secs, running) = {
+        let timer = app.state::<Timer>();
+        let state = timer.0.lock().unwrap();
+        (state.remaining_secs_now(), state.running)
+    };
+
+    let mut sessions_today = 0;
+    with_store(app, |store| {
+        if let Ok(stats) = get_from_store::<Stats>(store, "stats") {
+            sessions_today = stats.today.sessions;
+        }
+        Ok(())
+    });
+
+    let mut text = format!(
+        "{} {:02}:{:02} · {} today",
+        phase.label(),
+        remaining_secs / 60,
+        remaining_secs % 60,
+        sessions_today
+    );
+    if !running {
+        text.push_str(" (paused)");
+    }
+
+    StatusSnapshot {
+        text,
+        class: phase.class(),
+        phase,
+        remaining_secs,
+        running,
+        session_number,
+        sessions_today,
+        tag,
+        silent_until: silent::until(app),
+    }
+}
+
+#[tauri::command]
+pub fn get_state(app: AppHandle) -> StatusSnapshot {
+    status_snapshot(&app)
+}
+
+#[tauri::command]
+pub fn get_status_line(app: AppHandle) -> String {
+    status_snapshot(&app).text
+}
+
+// Headless launches serve status bars whatever the settings say, otherwise
+// turning `control_api` off closes every connection until it's back on
+pub fn spawn(app: AppHandle, always: bool) {
+    thread::spawn(move || {
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, CONTROL_PORT)) {
+            Ok(listener) => listener,
+            Err(err) => {
+                eprintln!("Control API disabled, cannot bind port {CONTROL_PORT}: {err}");
+                return;
+            }
+        };
+
+        for stream in listener.incoming().flatten() {
+            if !always && !read_settings(&app).control_api {
+                continue;
+            }
+            if let Err(err) = handle_connection(&app, stream) {
+                eprintln!("Control API request failed: {err}");
+            }
+        }
+    });
+}
+
+// Method and path of an HTTP request, the rest is ignored
+pub fn read_request(stream: &mut TcpStream) -> std::io::Result<(String, String)> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
+    let mut request_line = String::new();
+    BufReader::new(stream.try_clone()?).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+    Ok((method, path))
+}
+
+pub fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn handle_connection(app: &AppHandle, mut stream: TcpStream) -> std::io::Result<()> {
+    let (method, path) = read_request(&mut stream)?;
+
+    let (status, content_type, body) = match (method.as_str(), path.as_str()) {
+        ("GET", "/status") => (
+            "200 OK",
+            "application/json",
+            json!(status_snapshot(app)).to_string(),
+        ),
+        ("GET", "/version") => (
+            "200 OK",
+            "application/json",
+            json!(events::API_VERSION).to_string(),
+        ),
+        ("GET", "/status/line") => (
+            "200 OK",
+            "text/plain; charset=utf-8",
+            status_snapshot(app).text,
+        ),
+        _ => ("404 Not Found", "text/plain", "Not found".to_string()),
+    };
+
+    respond(&mut stream, status, content_type, &body)
+}
+
+// One-shot `pomodorio --status [--json]` for status bar modules to poll
+pub fn print_status(as_json: bool) {
+    let status =
+        fetch_status().unwrap_or_else(|_| json!({ "text": "Not running", "class": "offline" }));
+
+    if as_json {
+        println!("{status}");
+    } else {
+        println!("{}", status["text"].as_str().unwrap_or_default());
+    }
+}
+
+fn fetch_status() -> std::io::Result<serde_json::Value> {
+    let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, CONTROL_PORT))?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    stream.write_all(b"GET /status HTTP/1.0\r\nHost: localhost\r\n\r\n")?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+    Ok(serde_json::from_str(body)?)
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    add_to_stats, events, history, persist, timer::Timer, tray, with_store, ActiveProfile,
+    ActiveTag, ActiveTask, Phase, TimePhase,
+};
+
+const RUNNING_KEY: &str = "running";
+
+// Left in the store while a Work session is under way and removed on a
+// clean exit, so finding it at launch means the app went down mid-session
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct RunningMarker {
+    started_at: DateTime<Utc>,
+    // The last time the session was known to be counting
+    last_seen: DateTime<Utc>,
+    tag: Option<String>,
+    profile: Option<String>,
+    #[serde(default)]
+    task: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct CrashRecovery {
+    started_at: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    // What accepting would credit to the stats
+    minutes: i32,
+}
+
+// Found at launch, until the frontend answers `crash-recovery`
+#[derive(Default)]
+pub struct PendingRecovery(Mutex<Option<RunningMarker>>);
+
+fn minutes(marker: &RunningMarker) -> i32 {
+    (marker.last_seen - marker.started_at).num_minutes() as i32
+}
+
+// Call when the timer starts, pauses or the phase changes, and every
+// minute while Work counts down
+pub fn update(app: &AppHandle) {
+    let phase = *app.state::<Phase>().0.lock().unwrap();
+    let running = app.state::<Timer>().0.lock().unwrap().running;
+    let marker = RunningMarker {
+        started_at: history::phase_started_at(app),
+        last_seen: Utc::now(),
+        tag: app.state::<ActiveTag>().0.lock().unwrap().clone(),
+        profile: app.state::<ActiveProfile>().0.lock().unwrap().clone(),
+        task: app.state::<ActiveTask>().0.lock().unwrap().clone(),
+    };
+
+    with_store(app, |store| {
+        let paused_work = phase == TimePhase::Work && store.has(RUNNING_KEY);
+        if phase == TimePhase::Work && running || paused_work {
+            store.insert(RUNNING_KEY.into(), json!(marker))?;
+        } else if !store.delete(RUNNING_KEY)? {
+            return Ok(());
+        }
+        persist::save(app);
+        Ok(())
+    });
+}
+
+pub fn clear(app: &AppHandle) {
+    with_store(app, |store| {
+        if store.delete(RUNNING_KEY)? {
+            persist::save(app);
+        }
+        Ok(())
+    });
+}
+
+// Offers the interrupted session, if the last run didn't end cleanly
+pub fn check_at_startup(app: &AppHandle) {
+    let mut marker = None;
+    with_store(app, |store| {
+        marker = store
+            .get(RUNNING_KEY)
+            .and_then(|value| from_value::<RunningMarker>(value.clone()).ok());
+        if store.delete(RUNNING_KEY)? {
+            persist::save(app);
+        }
+        Ok(())
+    });
+
+    let Some(marker) = marker.filter(|marker| minutes(marker) > 0) else {
+        return;
+    };
+    eprintln!(
+        "The last Work session, started at {}, was interrupted",
+        marker.started_at
+    );
+    events::emit(
+        app,
+        "crash-recovery",
+        Some(CrashRecovery {
+            started_at: marker.started_at,
+            last_seen: marker.last_seen,
+            minutes: minutes(&marker),
+        }),
+    );
+    *app.state::<PendingRecovery>().0.lock().unwrap() = Some(marker);
+}
+
+// The answer to `crash-recovery`, `credit` adds the interrupted session's
+// minutes to the stats and history as a completed session
+#[tauri::command]
+pub fn resolve_crash_recovery(credit: bool, app: AppHandle) {
+    let Some(marker) = app.state::<PendingRecovery>().0.lock().unwrap().take() else {
+        return;
+    };
+    if credit {
+        let minutes = minutes(&marker);
+        with_store(&app, |store| {
+            if let Err(err) = add_to_stats(store, minutes) {
+                eprintln!("Failed to credit the interrupted session: {err}");
+            }
+            history::push(
+                store,
+                history::SessionRecord {
+                    start: marker.started_at,
+                    end: marker.last_seen,
+                    phase: TimePhase::Work,
+                    minutes,
+                    completed: true,
+                    tag: marker.tag,
+                    profile: marker.profile,
+                    task: marker.task,
+                    note: None,
+                },
+            );
+            Ok(())
+        });
+        tray::refresh(&app);
+    }
+    events::emit(&app, "crash-recovery", None::<CrashRecovery>);
+}
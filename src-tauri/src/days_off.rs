@@ -0,0 +1,76 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::Store;
+
+use crate::{events, goal, with_store};
+
+const DAYS_OFF_KEY: &str = "days_off";
+
+// Planned days without pomodoros, e.g. a holiday. The goal asks nothing
+// of them and the reports label them rather than showing a gap.
+#[derive(Serialize, Clone, Debug)]
+pub struct DayOff {
+    pub date: NaiveDate,
+    pub reason: String,
+}
+
+// Date to reason
+pub fn load(store: &Store<Wry>) -> BTreeMap<NaiveDate, String> {
+    store
+        .get(DAYS_OFF_KEY)
+        .and_then(|value| from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+pub fn read(app: &AppHandle) -> BTreeMap<NaiveDate, String> {
+    let mut days_off = BTreeMap::new();
+    with_store(app, |store| {
+        days_off = load(store);
+        Ok(())
+    });
+    days_off
+}
+
+fn list(days_off: BTreeMap<NaiveDate, String>) -> Vec<DayOff> {
+    days_off
+        .into_iter()
+        .map(|(date, reason)| DayOff { date, reason })
+        .collect()
+}
+
+fn modify(app: &AppHandle, change: impl FnOnce(&mut BTreeMap<NaiveDate, String>)) -> Vec<DayOff> {
+    let mut days_off = BTreeMap::new();
+    with_store(app, |store| {
+        days_off = load(store);
+        change(&mut days_off);
+        store.insert(DAYS_OFF_KEY.into(), json!(days_off))
+    });
+    // Marking today or a day still owed for changes the goal
+    goal::publish(app);
+    let days_off = list(days_off);
+    events::emit(app, "days-off", &days_off);
+    days_off
+}
+
+// Past, today or ahead of time. Marking a day again replaces the reason.
+#[tauri::command]
+pub fn mark_day_off(date: NaiveDate, reason: String, app: AppHandle) -> Vec<DayOff> {
+    modify(&app, |days_off| {
+        days_off.insert(date, reason);
+    })
+}
+
+#[tauri::command]
+pub fn unmark_day_off(date: NaiveDate, app: AppHandle) -> Vec<DayOff> {
+    modify(&app, |days_off| {
+        days_off.remove(&date);
+    })
+}
+
+#[tauri::command]
+pub fn get_days_off(app: AppHandle) -> Vec<DayOff> {
+    list(read(&app))
+}
@@ -0,0 +1,193 @@
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::Store;
+
+use crate::{
+    analytics::Range,
+    history::{self, DailyTotal},
+    with_store,
+};
+
+const ESTIMATES_KEY: &str = "estimates";
+
+// Pomodoros planned for a tag on a day, before the work is done
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Estimate {
+    pub tag: String,
+    pub date: NaiveDate,
+    pub pomodoros: i32,
+}
+
+fn load(store: &Store<Wry>) -> Vec<Estimate> {
+    store
+        .get(ESTIMATES_KEY)
+        .and_then(|value| from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+// One estimate per tag and day, a new one replaces it and 0 removes it
+#[tauri::command]
+pub fn set_estimate(tag: String, pomodoros: i32, app: AppHandle) {
+    let today = Utc::now().date_naive();
+    with_store(&app, |store| {
+        let mut estimates = load(store);
+        estimates.retain(|estimate| estimate.tag != tag || estimate.date != today);
+        if pomodoros > 0 {
+            estimates.push(Estimate {
+                tag,
+                date: today,
+                pomodoros,
+            });
+        }
+        store.insert(ESTIMATES_KEY.into(), json!(estimates))
+    });
+}
+
+#[derive(Serialize, Debug)]
+pub struct TagAccuracy {
+    pub tag: String,
+    pub estimated: i32,
+    pub actual: i32,
+    // Actual over estimated, above 1 took longer than planned. None without an estimate.
+    pub ratio: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EstimationAccuracy {
+    pub tags: Vec<TagAccuracy>,
+    // Over the tags that had an estimate only
+    pub ratio: Option<f64>,
+}
+
+fn ratio(actual: i32, estimated: i32) -> Option<f64> {
+    (estimated > 0).then(|| actual as f64 / estimated as f64)
+}
+
+// Actuals are completed Work sessions ending on a day in range, pruned ones
+// included, estimates count for the day they were made on
+fn accuracy(
+    estimates: &[Estimate],
+    days: &BTreeMap<NaiveDate, DailyTotal>,
+    range: Range,
+    today: NaiveDate,
+) -> EstimationAccuracy {
+    let mut by_tag: BTreeMap<&str, (i32, i32)> = BTreeMap::new();
+    for estimate in estimates
+        .iter()
+        .filter(|estimate| range.contains(estimate.date, today))
+    {
+        by_tag.entry(&estimate.tag).or_default().0 += estimate.pomodoros;
+    }
+    for day in days.values().filter(|day| range.contains(day.date, today)) {
+        for (tag, sessions) in &day.tags {
+            by_tag.entry(tag).or_default().1 += sessions;
+        }
+    }
+
+    let (mut estimated, mut actual) = (0, 0);
+    for (tag_estimated, tag_actual) in by_tag.values().filter(|(estimated, _)| *estimated > 0) {
+        estimated += tag_estimated;
+        actual += tag_actual;
+    }
+    EstimationAccuracy {
+        tags: by_tag
+            .into_iter()
+            .map(|(tag, (estimated, actual))| TagAccuracy {
+                tag: tag.to_string(),
+                estimated,
+                actual,
+                ratio: ratio(actual, estimated),
+            })
+            .collect(),
+        ratio: ratio(actual, estimated),
+    }
+}
+
+// Estimated against actual pomodoros per tag, to calibrate future planning
+#[tauri::command]
+pub fn get_estimation_accuracy(range: Range, app: AppHandle) -> EstimationAccuracy {
+    let mut report = EstimationAccuracy {
+        tags: Vec::new(),
+        ratio: None,
+    };
+    with_store(&app, |store| {
+        report = accuracy(
+            &load(store),
+            &history::load_days(store),
+            range,
+            Utc::now().date_naive(),
+        );
+        Ok(())
+    });
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::SessionRecord, TimePhase};
+
+    fn work(end: &str, tag: Option<&str>, completed: bool) -> SessionRecord {
+        SessionRecord {
+            completed,
+            tag: tag.map(str::to_string),
+            ..SessionRecord::ended(TimePhase::Work, end, 25)
+        }
+    }
+
+    fn estimate(tag: &str, date: &str, pomodoros: i32) -> Estimate {
+        Estimate {
+            tag: tag.to_string(),
+            date: date.parse().unwrap(),
+            pomodoros,
+        }
+    }
+
+    #[test]
+    fn accuracy_compares_estimates_to_completed_sessions_per_tag() {
+        let estimates = [
+            estimate("writing", "2024-03-04", 2),
+            estimate("writing", "2024-03-05", 2),
+            estimate("review", "2024-03-05", 2),
+            // Last week, out of range
+            estimate("review", "2024-03-01", 5),
+        ];
+        let records = [
+            work("2024-03-04T10:00:00Z", Some("writing"), true),
+            work("2024-03-04T11:00:00Z", Some("writing"), true),
+            work("2024-03-05T10:00:00Z", Some("writing"), true),
+            work("2024-03-05T11:00:00Z", Some("writing"), true),
+            work("2024-03-05T12:00:00Z", Some("writing"), true),
+            work("2024-03-05T13:00:00Z", Some("writing"), true),
+            work("2024-03-05T14:00:00Z", Some("review"), true),
+            work("2024-03-05T15:00:00Z", Some("review"), false),
+            work("2024-03-05T16:00:00Z", Some("email"), true),
+            work("2024-03-05T17:00:00Z", None, true),
+        ];
+        let report = accuracy(
+            &estimates,
+            &history::daily_totals(&records, &[]),
+            Range::Week,
+            "2024-03-06".parse().unwrap(),
+        );
+
+        let tags: Vec<_> = report
+            .tags
+            .iter()
+            .map(|tag| (tag.tag.as_str(), tag.estimated, tag.actual, tag.ratio))
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                ("email", 0, 1, None),
+                ("review", 2, 1, Some(0.5)),
+                ("writing", 4, 6, Some(1.5)),
+            ]
+        );
+        // 7 done out of 6 planned, the unestimated tag left out
+        assert_eq!(report.ratio, Some(7.0 / 6.0));
+    }
+}
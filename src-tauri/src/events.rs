@@ -0,0 +1,118 @@
+use serde::Serialize;
+use serde_json::{json, value::RawValue};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, PageLoadPayload, Window};
+
+use crate::payload::PayloadWriter;
+
+// Emitted by a page once its listeners are registered
+const FRONTEND_READY: &str = "frontend-ready";
+
+// Carried by every structured payload. Bumped only when a field changes
+// meaning or goes away, new fields are added without it and listeners
+// ignore what they don't know.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// The commands, the control API and the events as a whole. Major follows
+// `SCHEMA_VERSION`, minor goes up when something is added. Integrations
+// built against one major version keep working until it changes.
+//
+// 1.1: set_task, annotate_session, get_command_metrics, get_build_features,
+// get_remote_stats, and the task and data-repaired events
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct ApiVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+pub const API_VERSION: ApiVersion = ApiVersion {
+    major: SCHEMA_VERSION,
+    minor: 1,
+};
+
+#[tauri::command]
+pub fn get_api_version() -> ApiVersion {
+    API_VERSION
+}
+
+// Optional Cargo features this binary was built with. Settings for what's
+// left out are accepted and ignored, the frontend can hide them.
+#[tauri::command]
+pub fn get_build_features() -> Vec<&'static str> {
+    [
+        ("audio", cfg!(feature = "audio")),
+        ("http-api", cfg!(feature = "http-api")),
+        ("integrations-slack", cfg!(feature = "integrations-slack")),
+    ]
+    .into_iter()
+    .filter_map(|(feature, enabled)| enabled.then_some(feature))
+    .collect()
+}
+
+// Last payload of every state event, in the order they were first seen.
+// One-off events (notifications, announcements) skip this and are never replayed.
+// Payloads are kept as the JSON text they went out as.
+pub struct EventReplay(
+    Mutex<Vec<(&'static str, Box<RawValue>)>>,
+    Mutex<PayloadWriter>,
+);
+
+impl Default for EventReplay {
+    fn default() -> Self {
+        let fixed = json!({ "api_version": API_VERSION });
+        Self(Mutex::default(), Mutex::new(PayloadWriter::new(&fixed)))
+    }
+}
+
+// Every event goes out as `{ "api_version": ..., "data": payload }`. Some
+// go out every second, so it's serialized once and without a `Value` tree.
+fn envelope<S: Serialize>(app: &AppHandle, event: &str, payload: S) -> Option<Box<RawValue>> {
+    let replay = app.state::<EventReplay>();
+    let mut writer = replay.1.lock().unwrap();
+    let json = match writer.write(&payload) {
+        Ok(json) => json.to_string(),
+        Err(err) => {
+            eprintln!("Failed to serialize {event}: {err}");
+            return None;
+        }
+    };
+    Some(RawValue::from_string(json).unwrap())
+}
+
+pub fn emit<S: Serialize>(app: &AppHandle, event: &'static str, payload: S) {
+    let Some(payload) = envelope(app, event, payload) else {
+        return;
+    };
+    {
+        let replay = app.state::<EventReplay>();
+        let mut last = replay.0.lock().unwrap();
+        match last.iter_mut().find(|(name, _)| *name == event) {
+            Some((_, value)) => *value = payload.clone(),
+            None => last.push((event, payload.clone())),
+        }
+    }
+    let _ = app.emit_all(event, payload);
+}
+
+// For one-off events, in the same envelope but never replayed
+pub fn emit_once<S: Serialize>(app: &AppHandle, event: &str, payload: S) {
+    if let Some(payload) = envelope(app, event, payload) {
+        let _ = app.emit_all(event, payload);
+    }
+}
+
+pub fn replay(window: &Window) {
+    let app = window.app_handle();
+    let last = app.state::<EventReplay>().0.lock().unwrap().clone();
+    for (event, payload) in last {
+        let _ = window.emit(event, payload);
+    }
+}
+
+// The load hook fires before the page's own scripts run, so replay right
+// away for early listeners and again once the page says it's ready
+pub fn on_page_load(window: Window, _payload: PageLoadPayload) {
+    replay(&window);
+    let target = window.clone();
+    window.once(FRONTEND_READY, move |_| replay(&target));
+}
@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::{process::Command, thread};
+use tauri::AppHandle;
+
+use crate::{read_settings, TimePhase};
+
+// Runs a named Shortcut to toggle a macOS Focus mode. The user creates
+// the two shortcuts in Shortcuts.app with a "Set Focus" action.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MacosFocus {
+    pub enabled: bool,
+    pub on_shortcut: String,
+    pub off_shortcut: String,
+}
+
+impl Default for MacosFocus {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            on_shortcut: "Pomodorio Focus On".to_string(),
+            off_shortcut: "Pomodorio Focus Off".to_string(),
+        }
+    }
+}
+
+// Run a helper program off the timer thread, logging failures
+pub fn run_detached(program: &str, args: Vec<String>) {
+    let program = program.to_string();
+    thread::spawn(move || match Command::new(&program).args(&args).status() {
+        Ok(status) if !status.success() => eprintln!("{program} {args:?} exited with {status}"),
+        Err(err) => eprintln!("Failed to run {program}: {err}"),
+        _ => {}
+    });
+}
+
+fn set_macos_focus(config: &MacosFocus, focused: bool) {
+    let shortcut = if focused {
+        &config.on_shortcut
+    } else {
+        &config.off_shortcut
+    };
+    run_detached("shortcuts", vec!["run".to_string(), shortcut.clone()]);
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationDaemon {
+    Auto,
+    Dunst,
+    Mako,
+    Gnome,
+}
+
+impl Default for NotificationDaemon {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+// Pause desktop notifications during Work on Linux
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LinuxNotificationPause {
+    pub enabled: bool,
+    pub daemon: NotificationDaemon,
+}
+
+fn succeeds(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn detect_notification_daemon() -> Option<NotificationDaemon> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    if desktop.to_uppercase().contains("GNOME") {
+        Some(NotificationDaemon::Gnome)
+    } else if succeeds("dunstctl", &["is-paused"]) {
+        Some(NotificationDaemon::Dunst)
+    } else if succeeds("makoctl", &["mode"]) {
+        Some(NotificationDaemon::Mako)
+    } else {
+        None
+    }
+}
+
+fn set_notifications_paused(config: &LinuxNotificationPause, paused: bool) {
+    let daemon = config.daemon;
+    // Detection shells out, so keep it off the caller's thread too
+    thread::spawn(move || {
+        let daemon = match daemon {
+            NotificationDaemon::Auto => match detect_notification_daemon() {
+                Some(daemon) => daemon,
+                None => {
+                    eprintln!("No supported notification daemon found to pause");
+                    return;
+                }
+            },
+            daemon => daemon,
+        };
+
+        match daemon {
+            NotificationDaemon::Dunst => {
+                run_detached("dunstctl", vec!["set-paused".into(), paused.to_string()])
+            }
+            NotificationDaemon::Mako => run_detached(
+                "makoctl",
+                vec![
+                    "mode".into(),
+                    if paused { "-a" } else { "-r" }.into(),
+                    "do-not-disturb".into(),
+                ],
+            ),
+            NotificationDaemon::Gnome => run_detached(
+                "gsettings",
+                vec![
+                    "set".into(),
+                    "org.gnome.desktop.notifications".into(),
+                    "show-banners".into(),
+                    (!paused).to_string(),
+                ],
+            ),
+            NotificationDaemon::Auto => {}
+        }
+    });
+}
+
+// Focus Assist has no public API; this drives the same WNF state the
+// Action Center toggle uses. Profiles: 0 = off, 1 = priority only, 2 = alarms only.
+#[cfg(windows)]
+mod focus_assist {
+    use std::{ffi::c_void, ptr, sync::Mutex};
+
+    const WNF_SHEL_QUIETHOURS_ACTIVE_PROFILE_CHANGED: u64 = 0x0D83_063E_A3BF_1C75;
+    const ALARMS_ONLY: u32 = 2;
+
+    // Profile to go back to once Work is over
+    static PREVIOUS_PROFILE: Mutex<Option<u32>> = Mutex::new(None);
+
+    #[link(name = "ntdll")]
+    extern "system" {
+        fn NtQueryWnfStateData(
+            state_name: *const u64,
+            type_id: *const c_void,
+            explicit_scope: *const c_void,
+            change_stamp: *mut u32,
+            buffer: *mut c_void,
+            buffer_size: *mut u32,
+        ) -> i32;
+
+        fn NtUpdateWnfStateData(
+            state_name: *const u64,
+            buffer: *const c_void,
+            length: u32,
+            type_id: *const c_void,
+            explicit_scope: *const c_void,
+            matching_change_stamp: u32,
+            check_stamp: u32,
+        ) -> i32;
+    }
+
+    fn query_profile() -> Option<u32> {
+        let mut profile = 0u32;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let mut stamp = 0u32;
+        let status = unsafe {
+            NtQueryWnfStateData(
+                &WNF_SHEL_QUIETHOURS_ACTIVE_PROFILE_CHANGED,
+                ptr::null(),
+                ptr::null(),
+                &mut stamp,
+                &mut profile as *mut u32 as *mut c_void,
+                &mut size,
+            )
+        };
+        (status >= 0).then_some(profile)
+    }
+
+    fn update_profile(profile: u32) {
+        let status = unsafe {
+            NtUpdateWnfStateData(
+                &WNF_SHEL_QUIETHOURS_ACTIVE_PROFILE_CHANGED,
+                &profile as *const u32 as *const c_void,
+                std::mem::size_of::<u32>() as u32,
+                ptr::null(),
+                ptr::null(),
+                0,
+                0,
+            )
+        };
+        if status < 0 {
+            eprintln!("Failed to set Focus Assist profile {profile}: NTSTATUS {status:#x}");
+        }
+    }
+
+    pub fn set_focused(focused: bool) {
+        let mut previous = PREVIOUS_PROFILE.lock().unwrap();
+        if focused {
+            if previous.is_none() {
+                *previous = Some(query_profile().unwrap_or(0));
+            }
+            update_profile(ALARMS_ONLY);
+        } else if let Some(profile) = previous.take() {
+            update_profile(profile);
+        }
+    }
+}
+
+// Enter focus when Work starts, leave it on any break
+pub fn on_phase_change(app: &AppHandle, phase: TimePhase) {
+    let integrations = read_settings(app).integrations;
+    let focused = phase == TimePhase::Work;
+
+    if cfg!(target_os = "macos") && integrations.macos_focus.enabled {
+        set_macos_focus(&integrations.macos_focus, focused);
+    }
+
+    #[cfg(windows)]
+    if integrations.windows_focus_assist {
+        focus_assist::set_focused(focused);
+    }
+
+    if cfg!(target_os = "linux") && integrations.linux_notification_pause.enabled {
+        set_notifications_paused(&integrations.linux_notification_pause, focused);
+    }
+}
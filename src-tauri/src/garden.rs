@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::Store;
+
+use crate::{events, with_store, Settings, TimePhase};
+
+const GARDEN_KEY: &str = "garden";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    #[default]
+    Seed,
+    Sprout,
+    Sapling,
+    Young,
+    // Planted into the forest with the next session, a new seed takes its place
+    Tree,
+}
+
+impl Stage {
+    fn next(self) -> Option<Stage> {
+        match self {
+            Stage::Seed => Some(Stage::Sprout),
+            Stage::Sprout => Some(Stage::Sapling),
+            Stage::Sapling => Some(Stage::Young),
+            Stage::Young => Some(Stage::Tree),
+            Stage::Tree => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GrownTree {
+    pub planted_at: DateTime<Utc>,
+    pub grown_at: DateTime<Utc>,
+}
+
+// One plant growing a stage per completed Work session. Skipping Work in
+// strict mode kills it, and the next session starts over from a seed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Garden {
+    pub stage: Stage,
+    pub planted_at: DateTime<Utc>,
+    pub forest: Vec<GrownTree>,
+    pub withered: i32,
+}
+
+impl Default for Garden {
+    fn default() -> Self {
+        Self {
+            stage: Stage::default(),
+            planted_at: Utc::now(),
+            forest: Vec::new(),
+            withered: 0,
+        }
+    }
+}
+
+fn load(store: &Store<Wry>) -> Garden {
+    store
+        .get(GARDEN_KEY)
+        .and_then(|value| from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, store: &mut Store<Wry>, garden: &Garden) {
+    if let Err(err) = store.insert(GARDEN_KEY.into(), json!(garden)) {
+        eprintln!("Failed to save the garden: {err}");
+    }
+    events::emit(app, "garden", garden);
+}
+
+// After a Work session was credited, takes the store from `advance_phase`
+pub fn grow(app: &AppHandle, store: &mut Store<Wry>) {
+    let mut garden = load(store);
+    match garden.stage.next() {
+        Some(stage) => garden.stage = stage,
+        None => {
+            garden.forest.push(GrownTree {
+                planted_at: garden.planted_at,
+                grown_at: Utc::now(),
+            });
+            garden.stage = Stage::Sprout;
+            garden.planted_at = Utc::now();
+        }
+    }
+    save(app, store, &garden);
+}
+
+// After the user skipped a phase, only a skipped Work session in strict
+// mode costs the plant
+pub fn on_skip(app: &AppHandle, store: &mut Store<Wry>, settings: &Settings, phase: TimePhase) {
+    if !settings.strict_mode || phase != TimePhase::Work {
+        return;
+    }
+    let mut garden = load(store);
+    if garden.stage == Stage::Seed {
+        return;
+    }
+    garden.withered += 1;
+    garden.stage = Stage::Seed;
+    garden.planted_at = Utc::now();
+    save(app, store, &garden);
+}
+
+pub fn publish(app: &AppHandle, store: &mut Store<Wry>) {
+    events::emit(app, "garden", load(store));
+}
+
+#[tauri::command]
+pub fn get_garden_state(app: AppHandle) -> Garden {
+    let mut garden = Garden::default();
+    with_store(&app, |store| {
+        garden = load(store);
+        Ok(())
+    });
+    garden
+}
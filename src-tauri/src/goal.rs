@@ -0,0 +1,219 @@
+use chrono::{Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use std::collections::{BTreeMap, HashMap};
+use tauri::AppHandle;
+
+use crate::{
+    days_off, events,
+    history::{self, SessionRecord},
+    read_settings, with_store, TimePhase,
+};
+
+const DEBT_KEY: &str = "goal_debt";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DailyGoal {
+    // Completed Work sessions a day, none means no goal
+    pub sessions: Option<i32>,
+    // What a day falls short by is added to the next one's goal, up to `max_debt`
+    pub carry_debt: bool,
+    pub max_debt: i32,
+}
+
+impl Default for DailyGoal {
+    fn default() -> Self {
+        Self {
+            sessions: None,
+            carry_debt: false,
+            max_debt: 4,
+        }
+    }
+}
+
+// Sessions owed, with every day up to and including `through` accounted for
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+struct Debt {
+    sessions: i32,
+    through: NaiveDate,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct GoalProgress {
+    // 0 on a day off
+    pub goal: i32,
+    pub day_off: bool,
+    // Carried over from earlier days, 0 unless `carry_debt` is on
+    pub debt: i32,
+    pub done: i32,
+    // goal + debt
+    pub target: i32,
+}
+
+// Days are UTC days like the stats
+fn sessions_per_day(records: &[SessionRecord]) -> HashMap<NaiveDate, i32> {
+    let mut days = HashMap::new();
+    for record in records
+        .iter()
+        .filter(|record| record.completed && record.phase == TimePhase::Work)
+    {
+        *days.entry(record.end.date_naive()).or_default() += 1;
+    }
+    days
+}
+
+// Brings the debt up to `yesterday`. A good day pays back what's owed, a
+// day off adds nothing, and nothing builds up while carrying debt over is off.
+fn settle(
+    mut debt: Debt,
+    per_day: &HashMap<NaiveDate, i32>,
+    days_off: &BTreeMap<NaiveDate, String>,
+    goal: &DailyGoal,
+    yesterday: NaiveDate,
+) -> Debt {
+    let days = (debt.through + Days::new(1))
+        .iter_days()
+        .take_while(|date| *date <= yesterday);
+    for date in days {
+        debt.sessions = match goal.sessions.filter(|_| goal.carry_debt) {
+            Some(target) => {
+                let target = if days_off.contains_key(&date) {
+                    0
+                } else {
+                    target
+                };
+                let done = per_day.get(&date).copied().unwrap_or(0);
+                (debt.sessions + target - done).clamp(0, goal.max_debt.max(0))
+            }
+            None => 0,
+        };
+        debt.through = date;
+    }
+    debt
+}
+
+// Settles the stored debt and applies `change` to it, None without a goal.
+// Debt starts counting the day after the first call.
+fn update(app: &AppHandle, change: impl FnOnce(&mut i32)) -> Option<GoalProgress> {
+    let goal = read_settings(app).daily_goal;
+    let sessions = goal.sessions?;
+    let today = Utc::now().date_naive();
+    let yesterday = today - Days::new(1);
+
+    let mut progress = None;
+    with_store(app, |store| {
+        let records = history::load(store);
+        let per_day = sessions_per_day(&records);
+        let days_off = days_off::load(store);
+        let stored = store
+            .get(DEBT_KEY)
+            .and_then(|value| from_value(value.clone()).ok())
+            .unwrap_or(Debt {
+                sessions: 0,
+                through: yesterday,
+            });
+        let mut debt = settle(stored, &per_day, &days_off, &goal, yesterday);
+        change(&mut debt.sessions);
+        debt.sessions = debt.sessions.max(0);
+        if debt != stored {
+            store.insert(DEBT_KEY.into(), json!(debt))?;
+        }
+
+        let day_off = days_off.contains_key(&today);
+        let goal = if day_off { 0 } else { sessions };
+        progress = Some(GoalProgress {
+            goal,
+            day_off,
+            debt: debt.sessions,
+            done: per_day.get(&today).copied().unwrap_or(0),
+            target: goal + debt.sessions,
+        });
+        Ok(())
+    });
+    progress
+}
+
+pub fn progress(app: &AppHandle) -> Option<GoalProgress> {
+    update(app, |_| {})
+}
+
+// After a phase change
+pub fn publish(app: &AppHandle) {
+    events::emit(app, "goal-progress", progress(app));
+}
+
+#[tauri::command]
+pub fn get_goal_progress(app: AppHandle) -> Option<GoalProgress> {
+    progress(&app)
+}
+
+// Wipes `sessions` off the debt, or all of it without
+#[tauri::command]
+pub fn forgive_debt(sessions: Option<i32>, app: AppHandle) -> Option<GoalProgress> {
+    let progress = update(&app, |debt| match sessions {
+        Some(sessions) => *debt -= sessions.max(0),
+        None => *debt = 0,
+    });
+    events::emit(&app, "goal-progress", progress);
+    progress
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn settle_carries_shortfalls_up_to_the_cap_and_pays_them_back() {
+        let goal = DailyGoal {
+            sessions: Some(4),
+            carry_debt: true,
+            max_debt: 4,
+        };
+        let per_day = HashMap::from([
+            (date("2024-03-01"), 3),
+            (date("2024-03-02"), 1),
+            (date("2024-03-04"), 6),
+        ]);
+        let days_off = BTreeMap::new();
+        let start = Debt {
+            sessions: 0,
+            through: date("2024-02-29"),
+        };
+
+        // 1 short, then 3 more, then capped at 4 on an empty day
+        let debt = settle(start, &per_day, &days_off, &goal, date("2024-03-03"));
+        assert_eq!(
+            debt,
+            Debt {
+                sessions: 4,
+                through: date("2024-03-03"),
+            }
+        );
+        // 6 against a goal of 4 pays back 2
+        assert_eq!(
+            settle(debt, &per_day, &days_off, &goal, date("2024-03-04")).sessions,
+            2
+        );
+
+        // Nothing was asked of a day off, so the whole 6 goes to the debt
+        let holiday = BTreeMap::from([(date("2024-03-04"), "Holiday".to_string())]);
+        assert_eq!(
+            settle(debt, &per_day, &holiday, &goal, date("2024-03-04")).sessions,
+            0
+        );
+
+        let off = DailyGoal {
+            carry_debt: false,
+            ..goal
+        };
+        assert_eq!(
+            settle(debt, &per_day, &days_off, &off, date("2024-03-04")).sessions,
+            0
+        );
+    }
+}
@@ -0,0 +1,221 @@
+// End-to-end runs of the real app, built with `cargo test --features harness`.
+// No window is opened, but the runtime and tray still need a display, e.g.
+// `xvfb-run cargo test --features harness` on CI.
+use chrono::{DateTime, Days, Utc};
+use serde_json::{json, Value};
+use std::{fs, path::PathBuf};
+use tauri::{App, AppHandle, Manager};
+
+use crate::{
+    app_builder, read_settings, switch_phase, timer, touch_last_opened, update_settings, wipe,
+    with_store, Phase, SessionNumber, Stats, TimePhase, STORE_PATH,
+};
+
+pub struct Harness {
+    app: App,
+    data_dir: PathBuf,
+}
+
+impl Harness {
+    // Launches over a store holding `stored`, keys left out get the defaults
+    pub fn with_store(stored: Value) -> Self {
+        let mut context = tauri::generate_context!();
+        context.config_mut().tauri.windows.clear();
+        context.config_mut().tauri.bundle.identifier =
+            format!("com.pomodorio.harness.{}", std::process::id());
+        let data_dir = tauri::api::path::app_data_dir(context.config())
+            .expect("no data directory for the harness store");
+        let _ = fs::remove_dir_all(&data_dir);
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::write(data_dir.join(STORE_PATH), stored.to_string()).unwrap();
+
+        let builder = tauri::Builder::default();
+        // Tests don't run on the main thread, macOS can't do without it
+        #[cfg(any(windows, target_os = "linux"))]
+        let builder = builder.any_thread();
+        let app = app_builder(builder, true)
+            .build(context)
+            .expect("error while building the harness app");
+        // Headless launches start counting down, tests move the cycle themselves
+        timer::set_running(&app.handle(), false);
+
+        Self { app, data_dir }
+    }
+
+    // Back to a fresh first launch, for the next scenario
+    pub fn reset(&self) {
+        wipe::reset(&self.handle()).unwrap();
+    }
+
+    pub fn handle(&self) -> AppHandle {
+        self.app.handle()
+    }
+
+    pub fn stored(&self, key: &str) -> Value {
+        let mut value = Value::Null;
+        with_store(&self.handle(), |store| {
+            value = store.get(key).cloned().unwrap_or_default();
+            Ok(())
+        });
+        value
+    }
+
+    pub fn set_stored(&self, key: &str, value: Value) {
+        with_store(&self.handle(), |store| store.insert(key.into(), value));
+    }
+
+    pub fn stats(&self) -> Stats {
+        serde_json::from_value(self.stored("stats")).unwrap()
+    }
+
+    pub fn phase(&self) -> TimePhase {
+        *self.app.state::<Phase>().0.lock().unwrap()
+    }
+
+    pub fn session_number(&self) -> i32 {
+        *self.app.state::<SessionNumber>().0.lock().unwrap()
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.data_dir);
+    }
+}
+
+fn yesterdays_stats() -> Value {
+    json!({
+        "today": { "minutes": 50, "sessions": 2 },
+        "week": { "minutes": 50, "sessions": 2 },
+        "total": { "minutes": 500, "sessions": 20 },
+    })
+}
+
+// Every scenario runs on one app, built on this test's thread. GTK can only
+// be used from the thread that initialized it, and libtest gives each test
+// its own. The launch is checked first, the rest start from a reset store.
+#[test]
+fn scenarios() {
+    let yesterday = Utc::now() - Days::new(1);
+    let harness = Harness::with_store(json!({
+        "last_opened": yesterday,
+        "stats": yesterdays_stats(),
+    }));
+    launching_on_a_new_day_rolls_today_over(&harness, yesterday);
+
+    let scenarios: [fn(&Harness); 4] = [
+        switch_phase_credits_finished_work_and_steps_back,
+        update_settings_changes_the_cycle_from_the_next_phase,
+        a_run_past_midnight_rolls_today_over,
+        the_share_link_follows_the_setting_once_started,
+    ];
+    for scenario in scenarios {
+        harness.reset();
+        scenario(&harness);
+    }
+}
+
+fn switch_phase_credits_finished_work_and_steps_back(harness: &Harness) {
+    assert_eq!(
+        (harness.phase(), harness.session_number()),
+        (TimePhase::Work, 0)
+    );
+
+    // Ran out on its own
+    switch_phase(false, false, harness.handle());
+    assert_eq!(
+        (harness.phase(), harness.session_number()),
+        (TimePhase::ShortBreak, 1)
+    );
+    let work_time = read_settings(&harness.handle()).work_time;
+    assert_eq!(harness.stats().today.sessions, 1);
+    assert_eq!(harness.stats().today.minutes, work_time);
+    assert_eq!(harness.stored("session_number"), json!(1));
+
+    // Previous takes the credit back along with the phase
+    switch_phase(true, true, harness.handle());
+    assert_eq!(
+        (harness.phase(), harness.session_number()),
+        (TimePhase::Work, 0)
+    );
+    assert_eq!(harness.stats().today.sessions, 0);
+
+    // Skipped Work isn't credited
+    switch_phase(false, true, harness.handle());
+    assert_eq!(harness.phase(), TimePhase::ShortBreak);
+    assert_eq!(harness.stats().today.sessions, 0);
+}
+
+fn update_settings_changes_the_cycle_from_the_next_phase(harness: &Harness) {
+    let mut settings = read_settings(&harness.handle());
+    settings.long_break_interval = 1;
+    update_settings(settings, harness.handle());
+
+    assert_eq!(harness.stored("settings")["long_break_interval"], json!(1));
+    assert_eq!(harness.phase(), TimePhase::Work);
+    switch_phase(false, true, harness.handle());
+    assert_eq!(harness.phase(), TimePhase::LongBreak);
+}
+
+fn launching_on_a_new_day_rolls_today_over(harness: &Harness, yesterday: DateTime<Utc>) {
+    let stats = harness.stats();
+    assert_eq!((stats.today.minutes, stats.today.sessions), (0, 0));
+    assert_eq!((stats.total.minutes, stats.total.sessions), (500, 20));
+    // Stamped for the next launch to compare against
+    assert_ne!(harness.stored("last_opened"), json!(yesterday));
+}
+
+fn a_run_past_midnight_rolls_today_over(harness: &Harness) {
+    let yesterday = Utc::now() - Days::new(1);
+    let stats = yesterdays_stats();
+
+    // Open since yesterday, the heartbeat is the first to see the new day
+    harness.set_stored("last_opened", json!(yesterday));
+    harness.set_stored("stats", stats.clone());
+    assert!(touch_last_opened(&harness.handle()));
+    assert_eq!(harness.stats().today.sessions, 0);
+
+    // Or a session ending before the heartbeat comes round
+    harness.set_stored("last_opened", json!(yesterday));
+    harness.set_stored("stats", stats);
+    switch_phase(false, false, harness.handle());
+    let stats = harness.stats();
+    assert_eq!((stats.today.sessions, stats.total.sessions), (1, 21));
+    assert_ne!(harness.stored("last_opened"), json!(yesterday));
+}
+
+#[cfg(feature = "http-api")]
+fn the_share_link_follows_the_setting_once_started(harness: &Harness) {
+    use crate::share::SHARE_PORT;
+    use std::{thread, time::Duration};
+
+    // None while nothing listens
+    let status_of = |url: &str| match ureq::get(url).call() {
+        Ok(response) => Some(response.status()),
+        Err(ureq::Error::Status(code, _)) => Some(code),
+        Err(_) => None,
+    };
+    harness.set_stored("share_token", json!("harness"));
+    let url = format!("http://127.0.0.1:{SHARE_PORT}/share/harness.json");
+    let share_link = |enabled| {
+        let mut settings = read_settings(&harness.handle());
+        settings.share_link = enabled;
+        update_settings(settings, harness.handle());
+    };
+
+    share_link(true);
+    // The server binds on its own thread
+    let status = (0..20).find_map(|_| {
+        thread::sleep(Duration::from_millis(100));
+        status_of(&url)
+    });
+    assert_eq!(status, Some(200));
+
+    share_link(false);
+    assert_eq!(status_of(&url), Some(404));
+    share_link(true);
+    assert_eq!(status_of(&url), Some(200));
+}
+
+#[cfg(not(feature = "http-api"))]
+fn the_share_link_follows_the_setting_once_started(_harness: &Harness) {}
@@ -0,0 +1,106 @@
+use serde::Serialize;
+#[cfg(feature = "integrations-slack")]
+use serde_json::Value;
+use std::{collections::HashMap, sync::Mutex, thread, time::Duration};
+use tauri::{AppHandle, Manager};
+
+#[cfg(feature = "integrations-slack")]
+use crate::secrets;
+use crate::{events, power, read_settings};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(300);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+#[cfg(feature = "integrations-slack")]
+const SLACK_AUTH_TEST_URL: &str = "https://slack.com/api/auth.test";
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum IntegrationStatus {
+    Connected,
+    // Unreachable or failing, calls are being retried or parked
+    Degraded,
+    // The token is missing, expired or was revoked
+    AuthExpired,
+}
+
+// Latest status of every configured integration, by name
+#[derive(Default)]
+pub struct IntegrationHealth(Mutex<HashMap<String, IntegrationStatus>>);
+
+// Emits the whole map rather than one entry, so the replayed event still
+// covers every integration
+pub fn report(app: &AppHandle, integration: &str, status: IntegrationStatus) {
+    let snapshot = {
+        let health = app.state::<IntegrationHealth>();
+        let mut health = health.0.lock().unwrap();
+        if health.get(integration) == Some(&status) {
+            return;
+        }
+        health.insert(integration.to_string(), status);
+        health.clone()
+    };
+    events::emit(app, "integration-status", snapshot);
+}
+
+fn check_webhook(agent: &ureq::Agent, url: &str) -> IntegrationStatus {
+    match agent.head(url).call() {
+        Err(ureq::Error::Transport(_)) => IntegrationStatus::Degraded,
+        Err(ureq::Error::Status(code, _)) if code >= 500 => IntegrationStatus::Degraded,
+        _ => IntegrationStatus::Connected,
+    }
+}
+
+#[cfg(feature = "integrations-slack")]
+fn check_slack(app: &AppHandle, agent: &ureq::Agent) -> IntegrationStatus {
+    let Some(token) = secrets::get(app, "slack") else {
+        return IntegrationStatus::AuthExpired;
+    };
+    let response = agent
+        .post(SLACK_AUTH_TEST_URL)
+        .set("Authorization", &format!("Bearer {token}"))
+        .call();
+    match response.map(|response| response.into_json::<Value>()) {
+        Ok(Ok(body)) if body["ok"] == true => IntegrationStatus::Connected,
+        Ok(Ok(_)) | Err(ureq::Error::Status(401 | 403, _)) => IntegrationStatus::AuthExpired,
+        _ => IntegrationStatus::Degraded,
+    }
+}
+
+// Checks in on every configured integration now and then, so a broken one
+// shows up before the next phase change tries to use it
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || {
+        let agent = ureq::AgentBuilder::new().timeout(CHECK_TIMEOUT).build();
+        loop {
+            if power::low_power(&app) {
+                thread::sleep(CHECK_INTERVAL);
+                continue;
+            }
+            let integrations = read_settings(&app).integrations;
+            if !integrations.webhooks.is_empty() {
+                let all_connected = integrations
+                    .webhooks
+                    .iter()
+                    .all(|url| check_webhook(&agent, url) == IntegrationStatus::Connected);
+                let status = if all_connected {
+                    IntegrationStatus::Connected
+                } else {
+                    IntegrationStatus::Degraded
+                };
+                report(&app, "webhook", status);
+            }
+            #[cfg(feature = "integrations-slack")]
+            if integrations.slack_status {
+                report(&app, "slack", check_slack(&app, &agent));
+            }
+            thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_integration_status(
+    health: tauri::State<IntegrationHealth>,
+) -> HashMap<String, IntegrationStatus> {
+    health.0.lock().unwrap().clone()
+}
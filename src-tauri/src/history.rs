@@ -0,0 +1,471 @@
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use std::{collections::BTreeMap, sync::Mutex, thread, time::Duration};
+use tauri::{AppHandle, ClipboardManager, Manager, Wry};
+use tauri_plugin_store::Store;
+
+use crate::{
+    days_off, get_from_store, goal, read_settings, with_store, ActiveProfile, ActiveTag,
+    ActiveTask, Error, Stat, Stats, TimePhase,
+};
+
+const HISTORY_KEY: &str = "history";
+// Sessions per page of `query_sessions` when the filter sets no limit
+const DEFAULT_PAGE_SIZE: usize = 100;
+// What the sessions pruned from the history added up to per day
+const DAILY_TOTALS_KEY: &str = "daily_totals";
+const PRUNE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+pub use pomodorio_core::history::{daily_totals, DailyTotal, SessionRecord};
+
+// When the current phase started, for the record written once it ends
+pub struct PhaseStart(Mutex<DateTime<Utc>>);
+
+impl Default for PhaseStart {
+    fn default() -> Self {
+        Self(Mutex::new(Utc::now()))
+    }
+}
+
+pub fn mark_phase_start(app: &AppHandle) {
+    *app.state::<PhaseStart>().0.lock().unwrap() = Utc::now();
+}
+
+pub fn phase_started_at(app: &AppHandle) -> DateTime<Utc> {
+    *app.state::<PhaseStart>().0.lock().unwrap()
+}
+
+// Wall clock time since the phase started, pauses included
+pub fn elapsed_minutes(app: &AppHandle) -> i32 {
+    let start = *app.state::<PhaseStart>().0.lock().unwrap();
+    (Utc::now() - start).num_minutes() as i32
+}
+
+pub fn load(store: &Store<Wry>) -> Vec<SessionRecord> {
+    store
+        .get(HISTORY_KEY)
+        .and_then(|value| from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn save(store: &mut Store<Wry>, records: &[SessionRecord]) {
+    if let Err(err) = store.insert(HISTORY_KEY.into(), json!(records)) {
+        eprintln!("Failed to save session history: {err}");
+    }
+}
+
+pub fn record(
+    app: &AppHandle,
+    store: &mut Store<Wry>,
+    phase: TimePhase,
+    minutes: i32,
+    completed: bool,
+) {
+    let record = SessionRecord {
+        start: *app.state::<PhaseStart>().0.lock().unwrap(),
+        end: Utc::now(),
+        phase,
+        minutes,
+        completed,
+        tag: app.state::<ActiveTag>().0.lock().unwrap().clone(),
+        profile: app.state::<ActiveProfile>().0.lock().unwrap().clone(),
+        task: app.state::<ActiveTask>().0.lock().unwrap().clone(),
+        note: None,
+    };
+    push(store, record);
+}
+
+pub fn push(store: &mut Store<Wry>, record: SessionRecord) {
+    let mut records = load(store);
+    records.push(record);
+    save(store, &records);
+}
+
+// Undo of the last stat credit, see `rewind_phase`
+pub fn remove_last_completed(store: &mut Store<Wry>) {
+    let mut records = load(store);
+    let last = records
+        .iter()
+        .rposition(|record| record.completed && record.phase == TimePhase::Work);
+    if let Some(index) = last {
+        records.remove(index);
+        save(store, &records);
+    }
+}
+
+fn for_profile(records: Vec<SessionRecord>, profile: Option<&str>) -> Vec<SessionRecord> {
+    match profile {
+        Some(profile) => records
+            .into_iter()
+            .filter(|record| record.profile.as_deref() == Some(profile))
+            .collect(),
+        None => records,
+    }
+}
+
+// Same windows as `check_stat_reset`: the UTC day and ISO week
+fn stats_from(records: &[SessionRecord], now: DateTime<Utc>) -> Stats {
+    let mut stats = Stats::default();
+    let completed = records
+        .iter()
+        .filter(|record| record.completed && record.phase == TimePhase::Work);
+    for record in completed {
+        let add = |stat: &mut Stat| {
+            stat.minutes += record.minutes;
+            stat.sessions += 1;
+        };
+        if record.end.date_naive() == now.date_naive() {
+            add(&mut stats.today);
+        }
+        if record.end.iso_week() == now.iso_week() {
+            add(&mut stats.week);
+        }
+        add(&mut stats.total);
+    }
+    stats
+}
+
+#[tauri::command]
+pub fn get_stats(profile: Option<String>, app: AppHandle) -> Result<Stats, Error> {
+    let mut result = Ok(Stats::default());
+    with_store(&app, |store| {
+        result = match &profile {
+            Some(profile) => Ok(stats_from(
+                &for_profile(load(store), Some(profile)),
+                Utc::now(),
+            )),
+            None => get_from_store(store, "stats"),
+        };
+        Ok(())
+    });
+    result
+}
+
+// Also serves as the export, the frontend saves it wherever the user picks
+#[tauri::command]
+pub fn get_history(profile: Option<String>, app: AppHandle) -> Vec<SessionRecord> {
+    let mut records = Vec::new();
+    with_store(&app, |store| {
+        records = for_profile(load(store), profile.as_deref());
+        Ok(())
+    });
+    records
+}
+
+// Every field narrows the result, unset ones match everything
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct SessionFilter {
+    // UTC days the session ended on, both ends included
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub phase: Option<TimePhase>,
+    pub tag: Option<String>,
+    pub task: Option<String>,
+    pub profile: Option<String>,
+    pub min_minutes: Option<i32>,
+    // Found anywhere in the note, ignoring case
+    pub note_text: Option<String>,
+    // Only sessions that ran out on their own, or only skipped ones
+    pub completed: Option<bool>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl SessionFilter {
+    fn matches(&self, record: &SessionRecord) -> bool {
+        let date = record.end.date_naive();
+        self.from.map_or(true, |from| date >= from)
+            && self.to.map_or(true, |to| date <= to)
+            && self.phase.map_or(true, |phase| record.phase == phase)
+            && self
+                .tag
+                .as_ref()
+                .map_or(true, |tag| record.tag.as_ref() == Some(tag))
+            && self
+                .task
+                .as_ref()
+                .map_or(true, |task| record.task.as_ref() == Some(task))
+            && self
+                .profile
+                .as_ref()
+                .map_or(true, |profile| record.profile.as_ref() == Some(profile))
+            && self
+                .min_minutes
+                .map_or(true, |minutes| record.minutes >= minutes)
+            && self
+                .completed
+                .map_or(true, |completed| record.completed == completed)
+            && self.note_text.as_ref().map_or(true, |text| {
+                let note = record.note.as_deref().unwrap_or_default();
+                note.to_lowercase().contains(&text.to_lowercase())
+            })
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SessionPage {
+    pub sessions: Vec<SessionRecord>,
+    // Matches across all pages
+    pub total: usize,
+}
+
+// Newest first
+fn query(records: Vec<SessionRecord>, filter: &SessionFilter) -> SessionPage {
+    let matching: Vec<_> = records
+        .into_iter()
+        .rev()
+        .filter(|record| filter.matches(record))
+        .collect();
+    SessionPage {
+        total: matching.len(),
+        sessions: matching
+            .into_iter()
+            .skip(filter.offset)
+            .take(filter.limit.unwrap_or(DEFAULT_PAGE_SIZE))
+            .collect(),
+    }
+}
+
+// A page of the history at a time, for browsing it once it's too long to
+// send over in one go like `get_history` does
+#[tauri::command]
+pub fn query_sessions(filter: SessionFilter, app: AppHandle) -> SessionPage {
+    let mut records = Vec::new();
+    with_store(&app, |store| {
+        records = load(store);
+        Ok(())
+    });
+    query(records, &filter)
+}
+
+// Sets the note on the session that started at `start`, an empty one clears it
+#[tauri::command]
+pub fn annotate_session(start: DateTime<Utc>, note: String, app: AppHandle) -> Result<(), Error> {
+    let mut result = Err(Error::UnknownSession(start));
+    with_store(&app, |store| {
+        let mut records = load(store);
+        let found = records
+            .iter_mut()
+            .rev()
+            .find(|record| record.start == start);
+        if let Some(record) = found {
+            record.note = Some(note.trim().to_string()).filter(|note| !note.is_empty());
+            save(store, &records);
+            result = Ok(());
+        }
+        Ok(())
+    });
+    result
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryRange {
+    Today,
+    Week,
+}
+
+fn format_minutes(minutes: i32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h {minutes}m"),
+    }
+}
+
+// "Today: 6 pomodoros, 2h 30m focused (writing 3, review 2)". The counts
+// come from the stats, the per-tag split from the history. Today's line
+// also says how much of the goal debt is still owed.
+fn summary(
+    stats: &Stats,
+    records: &[SessionRecord],
+    range: SummaryRange,
+    now: DateTime<Utc>,
+    owed: i32,
+) -> String {
+    let (label, stat) = match range {
+        SummaryRange::Today => ("Today", &stats.today),
+        SummaryRange::Week => ("This week", &stats.week),
+    };
+    let plural = if stat.sessions == 1 { "" } else { "s" };
+    let mut text = format!(
+        "{label}: {} pomodoro{plural}, {} focused",
+        stat.sessions,
+        format_minutes(stat.minutes)
+    );
+
+    let mut by_tag = BTreeMap::new();
+    let in_range = records.iter().filter(|record| {
+        record.completed
+            && record.phase == TimePhase::Work
+            && match range {
+                SummaryRange::Today => record.end.date_naive() == now.date_naive(),
+                SummaryRange::Week => record.end.iso_week() == now.iso_week(),
+            }
+    });
+    for record in in_range {
+        if let Some(tag) = &record.tag {
+            *by_tag.entry(tag.as_str()).or_insert(0) += 1;
+        }
+    }
+    if !by_tag.is_empty() {
+        let tags: Vec<String> = by_tag
+            .iter()
+            .map(|(tag, sessions)| format!("{tag} {sessions}"))
+            .collect();
+        text.push_str(&format!(" ({})", tags.join(", ")));
+    }
+    if range == SummaryRange::Today && owed > 0 {
+        text.push_str(&format!(", {owed} owed from earlier days"));
+    }
+    text
+}
+
+// For pasting into standup notes. Returns the text as well, so the
+// frontend can show what was copied.
+#[tauri::command]
+pub fn copy_summary_to_clipboard(range: SummaryRange, app: AppHandle) -> Result<String, Error> {
+    let owed = goal::progress(&app).map_or(0, |progress| progress.debt);
+    let mut result = Ok(String::new());
+    with_store(&app, |store| {
+        result = get_from_store::<Stats>(store, "stats")
+            .map(|stats| summary(&stats, &load(store), range, Utc::now(), owed));
+        Ok(())
+    });
+    let mut text = result?;
+    let days_off = days_off::read(&app);
+    let today = days_off.get(&Utc::now().date_naive());
+    if let Some(reason) = today.filter(|_| range == SummaryRange::Today) {
+        match reason.as_str() {
+            "" => text.push_str(", day off"),
+            reason => text.push_str(&format!(", day off ({reason})")),
+        }
+    }
+    app.clipboard_manager()
+        .write_text(text.clone())
+        .map_err(tauri::Error::from)?;
+    Ok(text)
+}
+
+fn load_daily_totals(store: &Store<Wry>) -> Vec<DailyTotal> {
+    store
+        .get(DAILY_TOTALS_KEY)
+        .and_then(|value| from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+// Per day, the sessions still in the history and the totals of the pruned
+// ones, for reports that should reach past the retention
+pub fn load_days(store: &Store<Wry>) -> BTreeMap<NaiveDate, DailyTotal> {
+    daily_totals(&load(store), &load_daily_totals(store))
+}
+
+// Splits off the sessions that ended before `cutoff`, folding them into the
+// per-day totals
+fn split_before(
+    records: Vec<SessionRecord>,
+    cutoff: NaiveDate,
+    totals: &mut Vec<DailyTotal>,
+) -> (Vec<SessionRecord>, usize) {
+    let (old, kept): (Vec<_>, Vec<_>) = records
+        .into_iter()
+        .partition(|record| record.end.date_naive() < cutoff);
+    *totals = daily_totals(&old, totals).into_values().collect();
+    (kept, old.len())
+}
+
+// Drops raw sessions older than the retention setting, keeping what they
+// added up to per day. Returns how many were dropped.
+fn prune(app: &AppHandle) -> usize {
+    let Some(months) = read_settings(app).history_retention_months else {
+        return 0;
+    };
+    let Some(cutoff) = Utc::now()
+        .date_naive()
+        .checked_sub_months(Months::new(months))
+    else {
+        return 0;
+    };
+
+    let mut pruned = 0;
+    with_store(app, |store| {
+        let mut totals = load_daily_totals(store);
+        let (kept, dropped) = split_before(load(store), cutoff, &mut totals);
+        if dropped > 0 {
+            save(store, &kept);
+            store.insert(DAILY_TOTALS_KEY.into(), json!(totals))?;
+        }
+        pruned = dropped;
+        Ok(())
+    });
+    pruned
+}
+
+#[tauri::command]
+pub fn prune_history(app: AppHandle) -> usize {
+    prune(&app)
+}
+
+// Per-day totals for the days whose sessions were pruned, oldest first
+#[tauri::command]
+pub fn get_daily_totals(app: AppHandle) -> Vec<DailyTotal> {
+    let mut totals = Vec::new();
+    with_store(&app, |store| {
+        totals = load_daily_totals(store);
+        Ok(())
+    });
+    totals
+}
+
+pub fn spawn_pruning(app: AppHandle) {
+    thread::spawn(move || loop {
+        let pruned = prune(&app);
+        if pruned > 0 {
+            eprintln!("Pruned {pruned} sessions past the history retention");
+        }
+        thread::sleep(PRUNE_INTERVAL);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_filters_by_task_and_note_text() {
+        let record = SessionRecord::ended(TimePhase::Work, "2024-03-04T09:25:00Z", 25);
+        let records = vec![
+            SessionRecord {
+                task: Some("review".into()),
+                note: Some("Chased the Flaky test".into()),
+                ..record.clone()
+            },
+            SessionRecord {
+                task: Some("review".into()),
+                ..record.clone()
+            },
+            SessionRecord {
+                note: Some("flaky again".into()),
+                ..record
+            },
+        ];
+
+        let by_task = SessionFilter {
+            task: Some("review".into()),
+            ..Default::default()
+        };
+        assert_eq!(query(records.clone(), &by_task).total, 2);
+        let by_note = SessionFilter {
+            note_text: Some("FLAKY".into()),
+            ..Default::default()
+        };
+        assert_eq!(query(records.clone(), &by_note).total, 2);
+        let both = SessionFilter {
+            note_text: Some("flaky".into()),
+            ..by_task
+        };
+        assert_eq!(query(records, &both).total, 1);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    process::{Child, Command, Stdio},
+    sync::Mutex,
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{read_settings, TimePhase};
+
+const BREAK_IDLE: &str = "break-idle";
+const WORK_SLEEP: &str = "work-sleep";
+
+// Inhibitor locks held by helper processes, keyed by purpose. Each helper
+// also watches our pid, so a crash never leaves a lock behind.
+#[derive(Default)]
+pub struct Inhibitors(Mutex<HashMap<&'static str, Child>>);
+
+fn inhibitor_command(what: &str, why: &str) -> Option<Command> {
+    let pid = std::process::id();
+
+    if cfg!(target_os = "linux") {
+        let mut command = Command::new("systemd-inhibit");
+        command.args([
+            &format!("--what={what}"),
+            "--who=Pomodorio",
+            &format!("--why={why}"),
+            "--mode=block",
+            "sh",
+            "-c",
+            &format!("while kill -0 {pid} 2>/dev/null; do sleep 5; done"),
+        ]);
+        Some(command)
+    } else if cfg!(target_os = "macos") {
+        // caffeinate has no sleep/idle split, keep the display and system awake
+        let mut command = Command::new("caffeinate");
+        command.args(["-d", "-i", "-w", &pid.to_string()]);
+        Some(command)
+    } else {
+        None
+    }
+}
+
+pub fn acquire(app: &AppHandle, key: &'static str, what: &str, why: &str) {
+    let inhibitors = app.state::<Inhibitors>();
+    let mut inhibitors = inhibitors.0.lock().unwrap();
+    if inhibitors.contains_key(key) {
+        return;
+    }
+
+    let Some(mut command) = inhibitor_command(what, why) else {
+        eprintln!("Inhibiting {what} is not supported on this platform");
+        return;
+    };
+    match command.stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+        Ok(child) => {
+            inhibitors.insert(key, child);
+        }
+        Err(err) => eprintln!("Failed to inhibit {what}: {err}"),
+    }
+}
+
+pub fn release(app: &AppHandle, key: &'static str) {
+    let inhibitors = app.state::<Inhibitors>();
+    let child = inhibitors.0.lock().unwrap().remove(key);
+    if let Some(mut child) = child {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+pub fn release_all(app: &AppHandle) {
+    let inhibitors = app.state::<Inhibitors>();
+    let mut inhibitors = inhibitors.0.lock().unwrap();
+    for (_, mut child) in inhibitors.drain() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+pub fn on_phase_change(app: &AppHandle, phase: TimePhase) {
+    let settings = read_settings(app);
+
+    // Keep the screen from locking in the middle of a break, let it go once Work resumes
+    if settings.inhibit_idle_on_break && phase != TimePhase::Work {
+        acquire(app, BREAK_IDLE, "idle", "Break in progress");
+    } else {
+        release(app, BREAK_IDLE);
+    }
+
+    // Deep work can look idle, so don't let the machine suspend during Work
+    if settings.inhibit_sleep_on_work && phase == TimePhase::Work {
+        acquire(app, WORK_SLEEP, "idle:sleep", "Work session in progress");
+    } else {
+        release(app, WORK_SLEEP);
+    }
+}
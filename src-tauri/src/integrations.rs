@@ -0,0 +1,142 @@
+#[cfg(feature = "integrations-slack")]
+use chrono::Duration;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    outbound::{self, OutboundRequest},
+    power, read_settings, secrets,
+    timer::Timer,
+    SessionNumber, TimePhase,
+};
+
+#[cfg(feature = "integrations-slack")]
+const SLACK_PROFILE_URL: &str = "https://slack.com/api/users.profile.set";
+
+// Publishes phase changes to an ntfy topic, for phone notifications through
+// the ntfy app. Protected topics take an access token from the "ntfy" secret.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Ntfy {
+    pub topic: Option<String>,
+    // ntfy.sh or a self-hosted server
+    pub server: String,
+}
+
+impl Default for Ntfy {
+    fn default() -> Self {
+        Self {
+            topic: None,
+            server: "https://ntfy.sh".to_string(),
+        }
+    }
+}
+
+fn webhook(url: &str, phase: TimePhase, session_number: i32) -> OutboundRequest {
+    OutboundRequest {
+        integration: "webhook".into(),
+        method: "POST".into(),
+        url: url.to_string(),
+        body: Some(json!({
+            "event": "phase-changed",
+            "phase": phase,
+            "label": phase.label(),
+            "session_number": session_number,
+            "at": Utc::now(),
+        })),
+        bearer_secret: None,
+        basic_secret: None,
+        content_type: None,
+    }
+}
+
+// Focusing status for the length of a Work session, cleared on breaks.
+// The user token is kept under the "slack" secret.
+#[cfg(feature = "integrations-slack")]
+fn slack_status(phase: TimePhase, remaining_secs: i32) -> OutboundRequest {
+    let profile = if phase == TimePhase::Work {
+        let until = Utc::now() + Duration::seconds(remaining_secs as i64);
+        json!({
+            "status_text": "Focusing",
+            "status_emoji": ":tomato:",
+            "status_expiration": until.timestamp(),
+        })
+    } else {
+        json!({ "status_text": "", "status_emoji": "", "status_expiration": 0 })
+    };
+    OutboundRequest {
+        integration: "slack".into(),
+        method: "POST".into(),
+        url: SLACK_PROFILE_URL.into(),
+        body: Some(json!({ "profile": profile })),
+        bearer_secret: Some("slack".into()),
+        basic_secret: None,
+        content_type: None,
+    }
+}
+
+fn ntfy_message(
+    server: &str,
+    topic: &str,
+    phase: TimePhase,
+    remaining_secs: i32,
+    with_token: bool,
+) -> OutboundRequest {
+    let message = format!(
+        "{} for {} minutes",
+        phase.label(),
+        (remaining_secs + 59) / 60
+    );
+    let tags = match phase {
+        TimePhase::Work => ["tomato"],
+        TimePhase::ShortBreak | TimePhase::LongBreak => ["coffee"],
+    };
+    OutboundRequest {
+        integration: "ntfy".into(),
+        method: "POST".into(),
+        url: server.trim_end_matches('/').to_string(),
+        body: Some(json!({
+            "topic": topic,
+            "title": "Pomodorio",
+            "message": message,
+            "tags": tags,
+        })),
+        bearer_secret: with_token.then(|| "ntfy".to_string()),
+        basic_secret: None,
+        content_type: None,
+    }
+}
+
+pub fn on_phase_change(app: &AppHandle, phase: TimePhase) {
+    if power::low_power(app) {
+        return;
+    }
+    let integrations = read_settings(app).integrations;
+    let session_number = *app.state::<SessionNumber>().0.lock().unwrap();
+
+    for url in &integrations.webhooks {
+        outbound::send(app, webhook(url, phase, session_number));
+    }
+    // Left out of builds without the integration, the setting is ignored
+    #[cfg(feature = "integrations-slack")]
+    if integrations.slack_status {
+        let remaining_secs = app.state::<Timer>().0.lock().unwrap().remaining_secs;
+        outbound::send(app, slack_status(phase, remaining_secs));
+    }
+    if let Some(topic) = &integrations.ntfy.topic {
+        let remaining_secs = app.state::<Timer>().0.lock().unwrap().remaining_secs;
+        let with_token = secrets::get(app, "ntfy").is_some();
+        outbound::send(
+            app,
+            ntfy_message(
+                &integrations.ntfy.server,
+                topic,
+                phase,
+                remaining_secs,
+                with_token,
+            ),
+        );
+    }
+}
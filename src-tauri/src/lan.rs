@@ -0,0 +1,379 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    emit_session_number, emit_switch_phase, events, get_remaining, history, on_phase_changed,
+    presence, secrets,
+    timer::{self, Timer},
+    with_store, Phase, SessionNumber, TimePhase,
+};
+
+const SERVICE_TYPE: &str = "_pomodorio._tcp.local.";
+const SYNC_PORT: u16 = 47_833;
+// Also corrects clock drift between the machines' countdowns
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+// Share one pomodoro cycle with other machines on the network that use
+// the same group name. Anyone on the network can see the group, so peers
+// also need the same "lan" secret, nothing is synced without one.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LanSync {
+    pub enabled: bool,
+    pub group: String,
+}
+
+impl Default for LanSync {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            group: "default".to_string(),
+        }
+    }
+}
+
+// One line of JSON per message. Every local change bumps the revision,
+// the highest revision wins and ties go to the lowest peer id.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct SharedState {
+    peer: String,
+    group: String,
+    revision: u64,
+    phase: TimePhase,
+    session_number: i32,
+    remaining_secs: i32,
+    running: bool,
+}
+
+// A state and its HMAC-SHA256 under the shared secret, base64
+#[derive(Serialize, Deserialize)]
+struct Signed {
+    state: SharedState,
+    mac: String,
+}
+
+fn mac(secret: &str, state: &SharedState) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes any key");
+    mac.update(&serde_json::to_vec(state).unwrap_or_default());
+    mac
+}
+
+fn sign(secret: &str, state: SharedState) -> Signed {
+    let mac = STANDARD.encode(self::mac(secret, &state).finalize().into_bytes());
+    Signed { state, mac }
+}
+
+fn verify(secret: &str, signed: &Signed) -> bool {
+    STANDARD
+        .decode(&signed.mac)
+        .is_ok_and(|tag| mac(secret, &signed.state).verify_slice(&tag).is_ok())
+}
+
+pub struct Lan {
+    peer_id: String,
+    // Both unset while sync is off or has no secret
+    group: Mutex<Option<String>>,
+    secret: Mutex<Option<String>>,
+    revision: Mutex<u64>,
+    // Outgoing connections, we only ever read from incoming ones
+    peers: Mutex<HashMap<String, TcpStream>>,
+}
+
+impl Default for Lan {
+    fn default() -> Self {
+        Self {
+            peer_id: format!("{:016x}", rand::random::<u64>()),
+            group: Mutex::new(None),
+            secret: Mutex::new(None),
+            revision: Mutex::new(0),
+            peers: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn snapshot(app: &AppHandle, lan: &Lan, group: String) -> SharedState {
+    let timer = app.state::<Timer>();
+    let timer = timer.0.lock().unwrap();
+    SharedState {
+        peer: lan.peer_id.clone(),
+        group,
+        revision: *lan.revision.lock().unwrap(),
+        phase: *app.state::<Phase>().0.lock().unwrap(),
+        session_number: *app.state::<SessionNumber>().0.lock().unwrap(),
+        remaining_secs: timer.remaining_secs_now(),
+        running: timer.running,
+    }
+}
+
+fn broadcast(app: &AppHandle) {
+    let lan = app.state::<Lan>();
+    let Some(group) = lan.group.lock().unwrap().clone() else {
+        return;
+    };
+    let Some(secret) = lan.secret.lock().unwrap().clone() else {
+        return;
+    };
+    let signed = sign(&secret, snapshot(app, &lan, group));
+    let Ok(mut line) = serde_json::to_string(&signed) else {
+        return;
+    };
+    line.push('\n');
+
+    lan.peers
+        .lock()
+        .unwrap()
+        .retain(|_, stream| stream.write_all(line.as_bytes()).is_ok());
+}
+
+// Call after anything the user or the local timer changed about the cycle
+pub fn local_change(app: &AppHandle) {
+    let lan = app.state::<Lan>();
+    if lan.group.lock().unwrap().is_none() {
+        return;
+    }
+    *lan.revision.lock().unwrap() += 1;
+    broadcast(app);
+}
+
+// Take over a peer's cycle without counting it as a local change
+fn apply(app: &AppHandle, state: &SharedState) {
+    let previous_phase =
+        std::mem::replace(&mut *app.state::<Phase>().0.lock().unwrap(), state.phase);
+    let phase_changed = previous_phase != state.phase;
+
+    *app.state::<SessionNumber>().0.lock().unwrap() = state.session_number;
+    // What completing the adopted phase credits, however far into it the peer is
+    let mut given_minutes = None;
+    with_store(app, |store| {
+        emit_session_number(app, store, state.session_number);
+        if phase_changed {
+            given_minutes = get_remaining(app, store).ok();
+        }
+        store.insert(
+            "session_number".into(),
+            serde_json::json!(state.session_number),
+        )
+    });
+
+    let timer_changed = {
+        let timer = app.state::<Timer>();
+        let mut timer = timer.0.lock().unwrap();
+        if phase_changed {
+            timer.given_minutes = given_minutes.unwrap_or((state.remaining_secs + 59) / 60);
+        }
+        // A second of jitter isn't worth a visible jump
+        let jumped = phase_changed || (timer.remaining_secs_now() - state.remaining_secs).abs() > 1;
+        if jumped {
+            timer.remaining_secs = state.remaining_secs;
+            timer.restart_clock();
+            timer::emit_tick(app, &timer);
+        }
+        let toggled = timer.running != state.running;
+        if toggled {
+            timer.set_running(state.running);
+            timer::emit_tick(app, &timer);
+            events::emit(app, "timer-running", timer.running);
+        }
+        jumped || toggled
+    };
+    timer::wake(app);
+
+    if phase_changed {
+        history::mark_phase_start(app);
+        emit_switch_phase(app, state.phase);
+        on_phase_changed(app, state.phase);
+    } else if timer_changed {
+        presence::update(app);
+    }
+}
+
+fn receive(app: &AppHandle, signed: Signed) {
+    let lan = app.state::<Lan>();
+    let verified = lan
+        .secret
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|secret| verify(secret, &signed));
+    if !verified {
+        eprintln!("Ignoring a sync message that isn't signed with the group's secret");
+        return;
+    }
+    let state = signed.state;
+    if state.peer == lan.peer_id || lan.group.lock().unwrap().as_ref() != Some(&state.group) {
+        return;
+    }
+
+    {
+        let mut revision = lan.revision.lock().unwrap();
+        let wins =
+            state.revision > *revision || (state.revision == *revision && state.peer < lan.peer_id);
+        if !wins {
+            return;
+        }
+        *revision = state.revision;
+    }
+    apply(app, &state);
+}
+
+fn handle_connection(app: AppHandle, stream: TcpStream) {
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        match serde_json::from_str::<Signed>(&line) {
+            Ok(signed) => receive(&app, signed),
+            Err(err) => eprintln!("Ignoring malformed sync message: {err}"),
+        }
+    }
+}
+
+fn connect(app: &AppHandle, peer: String, address: SocketAddr) {
+    let lan = app.state::<Lan>();
+    if lan.peers.lock().unwrap().contains_key(&peer) {
+        return;
+    }
+    match TcpStream::connect_timeout(&address, Duration::from_secs(3)) {
+        Ok(stream) => {
+            // A stuck peer shouldn't hold up everyone else
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(2)));
+            eprintln!("Syncing with {peer} at {address}");
+            lan.peers.lock().unwrap().insert(peer, stream);
+            broadcast(app);
+        }
+        Err(err) => eprintln!("Failed to connect to {peer} at {address}: {err}"),
+    }
+}
+
+// Connects to every peer, the group can change while running and messages
+// from other groups are dropped on arrival
+fn discover(app: AppHandle) -> Result<(), mdns_sd::Error> {
+    let lan = app.state::<Lan>();
+    let daemon = ServiceDaemon::new()?;
+    let properties = [("peer", lan.peer_id.as_str())];
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        &lan.peer_id,
+        &format!("{}.local.", lan.peer_id),
+        "",
+        SYNC_PORT,
+        &properties[..],
+    )?
+    .enable_addr_auto();
+    daemon.register(service)?;
+
+    let browser = daemon.browse(SERVICE_TYPE)?;
+    let own_id = lan.peer_id.clone();
+    thread::spawn(move || {
+        // Keeps the daemon, and with it our announcement, alive
+        let _daemon = daemon;
+        while let Ok(event) = browser.recv() {
+            match event {
+                ServiceEvent::ServiceResolved(info) => {
+                    let peer = info
+                        .get_property_val_str("peer")
+                        .unwrap_or_default()
+                        .to_string();
+                    let address = info.get_addresses().iter().find(|ip| ip.is_ipv4()).copied();
+                    let other = !peer.is_empty() && peer != own_id;
+                    if let (true, Some(address)) = (other, address) {
+                        connect(&app, peer, SocketAddr::new(address, info.get_port()));
+                    }
+                }
+                ServiceEvent::ServiceRemoved(_, fullname) => {
+                    let peer = fullname.split('.').next().unwrap_or_default();
+                    app.state::<Lan>().peers.lock().unwrap().remove(peer);
+                }
+                _ => {}
+            }
+        }
+    });
+    Ok(())
+}
+
+// Picks up the current group and secret, or stops syncing when turned off.
+// At launch and after the settings or the secret change.
+pub fn refresh(app: &AppHandle, config: &LanSync) {
+    let secret = config.enabled.then(|| secrets::get(app, "lan")).flatten();
+    if config.enabled && secret.is_none() {
+        eprintln!("LAN sync needs the group's secret, set the \"lan\" secret first");
+    }
+    let lan = app.state::<Lan>();
+    *lan.group.lock().unwrap() = secret.as_ref().map(|_| config.group.clone());
+    *lan.secret.lock().unwrap() = secret;
+}
+
+// Listens and browses from the first time sync is turned on, `refresh`
+// decides whether anything is sent or taken
+pub fn spawn(app: AppHandle) {
+    let listener = match TcpListener::bind((IpAddr::from([0, 0, 0, 0]), SYNC_PORT)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("LAN sync unavailable, port {SYNC_PORT} is taken: {err}");
+            return;
+        }
+    };
+
+    let incoming = app.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let app = incoming.clone();
+            thread::spawn(move || handle_connection(app, stream));
+        }
+    });
+
+    let heartbeat = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(HEARTBEAT_INTERVAL);
+        broadcast(&heartbeat);
+    });
+
+    if let Err(err) = discover(app) {
+        eprintln!("LAN sync discovery failed: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn get_lan_peers(lan: tauri::State<Lan>) -> Vec<String> {
+    lan.peers.lock().unwrap().keys().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> SharedState {
+        SharedState {
+            peer: "a".into(),
+            group: "default".into(),
+            revision: 3,
+            phase: TimePhase::Work,
+            session_number: 0,
+            remaining_secs: 1500,
+            running: true,
+        }
+    }
+
+    #[test]
+    fn only_messages_signed_with_the_same_secret_verify() {
+        let signed = sign("secret", state());
+        assert!(verify("secret", &signed));
+        assert!(!verify("guessed", &signed));
+
+        let mut tampered = sign("secret", state());
+        tampered.state.remaining_secs = 1;
+        assert!(!verify("secret", &tampered));
+    }
+}
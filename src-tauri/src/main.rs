@@ -1,72 +1,350 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tauri::{api::notification::Notification, AppHandle, Manager, Wry, CustomMenuItem, SystemTray, SystemTrayMenu, SystemTrayMenuItem};
-use chrono::{DateTime, Datelike, Utc};
+mod actions;
+mod analytics;
+mod announce;
+mod audio;
+mod audit;
+mod autostart;
+mod caldav;
+mod celebrate;
+mod challenge;
+mod commands;
+mod companion;
+mod config;
+mod control;
+mod crash;
+mod days_off;
+mod estimate;
+mod events;
+mod focus;
+mod garden;
+mod goal;
+#[cfg(all(test, feature = "harness"))]
+mod harness;
+mod health;
+mod history;
+mod inhibit;
+mod integrations;
+mod lan;
+mod noise;
+mod notification;
+mod onboarding;
+mod outbound;
+mod payload;
+mod persist;
+mod power;
+mod presence;
+mod presentation;
+mod quit;
+mod registry;
+mod remote;
+mod render;
+mod schedule;
+mod secrets;
+mod settings_history;
+mod share;
+mod silent;
+mod subsystems;
+mod suggest;
+mod timebox;
+mod timer;
+mod timesheet;
+mod tray;
+mod volume;
+mod watchdog;
+mod wellbeing;
+mod wipe;
+mod workspace;
+mod xp;
+
+use chrono::{DateTime, Local, Timelike, Utc};
+use pomodorio_core::{
+    cycle,
+    stats::{Stat, Stats},
+    store::STORE_FILE as STORE_PATH,
+    TimePhase,
+};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{from_value, json};
-use std::{path::PathBuf, sync::Mutex};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+use tauri::{
+    api::notification::Notification, AppHandle, CustomMenuItem, LogicalSize, Manager,
+    PhysicalPosition, RunEvent, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
+    Window, Wry,
+};
 use tauri_plugin_store::{Builder, Store, StoreBuilder, StoreCollection};
 
-const STORE_PATH: &str = ".store.dat";
+const MAIN_WINDOW: &str = "main";
+// How many past notifications are kept for review
+const NOTIFICATION_HISTORY_LEN: usize = 50;
+// Gap kept between the compact window and the screen corner, in logical pixels
+const COMPACT_MARGIN: f64 = 16.0;
+// How often `last_opened` is written while running, in case the app never gets to exit cleanly
+const LAST_OPENED_INTERVAL: Duration = Duration::from_secs(5 * 60);
 
-#[derive(PartialEq, Serialize, Clone, Copy, Debug)]
-enum TimePhase {
-    Work,
-    ShortBreak,
-    LongBreak,
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+enum WindowMode {
+    Compact,
+    Full,
 }
 
-impl Default for TimePhase {
+impl Default for WindowMode {
     fn default() -> Self {
-        Self::Work
+        Self::Full
     }
 }
 
-#[derive(Default, Serialize, Deserialize, Debug)]
-struct Stat {
-    minutes: i32,
-    sessions: i32,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Stats {
-    today: Stat,
-    week: Stat,
-    total: Stat,
+struct WindowLayout {
+    width: f64,
+    height: f64,
+    decorations: bool,
+    always_on_top: bool,
 }
 
-impl Default for Stats {
-    fn default() -> Self {
-        Self {
-            today: Stat::default(),
-            week: Stat::default(),
-            total: Stat::default(),
+impl WindowMode {
+    fn layout(&self) -> WindowLayout {
+        match self {
+            // Tiny timer pinned to a screen corner
+            WindowMode::Compact => WindowLayout {
+                width: 160.0,
+                height: 64.0,
+                decorations: false,
+                always_on_top: true,
+            },
+            // Matches the window declared in tauri.conf.json
+            WindowMode::Full => WindowLayout {
+                width: 280.0,
+                height: 354.0,
+                decorations: false,
+                always_on_top: false,
+            },
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
 struct Settings {
     work_time: i32,
     short_break_time: i32,
     long_break_time: i32,
     long_break_interval: i32,
+    // Keep the main window hidden at launch and live in the tray only
+    start_minimized: bool,
+    // Where to periodically render the countdown as a PNG, if anywhere
+    countdown_image_path: Option<String>,
+    integrations: Integrations,
+    // Stop the screen from idling into the lock screen during breaks
+    inhibit_idle_on_break: bool,
+    sounds: audio::NotificationSounds,
+    ambient: audio::AmbientSound,
+    ducking: audio::Ducking,
+    // Opt-in microphone level check during Work, nothing is recorded
+    noise_check: noise::NoiseCheck,
+    break_suggestions: Vec<suggest::BreakSuggestion>,
+    revert_stats_on_previous: bool,
+    // Sessions still run but never count, for demos and testing
+    practice_mode: bool,
+    tag_overrides: HashMap<String, TagOverride>,
+    // End breaks on clock boundaries every this many minutes, e.g. 30 for :00 and :30
+    align_breaks_to: Option<i32>,
+    lan_sync: lan::LanSync,
+    // Serve a read-only status page to the LAN, read when the server starts
+    share_link: bool,
+    // Serve a live page with the countdown and today's stats to the owner's phone, read when the server starts
+    lan_companion: bool,
+    // Whose entries `export_timesheet` writes, for the time tracker to match on import
+    timesheet: timesheet::TimesheetIdentity,
+    // Completed Work sessions to aim for each day, with shortfalls optionally carried over
+    daily_goal: goal::DailyGoal,
+    // Point out very long weeks, skipped long breaks and late nights, once a day each
+    wellbeing_alerts: bool,
+    // Seconds to wait after a phase runs out before switching, 0 switches immediately
+    grace_period_secs: i32,
+    // Breaks can only be cut short by adding what's left of them to the next one
+    strict_mode: bool,
+    // Named sets of statistics, e.g. "work" and "study". Empty disables profiles.
+    profiles: Vec<String>,
+    // Picks the tag from the foreground window when Work starts, first match wins
+    tag_rules: Vec<workspace::TagRule>,
+    // Hold back notifications, overlays and sounds while a full-screen app or screen sharing is on
+    mute_while_presenting: bool,
+    // Windows kept on top of everything during breaks, and let go of during Work
+    pin_on_break: Vec<registry::WindowKind>,
+    // Monitors the break overlay leaves alone, by the name `list_monitors` gives them
+    overlay_excluded_monitors: Vec<String>,
+    // Linux only: one resident notification with a progress bar for every phase
+    sticky_notification: bool,
+    // Log every command call with its duration, failed and slow ones are logged regardless
+    log_commands: bool,
+    // Loopback HTTP API for status bars and `--status`, on by default and
+    // always on in headless mode
+    control_api: bool,
+    // Start counting down at launch instead of waiting for the play button
+    auto_start: bool,
+    // Turn off ambient sound and integrations and update less often when the battery runs low
+    low_battery: power::LowBattery,
+    // URLs and apps opened when a phase starts
+    phase_actions: Vec<actions::PhaseAction>,
+    // Absolute paths of the apps phase actions may open
+    allowed_apps: Vec<String>,
+    // JSON file kept up to date with the phase and when it ends, for other tools
+    presence_file: Option<String>,
+    // Hold a logind idle and sleep inhibitor during Work
+    inhibit_sleep_on_work: bool,
+    // The countdown as the tray title, or today's pomodoros drawn onto the icon
+    tray_display: tray::TrayDisplay,
+    // Which optional items the tray menu shows
+    tray_menu: tray::TrayMenu,
+    // Confetti, sound and milestones when a Work session completes
+    celebration: celebrate::Celebration,
+    // Months of raw sessions to keep, older ones only count towards daily totals. None keeps all.
+    history_retention_months: Option<u32>,
+    // Settings and stats shared through a document on a WebDAV or S3-compatible server
+    remote_sync: remote::RemoteSync,
+}
+
+// Durations used while a tag is active. Unset fields fall back to the
+// global settings, so a tag only has to name what it changes.
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+struct TagOverride {
+    work_time: Option<i32>,
+    short_break_time: Option<i32>,
+    long_break_time: Option<i32>,
+    long_break_interval: Option<i32>,
+}
+
+impl Settings {
+    fn minutes_for(&self, phase: TimePhase) -> i32 {
+        match phase {
+            TimePhase::Work => self.work_time,
+            TimePhase::ShortBreak => self.short_break_time,
+            TimePhase::LongBreak => self.long_break_time,
+        }
+    }
+
+    fn with_tag(mut self, tag: Option<&str>) -> Self {
+        let Some(overrides) = tag.and_then(|tag| self.tag_overrides.get(tag)).cloned() else {
+            return self;
+        };
+        if let Some(value) = overrides.work_time {
+            self.work_time = value;
+        }
+        if let Some(value) = overrides.short_break_time {
+            self.short_break_time = value;
+        }
+        if let Some(value) = overrides.long_break_time {
+            self.long_break_time = value;
+        }
+        if let Some(value) = overrides.long_break_interval {
+            self.long_break_interval = value;
+        }
+        self
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug)]
+#[serde(default)]
+struct Integrations {
+    macos_focus: focus::MacosFocus,
+    // Switch Focus Assist to "Alarms only" during Work on Windows
+    windows_focus_assist: bool,
+    linux_notification_pause: focus::LinuxNotificationPause,
+    // URLs that get a JSON POST on every phase change
+    webhooks: Vec<String>,
+    // Show "Focusing" as the Slack status during Work
+    slack_status: bool,
+    ntfy: integrations::Ntfy,
+    // Publish the Work sessions of a timebox to a CalDAV calendar
+    caldav: caldav::CalDav,
 }
 
 impl Default for Settings {
     fn default() -> Self {
+        let lengths = cycle::Lengths::default();
         Self {
-            work_time: 25,
-            short_break_time: 5,
-            long_break_time: 20,
-            long_break_interval: 4,
+            work_time: lengths.work_time,
+            short_break_time: lengths.short_break_time,
+            long_break_time: lengths.long_break_time,
+            long_break_interval: lengths.long_break_interval,
+            start_minimized: false,
+            countdown_image_path: None,
+            integrations: Integrations::default(),
+            inhibit_idle_on_break: false,
+            sounds: audio::NotificationSounds::default(),
+            ambient: audio::AmbientSound::default(),
+            ducking: audio::Ducking::default(),
+            noise_check: noise::NoiseCheck::default(),
+            break_suggestions: suggest::default_suggestions(),
+            revert_stats_on_previous: true,
+            practice_mode: false,
+            tag_overrides: HashMap::new(),
+            align_breaks_to: None,
+            lan_sync: lan::LanSync::default(),
+            share_link: false,
+            lan_companion: false,
+            timesheet: timesheet::TimesheetIdentity::default(),
+            daily_goal: goal::DailyGoal::default(),
+            wellbeing_alerts: true,
+            grace_period_secs: 0,
+            strict_mode: false,
+            profiles: Vec::new(),
+            tag_rules: Vec::new(),
+            // macOS checks for full screen through System Events, which
+            // prompts for Automation access, so it's opt-in there
+            mute_while_presenting: !cfg!(target_os = "macos"),
+            pin_on_break: Vec::new(),
+            overlay_excluded_monitors: Vec::new(),
+            sticky_notification: false,
+            log_commands: false,
+            control_api: true,
+            auto_start: false,
+            low_battery: power::LowBattery::default(),
+            phase_actions: Vec::new(),
+            allowed_apps: Vec::new(),
+            presence_file: None,
+            inhibit_sleep_on_work: false,
+            tray_display: tray::TrayDisplay::default(),
+            tray_menu: tray::TrayMenu::default(),
+            celebration: celebrate::Celebration::default(),
+            history_retention_months: None,
+            remote_sync: remote::RemoteSync::default(),
         }
     }
 }
 
 struct Phase(Mutex<TimePhase>);
 struct SessionNumber(Mutex<i32>);
+struct ActiveTag(Mutex<Option<String>>);
+struct ActiveTask(Mutex<Option<String>>);
+struct ActiveProfile(Mutex<Option<String>>);
+
+// Minutes credited when the last Work session completed, kept while the
+// break it led to is current so going back can undo the credit
+#[derive(Default)]
+struct LastCredit(Mutex<Option<i32>>);
+
+#[derive(Serialize, Clone, Debug)]
+struct NotificationRecord {
+    at: DateTime<Utc>,
+    phase: TimePhase,
+    title: String,
+    body: String,
+}
+
+// Ring buffer of recent notifications, so phase changes missed while away can be reviewed
+#[derive(Default)]
+struct NotificationHistory(Mutex<VecDeque<NotificationRecord>>);
 
 #[derive(Debug, thiserror::Error)]
 enum Error {
@@ -75,6 +353,57 @@ enum Error {
 
     #[error(transparent)]
     Serde(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid sound file: {0}")]
+    InvalidSound(String),
+
+    #[error("Invalid timebox: {0}")]
+    InvalidTimebox(String),
+
+    #[error("Unknown profile: {0}")]
+    UnknownProfile(String),
+
+    #[error(transparent)]
+    Secret(#[from] keyring::Error),
+
+    #[error("Unknown secret: {0}")]
+    UnknownSecret(String),
+
+    #[error("No settings version {0}")]
+    UnknownSettingsVersion(u64),
+
+    #[error(transparent)]
+    Autostart(#[from] auto_launch::Error),
+
+    #[error("Onboarding: {0}")]
+    Onboarding(String),
+
+    #[error("Invalid phase action: {0}")]
+    InvalidAction(String),
+
+    #[error("The erase confirmation is invalid or expired")]
+    InvalidEraseToken,
+
+    #[error("{0} isn't part of this build")]
+    NotInBuild(&'static str),
+
+    #[error("Remote sync: {0}")]
+    Sync(String),
+
+    #[error("Pairing: {0}")]
+    Pairing(String),
+
+    #[error("Invalid silent mode: {0}")]
+    InvalidSilentMode(String),
+
+    #[error("No session started at {0}")]
+    UnknownSession(DateTime<Utc>),
 }
 
 // we must manually implement serde::Serialize
@@ -83,7 +412,9 @@ impl serde::Serialize for Error {
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        let message = self.to_string();
+        commands::record_error(&message);
+        serializer.serialize_str(message.as_ref())
     }
 }
 
@@ -101,38 +432,176 @@ fn get_from_store<'a, T: DeserializeOwned>(store: &mut Store<Wry>, key: &str) ->
     )?)
 }
 
+// Stored settings with the config file laid over them. Everything that acts
+// on settings reads them through here; only writes go to the store directly.
+fn load_settings(app: &AppHandle, store: &mut Store<Wry>) -> Result<Settings, Error> {
+    let mut settings: serde_json::Value = get_from_store(store, "settings")?;
+    config::apply(app, &mut settings);
+    Ok(from_value(settings)?)
+}
+
+fn read_settings(app: &AppHandle) -> Settings {
+    let mut settings = Settings::default();
+    with_store(app, |store| {
+        settings = load_settings(app, store).unwrap_or_default();
+        Ok(())
+    });
+    settings
+}
+
+// Read-modify-write of the stored settings, the config file's overrides
+// are left out so they never end up in the store
+fn modify_settings<F: FnOnce(&mut Settings)>(app: &AppHandle, f: F) -> Result<(), Error> {
+    let mut result = Ok(());
+    with_store(app, |store| {
+        let before = get_from_store::<serde_json::Value>(store, "settings");
+        match before.and_then(|before| Ok((from_value::<Settings>(before.clone())?, before))) {
+            Ok((mut settings, before)) => {
+                f(&mut settings);
+                store.insert("settings".into(), json!(settings))?;
+                settings_history::record(store, &before, &json!(settings), "command");
+            }
+            Err(err) => result = Err(err),
+        }
+        Ok(())
+    });
+    result
+}
+
+#[derive(Serialize, Clone)]
+struct PhaseSwitch {
+    schema_version: u32,
+    phase: TimePhase,
+    #[serde(flatten)]
+    times: timer::PhaseTimes,
+}
+
+// After the countdown is reset, so the times are the new phase's
+fn emit_switch_phase(app: &AppHandle, phase: TimePhase) {
+    let times = timer::phase_times(app);
+    let switch = PhaseSwitch {
+        schema_version: events::SCHEMA_VERSION,
+        phase,
+        times,
+    };
+    events::emit(app, "switch-phase", switch);
+}
+
+#[derive(Serialize, Clone)]
+struct PhaseLength {
+    schema_version: u32,
+    phase: TimePhase,
+    total_secs: i32,
+    remaining_secs: i32,
+}
+
+// After the countdown is reset as well
+fn emit_remaining(app: &AppHandle, minutes: i32) {
+    let length = PhaseLength {
+        schema_version: events::SCHEMA_VERSION,
+        phase: *app.state::<Phase>().0.lock().unwrap(),
+        total_secs: minutes * 60,
+        remaining_secs: app
+            .state::<timer::Timer>()
+            .0
+            .lock()
+            .unwrap()
+            .remaining_secs_now(),
+    };
+    events::emit(app, "remaining", length);
+}
+
+#[derive(Serialize, Clone)]
+struct SessionProgress {
+    schema_version: u32,
+    session_number: i32,
+    // Work sessions since the last long break, counting the current one
+    session_in_cycle: i32,
+    long_break_interval: i32,
+}
+
+fn emit_session_number(app: &AppHandle, store: &mut Store<Wry>, session_number: i32) {
+    let long_break_interval = effective_settings(app, store)
+        .map_or(Settings::default().long_break_interval, |settings| {
+            settings.long_break_interval
+        });
+    let progress = SessionProgress {
+        schema_version: events::SCHEMA_VERSION,
+        session_number,
+        session_in_cycle: cycle::session_in_cycle(session_number, long_break_interval),
+        long_break_interval,
+    };
+    events::emit(app, "session-number", progress);
+}
+
 fn set_phase(app: &AppHandle, new_phase: TimePhase) {
     let phase = app.state::<Phase>();
     *phase.0.lock().unwrap() = new_phase;
-    app.emit_all("switch-phase", phase.0.lock().unwrap().clone());
 }
 
-fn update_session_number(app: &AppHandle, previous_value: i32, is_previous: bool) -> i32 {
+fn update_session_number(
+    app: &AppHandle,
+    store: &mut Store<Wry>,
+    previous_value: i32,
+    is_previous: bool,
+) -> i32 {
     let session_number = app.state::<SessionNumber>();
 
     let new_value = if !is_previous {
-        previous_value + 1
+        cycle::next(previous_value)
     } else {
-        previous_value - 1
+        cycle::previous(previous_value).unwrap_or(0)
     };
 
     *session_number.0.lock().unwrap() = new_value;
+    // Persisted so the long break cadence survives a restart
+    let _ = store.insert("session_number".into(), json!(new_value));
 
-    app.emit_all("session-number", new_value);
+    emit_session_number(app, store, new_value);
     new_value
 }
 
+// Settings with the active tag's overrides applied. They take effect from
+// the next phase, a running countdown is never changed under the user.
+fn effective_settings(app: &AppHandle, store: &mut Store<Wry>) -> Result<Settings, Error> {
+    let settings = load_settings(app, store)?;
+    let tag = app.state::<ActiveTag>().0.lock().unwrap().clone();
+    Ok(settings.with_tag(tag.as_deref()))
+}
+
+#[tauri::command]
+fn get_effective_settings(app: AppHandle) -> Result<Settings, Error> {
+    let mut result = Ok(Settings::default());
+    with_store(&app, |store| {
+        result = effective_settings(&app, store);
+        Ok(())
+    });
+    result
+}
+
 fn get_remaining(app: &AppHandle, store: &mut Store<Wry>) -> Result<i32, Error> {
-    let settings: Settings = get_from_store(store, "settings")?;
+    let settings = effective_settings(app, store)?;
 
     let phase = app.state::<Phase>();
-    let value = match phase.0.lock().unwrap().clone() {
-        TimePhase::Work => settings.work_time,
-        TimePhase::ShortBreak => settings.short_break_time,
-        TimePhase::LongBreak => settings.long_break_time,
-    };
+    let phase = phase.0.lock().unwrap().clone();
+    let mut value = settings.minutes_for(phase);
+    if let (TimePhase::ShortBreak | TimePhase::LongBreak, Some(step)) =
+        (phase, settings.align_breaks_to)
+    {
+        value = aligned_break_minutes(Local::now(), value, step);
+    }
+
+    Ok(timebox::cap_minutes(app, value))
+}
 
-    Ok(value)
+// Length of a break ending on the first `step`-minute clock boundary that
+// still leaves at least `minimum` minutes of rest, to the nearest minute
+fn aligned_break_minutes(now: DateTime<Local>, minimum: i32, step: i32) -> i32 {
+    let step = step.max(1) as u32 * 60;
+    let now = now.num_seconds_from_midnight();
+    let earliest = now + minimum.max(0) as u32 * 60;
+    let boundary = earliest.div_ceil(step) * step;
+    ((boundary - now + 30) / 60) as i32
 }
 
 fn get_new_phase(
@@ -140,25 +609,26 @@ fn get_new_phase(
     store: &mut Store<Wry>,
     session_number: i32,
 ) -> Result<TimePhase, Error> {
-    let settings: Settings = get_from_store(store, "settings")?;
+    let settings = effective_settings(app, store)?;
 
-    let phase = app.state::<Phase>();
-    let long_break_interval = settings.long_break_interval;
+    Ok(cycle::phase_for_session(
+        session_number,
+        settings.long_break_interval,
+    ))
+}
 
-    let new_phase = if session_number % 2 == 1 {
-        if (session_number % (long_break_interval * 2 - 1)) == 0 {
-            Ok(TimePhase::LongBreak)
-        } else {
-            Ok(TimePhase::ShortBreak)
-        }
-    } else {
-        Ok(TimePhase::Work)
-    };
-    new_phase
+// Credits the length the phase was entered with. Recomputing it now would
+// see a timebox's deadline as already reached.
+fn update_stats(app: &AppHandle, store: &mut Store<Wry>) -> Result<i32, Error> {
+    let given_minutes = app.state::<timer::Timer>().0.lock().unwrap().given_minutes;
+    add_to_stats(store, given_minutes)?;
+    Ok(given_minutes)
 }
 
-fn update_stats(app: &AppHandle, store: &mut Store<Wry>) -> Result<(), Error> {
-    let elapsed_time = get_remaining(&app, store)?;
+// One more session of `elapsed_time` minutes, on today's stats even if
+// the heartbeat hasn't seen midnight pass yet
+fn add_to_stats(store: &mut Store<Wry>, elapsed_time: i32) -> Result<(), Error> {
+    check_stat_reset(store)?;
     let mut stats: serde_json::Value = get_from_store(store, "stats")?;
 
     for key in ["today", "week", "total"].iter() {
@@ -168,112 +638,508 @@ fn update_stats(app: &AppHandle, store: &mut Store<Wry>) -> Result<(), Error> {
         let sessions: i32 = from_value(stats[key]["sessions"].clone())?;
         stats[key]["sessions"] = json!(sessions + 1);
     }
-    store.insert("stats".into(), json!(stats));
+    store.insert("stats".into(), json!(stats))?;
     Ok(())
 }
 
-fn emit_status_notification(app: &AppHandle) {
-    let phase = app.state::<Phase>();
-    let body = match phase.0.lock().unwrap().clone() {
-        TimePhase::Work => "Time to get back to work!",
-        TimePhase::ShortBreak => "Have a little rest!",
-        TimePhase::LongBreak => "Take some extra time to relax!",
+fn revert_stats(store: &mut Store<Wry>, credited_minutes: i32) -> Result<(), Error> {
+    let mut stats: serde_json::Value = get_from_store(store, "stats")?;
+
+    // "today" or "week" may have been reset in between, never go below zero
+    for key in ["today", "week", "total"].iter() {
+        let minutes: i32 = from_value(stats[key]["minutes"].clone())?;
+        stats[key]["minutes"] = json!((minutes - credited_minutes).max(0));
+
+        let sessions: i32 = from_value(stats[key]["sessions"].clone())?;
+        stats[key]["sessions"] = json!((sessions - 1).max(0));
+    }
+    store.insert("stats".into(), json!(stats))?;
+    Ok(())
+}
+
+fn record_notification(app: &AppHandle, phase: TimePhase, title: &str, body: &str) {
+    let record = NotificationRecord {
+        at: Utc::now(),
+        phase,
+        title: title.to_string(),
+        body: body.to_string(),
     };
 
-    Notification::new(app.config().tauri.bundle.identifier.clone())
-        .title("Phase changed")
-        .body(body)
-        .show()
-        .unwrap();
+    let history = app.state::<NotificationHistory>();
+    let mut history = history.0.lock().unwrap();
+    if history.len() == NOTIFICATION_HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(record.clone());
+    events::emit_once(app, "notification-recorded", record);
+}
+
+fn emit_status_notification(app: &AppHandle, store: &mut Store<Wry>) {
+    let phase = app.state::<Phase>();
+    let phase = *phase.0.lock().unwrap();
+    let title = "Phase changed";
+    let mut body = phase.prompt().to_string();
+    let settings = load_settings(app, store).unwrap_or_default();
+
+    if phase != TimePhase::Work {
+        if let Some(suggestion) = suggest::pick(app, &settings.break_suggestions) {
+            body = format!("{body} {suggestion}");
+        }
+    }
+
+    record_notification(app, phase, title, &body);
+    notification::withdraw(app);
+    // Still in the history, just not on a screen everyone's looking at
+    if presentation::active(app) || silent::active(app) {
+        return;
+    }
+    let sticky = settings.sticky_notification;
+    if phase != TimePhase::Work || sticky {
+        let remaining_secs = app.state::<timer::Timer>().0.lock().unwrap().remaining_secs;
+        if notification::show_live(app, title, &body, remaining_secs, sticky) {
+            return;
+        }
+    }
+    let shown = Notification::new(app.config().tauri.bundle.identifier.clone())
+        .title(title)
+        .body(&body)
+        .show();
+    if let Err(err) = shown {
+        eprintln!("Couldn't show the notification, alerting in the app instead: {err}");
+        notification::alert_in_app(app, &settings, phase, title, &body);
+    }
+}
+
+#[tauri::command]
+fn get_notification_history(history: tauri::State<NotificationHistory>) -> Vec<NotificationRecord> {
+    history.0.lock().unwrap().iter().cloned().collect()
 }
 
 #[tauri::command]
 fn reset_phase(app: AppHandle) {
     with_store(&app, |store| {
         let remaining = get_remaining(&app, store).unwrap();
-        app.emit_all("remaining", remaining);
+        history::mark_phase_start(&app);
+        timer::reset(&app, remaining);
+        emit_remaining(&app, remaining);
         Ok(())
     });
+    presence::update(&app);
+    lan::local_change(&app);
 }
 
-#[tauri::command]
-fn switch_phase(
-    is_previous: bool,
-    is_user: bool,
-    app: AppHandle,
-    session_number_state: tauri::State<SessionNumber>,
-    phase_state: tauri::State<Phase>,
-) {
-    let session_number = *session_number_state.0.lock().unwrap();
-    let phase = phase_state.0.lock().unwrap().clone();
+// Strict mode ledger: the rest of a break ended early, in whole minutes,
+// owed to the next break. Kept in the store so quitting doesn't clear it.
+fn owe_break_time(app: &AppHandle, store: &mut Store<Wry>, phase: TimePhase) {
+    let strict_mode = load_settings(app, store).map_or(false, |settings| settings.strict_mode);
+    if !strict_mode || phase == TimePhase::Work {
+        return;
+    }
+
+    let remaining_secs = app
+        .state::<timer::Timer>()
+        .0
+        .lock()
+        .unwrap()
+        .remaining_secs_now();
+    let debt =
+        get_from_store::<i32>(store, "break_debt").unwrap_or_default() + (remaining_secs + 59) / 60;
+    let _ = store.insert("break_debt".into(), json!(debt));
+    events::emit(app, "break-debt", debt);
+}
 
+// Entering a break pays off the whole debt at once
+fn pay_break_debt(app: &AppHandle, store: &mut Store<Wry>) -> i32 {
+    let debt = get_from_store::<i32>(store, "break_debt").unwrap_or_default();
+    if debt > 0 {
+        let _ = store.insert("break_debt".into(), json!(0));
+        events::emit(app, "break-debt", 0);
+    }
+    debt
+}
+
+#[tauri::command]
+fn get_break_debt(app: AppHandle) -> i32 {
+    let mut debt = 0;
     with_store(&app, |store| {
-        if TimePhase::Work == phase && !(is_user || is_previous) {
-            update_stats(&app, store);
+        debt = get_from_store(store, "break_debt").unwrap_or_default();
+        Ok(())
+    });
+    debt
+}
+
+fn enter_phase(app: &AppHandle, store: &mut Store<Wry>, session_number: i32) -> TimePhase {
+    let new_phase = get_new_phase(app, store, session_number).unwrap();
+    set_phase(app, new_phase);
+    silent::on_phase_boundary(app);
+
+    let mut remaining = get_remaining(app, store).unwrap();
+    if new_phase != TimePhase::Work {
+        remaining = timebox::cap_minutes(app, remaining + pay_break_debt(app, store));
+    }
+    history::mark_phase_start(app);
+    timer::reset(app, remaining);
+    emit_remaining(app, remaining);
+    emit_switch_phase(app, new_phase);
+
+    // After the reset, so a live notification starts from the new length
+    emit_status_notification(app, store);
+    new_phase
+}
+
+fn advance_phase(app: &AppHandle, is_user: bool) {
+    let session_number = *app.state::<SessionNumber>().0.lock().unwrap();
+    let phase = app.state::<Phase>().0.lock().unwrap().clone();
+    let mut new_phase = phase;
+    let mut completed = None;
+
+    with_store(app, |store| {
+        // Only a Work session that ran out on its own counts
+        let settings = load_settings(app, store).unwrap_or_default();
+        let practice_mode = settings.practice_mode;
+        let credit = if TimePhase::Work == phase && !is_user && !practice_mode {
+            update_stats(app, store).ok()
+        } else {
+            None
+        };
+        *app.state::<LastCredit>().0.lock().unwrap() = credit;
+        completed = credit;
+        if !practice_mode {
+            let minutes = credit.unwrap_or_else(|| history::elapsed_minutes(app));
+            history::record(app, store, phase, minutes, !is_user);
+            xp::on_phase_end(app, store, phase, !is_user, credit);
+            if credit.is_some() {
+                garden::grow(app, store);
+            } else if is_user {
+                garden::on_skip(app, store, &settings, phase);
+            }
+        }
+        if is_user {
+            owe_break_time(app, store, phase);
         }
 
-        let session_number = update_session_number(&app, session_number, is_previous);
+        let session_number = update_session_number(app, store, session_number, false);
+        new_phase = enter_phase(app, store, session_number);
+        Ok(())
+    });
+    // Before the phase change can end a timebox and withdraw what's left of it
+    if completed.is_some() {
+        caldav::on_session_complete(app);
+    }
+    on_phase_changed(app, new_phase);
+    if let Some(minutes) = completed {
+        celebrate::on_session_complete(app, minutes);
+    }
+    lan::local_change(app);
+}
 
-        let new_phase = get_new_phase(&app, store, session_number).unwrap();
-        set_phase(&app, new_phase);
+// Step back one phase in the cycle. Leaving a break right after a completed
+// Work session takes that session's stat credit back, unless disabled.
+fn rewind_phase(app: &AppHandle) {
+    let session_number = *app.state::<SessionNumber>().0.lock().unwrap();
+    if cycle::previous(session_number).is_none() {
+        // Nothing before the first Work session, just start it over
+        reset_phase(app.clone());
+        return;
+    }
 
-        emit_status_notification(&app);
+    let credit = app.state::<LastCredit>().0.lock().unwrap().take();
+    let phase = *app.state::<Phase>().0.lock().unwrap();
+    let mut new_phase = TimePhase::Work;
 
-        let remaining = get_remaining(&app, store).unwrap();
-        app.emit_all("remaining", remaining);
+    with_store(app, |store| {
+        owe_break_time(app, store, phase);
+
+        if let Some(minutes) = credit {
+            let revert = load_settings(app, store)
+                .map_or(true, |settings| settings.revert_stats_on_previous);
+            if revert {
+                if let Err(err) = revert_stats(store, minutes) {
+                    eprintln!("Failed to revert stats: {err}");
+                }
+                history::remove_last_completed(store);
+            }
+        }
+
+        let session_number = update_session_number(app, store, session_number, true);
+        new_phase = enter_phase(app, store, session_number);
         Ok(())
     });
+    on_phase_changed(app, new_phase);
+    lan::local_change(app);
+}
+
+// Side effects of entering a phase that read the store themselves,
+// so they have to run after `with_store` has released it
+fn on_phase_changed(app: &AppHandle, phase: TimePhase) {
+    workspace::on_phase_change(app, phase);
+    focus::on_phase_change(app, phase);
+    inhibit::on_phase_change(app, phase);
+    announce::on_phase_change(app, phase);
+    audio::on_phase_change(app, phase);
+    registry::on_phase_change(app, phase);
+    timebox::on_phase_change(app);
+    integrations::on_phase_change(app, phase);
+    actions::on_phase_change(app, phase);
+    presence::update(app);
+    crash::update(app);
+    challenge::refresh(app);
+    analytics::publish_focus_score(app);
+    goal::publish(app);
+    wellbeing::check(app);
+    tray::refresh(app);
+}
+
+#[tauri::command]
+fn switch_phase(is_previous: bool, is_user: bool, app: AppHandle) {
+    if is_previous {
+        rewind_phase(&app);
+    } else {
+        advance_phase(&app, is_user);
+    }
+}
+
+fn set_active_tag(app: &AppHandle, tag: Option<String>) {
+    let active_tag = app.state::<ActiveTag>();
+    events::emit(app, "tag", &tag);
+    *active_tag.0.lock().unwrap() = tag;
+    tray::refresh(app);
+}
+
+#[tauri::command]
+fn set_tag(tag: Option<String>, app: AppHandle) {
+    set_active_tag(&app, tag);
+}
+
+// Recorded with the sessions from now on, alongside the tag
+#[tauri::command]
+fn set_task(task: Option<String>, app: AppHandle) {
+    events::emit(&app, "task", &task);
+    *app.state::<ActiveTask>().0.lock().unwrap() = task;
+}
+
+// Quick actions accepted on the command line, either at launch or
+// forwarded from a second instance: `pomodorio --skip`, `pomodorio --tag writing`
+fn handle_cli_args(app: &AppHandle, argv: &[String]) {
+    let mut args = argv.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--skip" => advance_phase(app, true),
+            "--tag" => set_active_tag(app, args.next().cloned()),
+            _ => {}
+        }
+    }
+}
+
+// Completed sessions are recorded against the active profile from now on
+#[tauri::command]
+fn set_profile(profile: Option<String>, app: AppHandle) -> Result<(), Error> {
+    if let Some(name) = &profile {
+        if !read_settings(&app).profiles.contains(name) {
+            return Err(Error::UnknownProfile(name.clone()));
+        }
+    }
+
+    with_store(&app, |store| {
+        store.insert("active_profile".into(), json!(profile))
+    });
+    events::emit(&app, "profile", &profile);
+    *app.state::<ActiveProfile>().0.lock().unwrap() = profile;
+    Ok(())
+}
+
+#[tauri::command]
+fn set_practice_mode(enabled: bool, app: AppHandle) -> Result<(), Error> {
+    modify_settings(&app, |settings| settings.practice_mode = enabled)?;
+    events::emit(&app, "practice-mode", enabled);
+    Ok(())
 }
 
 #[tauri::command]
 fn update_settings(settings: Settings, app: AppHandle) {
     with_store(&app, |store| {
-        store.insert("settings".into(), json!(settings));
+        let before = store.get("settings").cloned().unwrap_or_default();
+        // The page edits what `read_settings` gave it, overrides included
+        let stored = json!(from_value::<Settings>(before.clone()).unwrap_or_default());
+        let mut settings = json!(settings);
+        config::strip(&app, &mut settings, &stored);
+        store.insert("settings".into(), settings.clone())?;
+        settings_history::record(store, &before, &settings, "ui");
         Ok(())
     });
+    events::emit(&app, "settings-changed", read_settings(&app));
+    tray::refresh(&app);
+    subsystems::start_enabled(&app);
+}
+
+fn apply_window_mode(window: &Window, mode: WindowMode) -> Result<(), Error> {
+    let layout = mode.layout();
+
+    window.set_decorations(layout.decorations)?;
+    window.set_always_on_top(layout.always_on_top)?;
+    window.set_size(LogicalSize::new(layout.width, layout.height))?;
+
+    if mode == WindowMode::Compact {
+        // Pin the compact timer to the top-right corner of the current monitor
+        if let Some(monitor) = window.current_monitor()? {
+            let scale = monitor.scale_factor();
+            let x = monitor.position().x as f64 + monitor.size().width as f64
+                - (layout.width + COMPACT_MARGIN) * scale;
+            let y = monitor.position().y as f64 + COMPACT_MARGIN * scale;
+            window.set_position(PhysicalPosition::new(x, y))?;
+        }
+    } else {
+        window.center()?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
-fn restore_state(
-    app: AppHandle,
-    phase: tauri::State<Phase>,
-    session_number: tauri::State<SessionNumber>,
-) {
-    app.emit_all("switch-phase", phase.0.lock().unwrap().clone());
-    app.emit_all("session-number", *session_number.0.lock().unwrap());
+fn set_window_mode(mode: WindowMode, app: AppHandle) -> Result<(), Error> {
+    if let Some(window) = app.get_window(MAIN_WINDOW) {
+        apply_window_mode(&window, mode)?;
+    }
+
     with_store(&app, |store| {
-        let remaining = get_remaining(&app, store).unwrap();
-        app.emit_all("remaining", remaining);
+        store.insert("window_mode".into(), json!(mode))?;
         Ok(())
     });
+    events::emit(&app, "window-mode", mode);
+    Ok(())
 }
 
-// Check if the stats for yesterday or last week need resetting
-fn check_stat_reset(store: &mut Store<Wry>) -> Result<bool, Error> {
-    let last_opened: DateTime<Utc> = get_from_store(store, "last_opened")?;
-    let mut stats: Stats = get_from_store(store, "stats")?;
+// Seed the replay buffer, so the first page load already gets the full state
+fn publish_state(app: &AppHandle) {
+    let phase = *app.state::<Phase>().0.lock().unwrap();
+    emit_switch_phase(app, phase);
+    let tag = app.state::<ActiveTag>().0.lock().unwrap().clone();
+    events::emit(app, "tag", tag);
+    let task = app.state::<ActiveTask>().0.lock().unwrap().clone();
+    events::emit(app, "task", task);
+    let profile = app.state::<ActiveProfile>().0.lock().unwrap().clone();
+    events::emit(app, "profile", profile);
+    let running = app.state::<timer::Timer>().0.lock().unwrap().running;
+    events::emit(app, "timer-running", running);
+
+    with_store(app, |store| {
+        let remaining = get_remaining(app, store).unwrap();
+        emit_remaining(app, remaining);
+        let session_number = *app.state::<SessionNumber>().0.lock().unwrap();
+        emit_session_number(app, store, session_number);
 
-    let today = Utc::now();
+        let mode: WindowMode = get_from_store(store, "window_mode").unwrap_or_default();
+        events::emit(app, "window-mode", mode);
 
-    // If last opened is on a different year,
-    // or on a different day of the year
-    if today.year() != last_opened.year() || today.ordinal() != last_opened.ordinal() {
-        // Reset "today" on stats
-        stats.today = Stat::default();
-        store.insert("stats".into(), json!(stats));
-        return Ok(true);
+        let settings = load_settings(app, store).unwrap_or_default();
+        events::emit(app, "practice-mode", settings.practice_mode);
+
+        let debt: i32 = get_from_store(store, "break_debt").unwrap_or_default();
+        events::emit(app, "break-debt", debt);
+        garden::publish(app, store);
+        Ok(())
+    });
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_window(MAIN_WINDOW) {
+        let _ = window.show();
+        let _ = window.set_focus();
     }
-    if today.year() != last_opened.year()
-        || today.iso_week().week() != last_opened.iso_week().week()
-    {
-        // Reset "week" on stats
-        stats.week = Stat::default();
-        store.insert("stats".into(), json!(stats));
-        return Ok(true);
+    timer::wake(app);
+}
+
+fn handle_tray_event(app: &AppHandle, event: SystemTrayEvent) {
+    match event {
+        SystemTrayEvent::LeftClick { .. } => show_main_window(app),
+        SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
+            "quit" => quit::request_quit(app),
+            "hide" => {
+                if let Some(window) = app.get_window(MAIN_WINDOW) {
+                    let _ = window.hide();
+                }
+            }
+            id => {
+                tray::on_menu_click(app, id);
+            }
+        },
+        _ => {}
     }
-    return Ok(false);
 }
-fn main() {
+
+// Check if the stats for yesterday or last week need resetting, and stamp
+// `last_opened` for the next check. Also runs with the heartbeat and before
+// every credit, the app can stay open past midnight.
+fn check_stat_reset(store: &mut Store<Wry>) -> Result<bool, Error> {
+    let now = Utc::now();
+    let last_opened = store
+        .get("last_opened")
+        .and_then(|value| from_value::<DateTime<Utc>>(value.clone()).ok());
+    store.insert("last_opened".into(), json!(now))?;
+    // Unknown on the first launch, there's nothing to roll over yet
+    let Some(last_opened) = last_opened else {
+        return Ok(false);
+    };
+    let mut stats: Stats = get_from_store(store, "stats")?;
+    if !stats.roll_over(last_opened, now) {
+        return Ok(false);
+    }
+    store.insert("stats".into(), json!(stats))?;
+    Ok(true)
+}
+
+// Builds the one store every `with_store` goes through and hands it to the
+// plugin, which loads what's on disk over the defaults. Malformed values are
+// repaired and stats that rolled over while the app was closed are reset
+// before anything reads them.
+fn init_store(app: &AppHandle) -> Result<(), Error> {
+    let store = StoreBuilder::new(app.clone(), STORE_PATH.into())
+        .default("settings".into(), json!(Settings::default()))
+        .default("stats".into(), json!(Stats::default()))
+        .default("window_mode".into(), json!(WindowMode::default()))
+        .default("session_number".into(), json!(0))
+        .default("break_debt".into(), json!(0))
+        .default("history".into(), json!([]))
+        .build();
+    app.plugin(Builder::default().store(store).build())?;
+
+    let mut repairs = Vec::new();
+    with_store(app, |store| {
+        repairs = audit::repair_store(store);
+        Ok(())
+    });
+    // Rolls the stats over and saves the repairs along with it
+    touch_last_opened(app);
+    audit::report(app, repairs);
+    Ok(())
+}
+
+// What the next launch compares against, the writer thread saves it. True
+// if the stats rolled over.
+fn touch_last_opened(app: &AppHandle) -> bool {
+    let mut rolled_over = false;
+    with_store(app, |store| {
+        match check_stat_reset(store) {
+            Ok(rolled) => rolled_over = rolled,
+            Err(err) => eprintln!("Couldn't check whether the stats roll over: {err}"),
+        }
+        persist::save(app);
+        Ok(())
+    });
+    rolled_over
+}
+
+fn spawn_last_opened(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(LAST_OPENED_INTERVAL);
+        if touch_last_opened(&app) {
+            tray::refresh(&app);
+        }
+    });
+}
+
+// Everything but the single-instance plugin, which the test harness can't
+// have grabbing the instance lock
+fn app_builder(builder: tauri::Builder<Wry>, headless: bool) -> tauri::Builder<Wry> {
     let quit = CustomMenuItem::new("quit".to_string(), "Quit");
     let hide = CustomMenuItem::new("hide".to_string(), "Hide");
     let tray_menu = SystemTrayMenu::new()
@@ -285,31 +1151,243 @@ fn main() {
         .with_menu(tray_menu)
         .with_title("Pomodorio");
 
-    tauri::Builder::default()
-        .setup(|app| {
-            let store = StoreBuilder::new(app.handle(), STORE_PATH.into())
-                .default("settings".into(), json!(Settings::default()))
-                .default("stats".into(), json!(Stats::default()))
-                .default("last_opened".into(), json!(Utc::now()))
-                .build();
-            app.handle().plugin(Builder::default().store(store).build());
-            let mut store = StoreBuilder::new(app.handle(), STORE_PATH.into())
-                .default("settings".into(), json!(Settings::default()))
-                .default("stats".into(), json!(Stats::default()))
-                .default("last_opened".into(), json!(Utc::now()))
-                .build();
-            check_stat_reset(&mut store);
+    builder
+        .setup(move |app| {
+            app.manage(persist::Writer::spawn(app.handle()));
+            app.manage(outbound::Outbound::spawn(app.handle()));
+            init_store(&app.handle())?;
+            spawn_last_opened(app.handle());
+            config::spawn(app.handle());
+
+            // Reapply the window layout the user picked last time
+            let mut mode = WindowMode::default();
+            let mut settings = Settings::default();
+            with_store(&app.handle(), |store| {
+                mode = get_from_store(store, "window_mode").unwrap_or_default();
+                settings = load_settings(&app.handle(), store).unwrap_or_default();
+                Ok(())
+            });
+            if let Some(window) = app.get_window(MAIN_WINDOW) {
+                registry::register(&app.handle(), MAIN_WINDOW, registry::WindowKind::Main);
+                apply_window_mode(&window, mode)?;
+
+                // The window starts hidden; autostart users can leave it in the tray
+                if !settings.start_minimized {
+                    window.show()?;
+                }
+            }
+
+            // Pick the cycle back up where it was. A break cut short by
+            // quitting moves on to the Work session after it.
+            with_store(&app.handle(), |store| {
+                let mut session_number: i32 =
+                    get_from_store(store, "session_number").unwrap_or_default();
+                if session_number % 2 == 1 {
+                    session_number += 1;
+                }
+                *app.state::<SessionNumber>().0.lock().unwrap() = session_number;
+
+                // A profile removed from the settings in the meantime is dropped
+                let profile = store
+                    .get("active_profile")
+                    .and_then(|value| value.as_str())
+                    .map(str::to_string)
+                    .filter(|profile| settings.profiles.contains(profile));
+                *app.state::<ActiveProfile>().0.lock().unwrap() = profile;
+                store.insert("session_number".into(), json!(session_number))
+            });
+
+            with_store(&app.handle(), |store| {
+                let remaining = get_remaining(&app.handle(), store).unwrap();
+                timer::reset(&app.handle(), remaining);
+                Ok(())
+            });
+            // Before anything starts the timer and writes a new marker
+            crash::check_at_startup(&app.handle());
+            challenge::refresh(&app.handle());
+            timer::spawn(app.handle());
+            tray::spawn(app.handle());
+            watchdog::spawn(app.handle());
+            history::spawn_pruning(app.handle());
+            notification::check_at_startup(app.handle());
+            power::spawn(app.handle());
+            if headless {
+                subsystems::start_control_api(&app.handle());
+            }
+            subsystems::start_enabled(&app.handle());
+            // Without a frontend nothing else would start the countdown
+            if headless || settings.auto_start {
+                timer::set_running(&app.handle(), true);
+            }
+
+            publish_state(&app.handle());
+            presence::update(&app.handle());
+            tray::refresh(&app.handle());
+
+            let argv: Vec<String> = std::env::args().collect();
+            handle_cli_args(&app.handle(), &argv);
             Ok(())
         })
         .manage(Phase(Mutex::new(TimePhase::default())))
         .manage(SessionNumber(Mutex::new(0)))
+        .manage(ActiveTag(Mutex::new(None)))
+        .manage(ActiveTask(Mutex::new(None)))
+        .manage(ActiveProfile(Mutex::new(None)))
+        .manage(config::ConfigOverrides::default())
+        .manage(history::PhaseStart::default())
+        .manage(LastCredit::default())
+        .manage(timer::Timer::default())
+        .manage(NotificationHistory::default())
+        .manage(notification::LiveNotification::default())
+        .manage(inhibit::Inhibitors::default())
+        .manage(announce::Announcer::default())
+        .manage(audio::Audio::default())
+        .manage(events::EventReplay::default())
+        .manage(tray::CurrentDisplay::default())
+        .manage(tray::PendingRefresh::default())
+        .manage(watchdog::Heartbeats::default())
+        .manage(registry::WindowRegistry::default())
+        .manage(presentation::Presentation::default())
+        .manage(silent::SilentMode::default())
+        .manage(timebox::Timebox::default())
+        .manage(lan::Lan::default())
+        .manage(health::IntegrationHealth::default())
+        .manage(suggest::RecentSuggestions::default())
+        .manage(power::Power::default())
+        .manage(crash::PendingRecovery::default())
+        .manage(caldav::PublishedBlocks::default())
+        .manage(analytics::LastFocusScore::default())
+        .manage(wipe::EraseToken::default())
+        .manage(commands::CommandMetrics::default())
+        .manage(commands::LogCommands::default())
+        .manage(subsystems::Started::default())
         .system_tray(system_tray)
-        .invoke_handler(tauri::generate_handler![
+        .on_system_tray_event(handle_tray_event)
+        .on_page_load(events::on_page_load)
+        .on_window_event(|event| {
+            quit::on_window_event(&event);
+            registry::on_window_event(event);
+        })
+        .invoke_handler(commands::logged(tauri::generate_handler![
             switch_phase,
             reset_phase,
             update_settings,
-            restore_state
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+            set_practice_mode,
+            get_effective_settings,
+            get_break_debt,
+            set_window_mode,
+            set_tag,
+            set_task,
+            workspace::set_tag_rules,
+            set_profile,
+            history::get_stats,
+            history::get_history,
+            history::query_sessions,
+            history::annotate_session,
+            history::prune_history,
+            history::get_daily_totals,
+            timer::start_timer,
+            timer::pause_timer,
+            timer::extend_phase,
+            timer::skip_grace_period,
+            control::get_status_line,
+            control::get_state,
+            events::get_api_version,
+            events::get_build_features,
+            get_notification_history,
+            audio::set_notification_sound,
+            suggest::get_break_suggestion,
+            watchdog::heartbeat,
+            registry::open_window,
+            registry::close_window,
+            registry::list_windows,
+            registry::list_monitors,
+            presentation::get_presentation_mode,
+            presentation::set_presentation_mode,
+            silent::set_silent_mode,
+            goal::get_goal_progress,
+            goal::forgive_debt,
+            days_off::mark_day_off,
+            days_off::unmark_day_off,
+            days_off::get_days_off,
+            timebox::start_timebox,
+            timebox::cancel_timebox,
+            lan::get_lan_peers,
+            share::get_share_link,
+            share::reset_share_link,
+            companion::get_companion_link,
+            companion::reset_companion_link,
+            companion::get_pairing_qr,
+            settings_history::get_settings_history,
+            settings_history::revert_settings,
+            autostart::set_launch_at_login,
+            autostart::get_launch_at_login,
+            onboarding::get_onboarding_state,
+            onboarding::advance_onboarding,
+            notification::check_notification_permission,
+            power::get_power_state,
+            timer::run_timing_selftest,
+            schedule::get_upcoming_phases,
+            schedule::simulate_schedule,
+            actions::set_phase_actions,
+            history::copy_summary_to_clipboard,
+            quit::confirm_quit,
+            crash::resolve_crash_recovery,
+            garden::get_garden_state,
+            xp::get_level,
+            challenge::get_daily_challenge,
+            analytics::get_heatmap,
+            analytics::get_trends,
+            analytics::get_deep_work_blocks,
+            analytics::get_focus_score,
+            estimate::set_estimate,
+            estimate::get_estimation_accuracy,
+            timesheet::export_timesheet,
+            secrets::store_secret,
+            secrets::delete_secret,
+            secrets::has_secret,
+            outbound::get_outbox,
+            outbound::flush_outbox,
+            health::get_integration_status,
+            wipe::request_erase_token,
+            wipe::erase_all_data,
+            remote::sync_now,
+            remote::get_remote_stats,
+            commands::get_command_metrics
+        ]))
+}
+
+fn main() {
+    // Answered by the running instance, so handle it before single-instance kicks in
+    if std::env::args().any(|arg| arg == "--status") {
+        control::print_status(std::env::args().any(|arg| arg == "--json"));
+        return;
+    }
+
+    // Daemon mode for tray/status-bar users: same engine, no webview
+    let headless = std::env::args().any(|arg| arg == "--headless");
+    let mut context = tauri::generate_context!();
+    if headless {
+        context.config_mut().tauri.windows.clear();
+    }
+
+    // Must be registered first so a second launch forwards its args and exits
+    let builder =
+        tauri::Builder::default().plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            handle_cli_args(app, &argv);
+        }));
+    app_builder(builder, headless)
+        .build(context)
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                inhibit::release_all(app);
+                notification::withdraw(app);
+                presence::remove(app);
+                crash::clear(app);
+                touch_last_opened(app);
+                // The writer thread won't get to it before the process ends
+                with_store(app, |store| store.save());
+            }
+        });
 }
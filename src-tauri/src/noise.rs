@@ -0,0 +1,176 @@
+#[cfg(feature = "audio")]
+use rodio::cpal::{
+    self,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    SampleFormat,
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "audio")]
+use std::sync::{Arc, Mutex};
+use std::{thread, time::Duration};
+use tauri::{AppHandle, Manager};
+
+use crate::{events, read_settings, timer::Timer, Phase, SessionNumber, TimePhase};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+const SAMPLE_LENGTH: Duration = Duration::from_secs(3);
+// Samples in a row above the threshold before it counts as a noisy place
+const NOISY_SAMPLES: u32 = 3;
+
+// Listens to the microphone for a few seconds a minute during Work and
+// suggests masking the noise when it stays loud. Only the level is kept,
+// never the audio, and nothing leaves the machine.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NoiseCheck {
+    pub enabled: bool,
+    // RMS level in dBFS above which a sample counts as noisy
+    pub threshold_db: f32,
+}
+
+impl Default for NoiseCheck {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -30.0,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+enum Remedy {
+    // Play the ambient noise generator
+    AmbientSound,
+    // The ambient sound is already on, try headphones
+    Headphones,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct NoiseSuggestion {
+    level_db: f32,
+    remedy: Remedy,
+}
+
+#[cfg(feature = "audio")]
+fn level_db(sum_of_squares: f64, samples: u64) -> f32 {
+    if samples == 0 {
+        return f32::NEG_INFINITY;
+    }
+    let rms = (sum_of_squares / samples as f64).sqrt();
+    (20.0 * rms.max(1e-9).log10()) as f32
+}
+
+// RMS level of the default input over `SAMPLE_LENGTH`. The samples are
+// folded into a running sum as they arrive and dropped. macOS asks first,
+// with the usage string from Info.plist, which the bundle merges in.
+#[cfg(feature = "audio")]
+fn sample_level() -> Result<f32, String> {
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or("no microphone")?;
+    let config = device
+        .default_input_config()
+        .map_err(|err| err.to_string())?;
+    let totals = Arc::new(Mutex::new((0.0f64, 0u64)));
+    let on_error = |err: cpal::StreamError| eprintln!("Noise sampling failed: {err}");
+
+    let stream = match config.sample_format() {
+        SampleFormat::F32 => {
+            let totals = totals.clone();
+            device.build_input_stream(
+                &config.into(),
+                move |data: &[f32], _: &_| {
+                    let mut totals = totals.lock().unwrap();
+                    for sample in data {
+                        totals.0 += (*sample as f64).powi(2);
+                    }
+                    totals.1 += data.len() as u64;
+                },
+                on_error,
+                None,
+            )
+        }
+        SampleFormat::I16 => {
+            let totals = totals.clone();
+            device.build_input_stream(
+                &config.into(),
+                move |data: &[i16], _: &_| {
+                    let mut totals = totals.lock().unwrap();
+                    for sample in data {
+                        totals.0 += (*sample as f64 / i16::MAX as f64).powi(2);
+                    }
+                    totals.1 += data.len() as u64;
+                },
+                on_error,
+                None,
+            )
+        }
+        format => return Err(format!("unsupported sample format {format}")),
+    }
+    .map_err(|err| err.to_string())?;
+
+    stream.play().map_err(|err| err.to_string())?;
+    thread::sleep(SAMPLE_LENGTH);
+    drop(stream);
+    let (sum, samples) = *totals.lock().unwrap();
+    Ok(level_db(sum, samples))
+}
+
+#[cfg(not(feature = "audio"))]
+fn sample_level() -> Result<f32, String> {
+    Err("built without audio support".into())
+}
+
+fn counting_work(app: &AppHandle) -> bool {
+    *app.state::<Phase>().0.lock().unwrap() == TimePhase::Work
+        && app.state::<Timer>().0.lock().unwrap().running
+}
+
+// The microphone stays closed unless enabled, and outside running Work
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || {
+        let mut noisy = 0;
+        // Work session already told about the noise, once is enough
+        let mut suggested_in: Option<i32> = None;
+        loop {
+            thread::sleep(SAMPLE_INTERVAL);
+            let settings = read_settings(&app);
+            let session = *app.state::<SessionNumber>().0.lock().unwrap();
+            if !settings.noise_check.enabled
+                || !counting_work(&app)
+                || suggested_in == Some(session)
+            {
+                noisy = 0;
+                continue;
+            }
+
+            let level = match sample_level() {
+                Ok(level) => level,
+                Err(err) => {
+                    eprintln!("Couldn't sample the noise level: {err}");
+                    continue;
+                }
+            };
+            if level < settings.noise_check.threshold_db {
+                noisy = 0;
+                continue;
+            }
+            noisy += 1;
+            if noisy >= NOISY_SAMPLES {
+                noisy = 0;
+                suggested_in = Some(session);
+                let remedy = if settings.ambient.path.is_some() {
+                    Remedy::Headphones
+                } else {
+                    Remedy::AmbientSound
+                };
+                let suggestion = NoiseSuggestion {
+                    level_db: level,
+                    remedy,
+                };
+                events::emit_once(&app, "noise-suggestion", suggestion);
+            }
+        }
+    });
+}
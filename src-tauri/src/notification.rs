@@ -0,0 +1,250 @@
+use serde::Serialize;
+use std::{ops::Range, process::Command, sync::Mutex, thread};
+use tauri::{AppHandle, Manager};
+
+use crate::{audio, events, Settings, TimePhase};
+
+// How often a live notification's countdown is refreshed
+const UPDATE_EVERY_SECS: i32 = 30;
+
+// Only freedesktop notification servers can replace a notification in
+// place, elsewhere a break gets the usual one-off toast
+#[cfg(target_os = "linux")]
+mod live {
+    use notify_rust::{Hint, Notification, NotificationHandle, Timeout};
+
+    pub struct Live {
+        handle: NotificationHandle,
+        body: String,
+        total_secs: i32,
+    }
+
+    // Percentage of the phase that's done, for the progress bar hint
+    fn progress(total_secs: i32, remaining_secs: i32) -> i32 {
+        if total_secs <= 0 {
+            return 100;
+        }
+        (100 - remaining_secs * 100 / total_secs).clamp(0, 100)
+    }
+
+    fn countdown(body: &str, remaining_secs: i32) -> String {
+        format!(
+            "{body}\n{}:{:02} left",
+            remaining_secs / 60,
+            remaining_secs % 60
+        )
+    }
+
+    pub fn show(
+        app_name: &str,
+        title: &str,
+        body: &str,
+        remaining_secs: i32,
+        sticky: bool,
+    ) -> Option<Live> {
+        let mut notification = Notification::new();
+        notification
+            .appname(app_name)
+            .summary(title)
+            .body(&countdown(body, remaining_secs))
+            .timeout(Timeout::Never);
+        if sticky {
+            // Stays in the tray after being clicked, until it's withdrawn
+            notification
+                .hint(Hint::Resident(true))
+                .hint(Hint::CustomInt("value".into(), 0));
+        }
+        match notification.show() {
+            Ok(handle) => Some(Live {
+                handle,
+                body: body.to_string(),
+                total_secs: if sticky { remaining_secs } else { 0 },
+            }),
+            Err(err) => {
+                eprintln!("Failed to show live notification: {err}");
+                None
+            }
+        }
+    }
+
+    pub fn update(live: &mut Live, remaining_secs: i32) {
+        let body = countdown(&live.body, remaining_secs);
+        live.handle.body(&body);
+        if live.total_secs > 0 {
+            let progress = progress(live.total_secs, remaining_secs);
+            live.handle.hint(Hint::CustomInt("value".into(), progress));
+        }
+        live.handle.update();
+    }
+
+    pub fn close(live: Live) {
+        live.handle.close();
+    }
+
+    pub fn server_available() -> bool {
+        notify_rust::get_server_information().is_ok()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod live {
+    pub struct Live;
+
+    pub fn show(_: &str, _: &str, _: &str, _: i32, _: bool) -> Option<Live> {
+        None
+    }
+
+    pub fn update(_: &mut Live, _: i32) {}
+
+    pub fn close(_: Live) {}
+
+    pub fn server_available() -> bool {
+        false
+    }
+}
+
+// The break notification that's still open, if any
+#[derive(Default)]
+pub struct LiveNotification(Mutex<Option<live::Live>>);
+
+// Shows a notification that counts down the phase, a sticky one also keeps
+// a progress bar and stays put until the phase ends. False where that's not
+// supported, the caller then falls back to a regular notification.
+pub fn show_live(
+    app: &AppHandle,
+    title: &str,
+    body: &str,
+    remaining_secs: i32,
+    sticky: bool,
+) -> bool {
+    let identifier = app.config().tauri.bundle.identifier.clone();
+    let shown = live::show(&identifier, title, body, remaining_secs, sticky);
+    let supported = shown.is_some();
+    *app.state::<LiveNotification>().0.lock().unwrap() = shown;
+    supported
+}
+
+#[derive(Serialize, Clone)]
+struct InAppAlert<'a> {
+    phase: TimePhase,
+    title: &'a str,
+    body: &'a str,
+}
+
+// For when the OS wouldn't show the notification, so the phase change still
+// gets through. Gets the settings passed in, it runs with the store held.
+pub fn alert_in_app(
+    app: &AppHandle,
+    settings: &Settings,
+    phase: TimePhase,
+    title: &str,
+    body: &str,
+) {
+    events::emit_once(app, "in-app-alert", InAppAlert { phase, title, body });
+    audio::play_alert(app, &settings.sounds, phase);
+}
+
+// Ends the countdown, the phase it was counting down is over
+pub fn withdraw(app: &AppHandle) {
+    if let Some(live) = app.state::<LiveNotification>().0.lock().unwrap().take() {
+        live::close(live);
+    }
+}
+
+// Updated once with where the count landed, however many updates it passed
+pub fn on_tick(app: &AppHandle, passed: &Range<i32>) {
+    if !passed.clone().any(|secs| secs % UPDATE_EVERY_SECS == 0) {
+        return;
+    }
+    if let Some(live) = app.state::<LiveNotification>().0.lock().unwrap().as_mut() {
+        live::update(live, passed.start);
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionStatus {
+    Granted,
+    Blocked,
+    // macOS doesn't tell other processes
+    Unknown,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct NotificationPermission {
+    pub status: PermissionStatus,
+    // Where to turn notifications back on
+    pub guidance: Option<&'static str>,
+}
+
+// `reg query` prints "    ToastEnabled    REG_DWORD    0x0"
+fn registry_dword(key: &str, value: &str) -> Option<u32> {
+    let output = Command::new("reg")
+        .args(["query", key, "/v", value])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    text.split_whitespace()
+        .find_map(|word| word.strip_prefix("0x"))
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+}
+
+fn permission_status(app: &AppHandle) -> PermissionStatus {
+    if cfg!(target_os = "linux") {
+        // Without a notification server every notification is dropped
+        if live::server_available() {
+            PermissionStatus::Granted
+        } else {
+            PermissionStatus::Blocked
+        }
+    } else if cfg!(windows) {
+        // Missing values mean the defaults, which allow notifications
+        let identifier = &app.config().tauri.bundle.identifier;
+        let all = registry_dword(
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\PushNotifications",
+            "ToastEnabled",
+        );
+        let ours = registry_dword(
+            &format!(
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Notifications\Settings\{identifier}"
+            ),
+            "Enabled",
+        );
+        if all == Some(0) || ours == Some(0) {
+            PermissionStatus::Blocked
+        } else {
+            PermissionStatus::Granted
+        }
+    } else {
+        PermissionStatus::Unknown
+    }
+}
+
+fn guidance() -> &'static str {
+    if cfg!(target_os = "linux") {
+        "No notification server is running. Start one, such as dunst or mako, or turn notifications on in your desktop's settings."
+    } else if cfg!(windows) {
+        "Notifications are turned off. Open Settings > System > Notifications and turn them on for Pomodorio."
+    } else {
+        "If nothing shows up, open System Settings > Notifications > Pomodorio and allow notifications."
+    }
+}
+
+#[tauri::command]
+pub fn check_notification_permission(app: AppHandle) -> NotificationPermission {
+    let status = permission_status(&app);
+    NotificationPermission {
+        status,
+        guidance: (status != PermissionStatus::Granted).then(guidance),
+    }
+}
+
+// Off the main thread, asking the notification server or registry can be slow
+pub fn check_at_startup(app: AppHandle) {
+    thread::spawn(move || {
+        let permission = check_notification_permission(app.clone());
+        if permission.status == PermissionStatus::Blocked {
+            events::emit(&app, "notifications-blocked", permission);
+        }
+    });
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use tauri::AppHandle;
+
+use crate::{autostart, events, modify_settings, with_store, Error};
+
+const ONBOARDING_KEY: &str = "onboarding";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnboardingStep {
+    Notifications,
+    Autostart,
+    Preset,
+    Done,
+}
+
+impl OnboardingStep {
+    fn next(self) -> Self {
+        match self {
+            OnboardingStep::Notifications => OnboardingStep::Autostart,
+            OnboardingStep::Autostart => OnboardingStep::Preset,
+            OnboardingStep::Preset | OnboardingStep::Done => OnboardingStep::Done,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    // 25/5, long break every 4
+    Classic,
+    // 15/3, for getting started
+    Short,
+    // 50/10, for longer stretches of deep work
+    Long,
+}
+
+// One answer per step, checked against the step the user is actually on
+#[derive(Deserialize, Debug)]
+#[serde(tag = "step", rename_all = "kebab-case")]
+pub enum OnboardingAnswer {
+    // The frontend asks the OS and reports what it said
+    Notifications { granted: bool },
+    Autostart { enabled: bool },
+    Preset { preset: Preset },
+    // Move past the current step without changing anything
+    Skip,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OnboardingState {
+    pub first_run: bool,
+    pub step: OnboardingStep,
+    pub notifications_granted: Option<bool>,
+}
+
+impl Default for OnboardingState {
+    fn default() -> Self {
+        Self {
+            first_run: true,
+            step: OnboardingStep::Notifications,
+            notifications_granted: None,
+        }
+    }
+}
+
+fn load(app: &AppHandle) -> OnboardingState {
+    let mut state = OnboardingState::default();
+    with_store(app, |store| {
+        state = store
+            .get(ONBOARDING_KEY)
+            .and_then(|value| from_value(value.clone()).ok())
+            .unwrap_or_default();
+        Ok(())
+    });
+    state
+}
+
+fn apply_preset(app: &AppHandle, preset: Preset) -> Result<(), Error> {
+    let (work, short_break, long_break, interval) = match preset {
+        Preset::Classic => (25, 5, 20, 4),
+        Preset::Short => (15, 3, 15, 4),
+        Preset::Long => (50, 10, 30, 3),
+    };
+    modify_settings(app, |settings| {
+        settings.work_time = work;
+        settings.short_break_time = short_break;
+        settings.long_break_time = long_break;
+        settings.long_break_interval = interval;
+    })
+}
+
+// Stored after every step, so quitting halfway resumes where it left off
+#[tauri::command]
+pub fn get_onboarding_state(app: AppHandle) -> OnboardingState {
+    load(&app)
+}
+
+#[tauri::command]
+pub fn advance_onboarding(
+    answer: OnboardingAnswer,
+    app: AppHandle,
+) -> Result<OnboardingState, Error> {
+    let mut state = load(&app);
+    match (state.step, answer) {
+        (OnboardingStep::Done, _) => return Ok(state),
+        (_, OnboardingAnswer::Skip) => {}
+        (OnboardingStep::Notifications, OnboardingAnswer::Notifications { granted }) => {
+            state.notifications_granted = Some(granted);
+        }
+        (OnboardingStep::Autostart, OnboardingAnswer::Autostart { enabled }) => {
+            autostart::set_launch_at_login(enabled, app.clone())?;
+        }
+        (OnboardingStep::Preset, OnboardingAnswer::Preset { preset }) => {
+            apply_preset(&app, preset)?;
+        }
+        (step, answer) => {
+            return Err(Error::Onboarding(format!(
+                "{answer:?} doesn't answer the {step:?} step"
+            )))
+        }
+    }
+
+    state.step = state.step.next();
+    state.first_run = state.step != OnboardingStep::Done;
+    with_store(&app, |store| {
+        store.insert(ONBOARDING_KEY.into(), json!(state))
+    });
+    events::emit(&app, "onboarding", &state);
+    Ok(state)
+}
@@ -0,0 +1,295 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json, Value};
+use std::{
+    collections::VecDeque,
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    health::{self, IntegrationStatus},
+    secrets, with_store,
+};
+
+// Requests kept while offline, the oldest are dropped beyond this
+const QUEUE_LIMIT: usize = 500;
+// Spacing between requests, so a burst of phase changes can't get us rate limited
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+const FIRST_RETRY: Duration = Duration::from_secs(2);
+const MAX_RETRY: Duration = Duration::from_secs(300);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// Failed tries before a request is parked in the outbox, out of everyone's way
+const MAX_ATTEMPTS: u32 = 5;
+// How often a non-empty outbox checks whether we're back online
+const CONNECTIVITY_CHECK: Duration = Duration::from_secs(60);
+const OUTBOX_KEY: &str = "outbox";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OutboundRequest {
+    // Which integration this is for, e.g. "webhook" or "slack"
+    pub integration: String,
+    pub method: String,
+    pub url: String,
+    pub body: Option<Value>,
+    // Name of the secret sent as a bearer token. It's looked up when the
+    // request goes out, so tokens never sit in the queue.
+    pub bearer_secret: Option<String>,
+    // Name of a "user:password" secret for basic auth, e.g. for CalDAV
+    #[serde(default)]
+    pub basic_secret: Option<String>,
+    // Sends a string body as is with this type, rather than as JSON
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
+enum Outcome {
+    Sent,
+    Retry(String),
+    Failed(String),
+    Unauthorized(String),
+}
+
+// Slack style APIs answer 200 with `"ok": false` and the reason in `error`
+fn api_error(response: ureq::Response) -> Option<String> {
+    if response.content_type() != "application/json" {
+        return None;
+    }
+    let body: Value = response.into_json().ok()?;
+    (body["ok"] == false).then(|| body["error"].as_str().unwrap_or("unknown").to_string())
+}
+
+fn is_auth_error(error: &str) -> bool {
+    matches!(
+        error,
+        "invalid_auth" | "not_authed" | "token_expired" | "token_revoked" | "account_inactive"
+    )
+}
+
+fn send_now(app: &AppHandle, agent: &ureq::Agent, request: &OutboundRequest) -> Outcome {
+    let mut call = agent.request(&request.method, &request.url);
+    if let Some(name) = &request.bearer_secret {
+        match secrets::get(app, name) {
+            Some(token) => call = call.set("Authorization", &format!("Bearer {token}")),
+            None => return Outcome::Unauthorized(format!("no {name} secret")),
+        }
+    }
+    if let Some(name) = &request.basic_secret {
+        match secrets::get(app, name) {
+            Some(credentials) => {
+                call = call.set(
+                    "Authorization",
+                    &format!("Basic {}", STANDARD.encode(credentials)),
+                )
+            }
+            None => return Outcome::Unauthorized(format!("no {name} secret")),
+        }
+    }
+
+    let result = match (&request.body, &request.content_type) {
+        (Some(Value::String(body)), Some(content_type)) => {
+            call.set("Content-Type", content_type).send_string(body)
+        }
+        (Some(body), _) => call.send_json(body),
+        (None, _) => call.call(),
+    };
+    match result {
+        Ok(response) => match api_error(response) {
+            Some(error) if is_auth_error(&error) => Outcome::Unauthorized(error),
+            Some(error) => Outcome::Failed(error),
+            None => Outcome::Sent,
+        },
+        Err(ureq::Error::Status(code @ (401 | 403), _)) => {
+            Outcome::Unauthorized(format!("HTTP {code}"))
+        }
+        // Rate limited or the server is having trouble, worth another try
+        Err(ureq::Error::Status(code, _)) if code == 429 || code >= 500 => {
+            Outcome::Retry(format!("HTTP {code}"))
+        }
+        Err(ureq::Error::Status(code, _)) => Outcome::Failed(format!("HTTP {code}")),
+        // Offline, DNS or a timeout
+        Err(err) => Outcome::Retry(err.to_string()),
+    }
+}
+
+fn load_outbox(app: &AppHandle) -> Vec<OutboundRequest> {
+    let mut outbox = Vec::new();
+    with_store(app, |store| {
+        outbox = store
+            .get(OUTBOX_KEY)
+            .and_then(|value| from_value(value.clone()).ok())
+            .unwrap_or_default();
+        Ok(())
+    });
+    outbox
+}
+
+fn take_outbox(app: &AppHandle) -> Vec<OutboundRequest> {
+    let outbox = load_outbox(app);
+    if !outbox.is_empty() {
+        with_store(app, |store| store.insert(OUTBOX_KEY.into(), json!([])));
+    }
+    outbox
+}
+
+// Kept in the store, so it survives a restart while offline
+fn park(app: &AppHandle, request: OutboundRequest) {
+    eprintln!("Moving a {} request to the outbox", request.integration);
+    let mut outbox = load_outbox(app);
+    if outbox.len() == QUEUE_LIMIT {
+        outbox.remove(0);
+    }
+    outbox.push(request);
+    with_store(app, |store| store.insert(OUTBOX_KEY.into(), json!(outbox)));
+}
+
+// Any answer at all, even an error status, means the server is reachable
+fn reachable(agent: &ureq::Agent, url: &str) -> bool {
+    !matches!(agent.head(url).call(), Err(ureq::Error::Transport(_)))
+}
+
+// Every outbound integration call goes through this one worker, so a slow
+// or unreachable server never holds up the timer. Requests go out in order;
+// while the one at the front is backing off the rest wait behind it.
+pub struct Outbound(Mutex<Sender<OutboundRequest>>);
+
+impl Outbound {
+    pub fn spawn(app: AppHandle) -> Self {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+            let mut queue: VecDeque<OutboundRequest> = VecDeque::new();
+            let mut backoff = FIRST_RETRY;
+            let mut attempts = 0;
+            let mut next_attempt = Instant::now();
+            // Might be left over from the last run, the first check tells
+            let mut outbox_pending = true;
+            let mut last_check = Instant::now();
+
+            loop {
+                let timeout = if !queue.is_empty() {
+                    next_attempt.saturating_duration_since(Instant::now())
+                } else if outbox_pending {
+                    CONNECTIVITY_CHECK.saturating_sub(last_check.elapsed())
+                } else {
+                    Duration::from_secs(3600)
+                };
+                match receiver.recv_timeout(timeout) {
+                    Ok(request) => {
+                        if queue.len() == QUEUE_LIMIT {
+                            if let Some(dropped) = queue.pop_front() {
+                                eprintln!(
+                                    "Outbound queue full, dropping a {} request",
+                                    dropped.integration
+                                );
+                            }
+                        }
+                        queue.push_back(request);
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                if queue.is_empty() && outbox_pending && last_check.elapsed() >= CONNECTIVITY_CHECK
+                {
+                    last_check = Instant::now();
+                    match load_outbox(&app).first() {
+                        Some(parked) if reachable(&agent, &parked.url) => {
+                            queue.extend(take_outbox(&app));
+                            outbox_pending = false;
+                        }
+                        Some(_) => {}
+                        None => outbox_pending = false,
+                    }
+                }
+
+                if Instant::now() < next_attempt {
+                    continue;
+                }
+                let Some(request) = queue.front() else {
+                    continue;
+                };
+                let outcome = send_now(&app, &agent, request);
+                let status = match outcome {
+                    Outcome::Sent => IntegrationStatus::Connected,
+                    Outcome::Unauthorized(_) => IntegrationStatus::AuthExpired,
+                    Outcome::Retry(_) | Outcome::Failed(_) => IntegrationStatus::Degraded,
+                };
+                health::report(&app, &request.integration, status);
+                match outcome {
+                    Outcome::Sent => {
+                        queue.pop_front();
+                        attempts = 0;
+                        backoff = FIRST_RETRY;
+                        next_attempt = Instant::now() + MIN_INTERVAL;
+                        // Evidently back online
+                        if outbox_pending {
+                            queue.extend(take_outbox(&app));
+                            outbox_pending = false;
+                        }
+                    }
+                    Outcome::Retry(reason) => {
+                        attempts += 1;
+                        if attempts >= MAX_ATTEMPTS {
+                            eprintln!(
+                                "{} request failed {attempts} times: {reason}",
+                                request.integration
+                            );
+                            if let Some(request) = queue.pop_front() {
+                                park(&app, request);
+                            }
+                            outbox_pending = true;
+                            last_check = Instant::now();
+                            attempts = 0;
+                            backoff = FIRST_RETRY;
+                            next_attempt = Instant::now() + MIN_INTERVAL;
+                        } else {
+                            eprintln!(
+                                "{} request failed, retrying in {}s: {reason}",
+                                request.integration,
+                                backoff.as_secs()
+                            );
+                            next_attempt = Instant::now() + backoff;
+                            backoff = (backoff * 2).min(MAX_RETRY);
+                        }
+                    }
+                    Outcome::Failed(reason) | Outcome::Unauthorized(reason) => {
+                        eprintln!("Dropping {} request: {reason}", request.integration);
+                        queue.pop_front();
+                        attempts = 0;
+                        next_attempt = Instant::now() + MIN_INTERVAL;
+                    }
+                }
+            }
+        });
+        Self(Mutex::new(sender))
+    }
+}
+
+// Queue a request, returns right away
+pub fn send(app: &AppHandle, request: OutboundRequest) {
+    let outbound = app.state::<Outbound>();
+    let _ = outbound.0.lock().unwrap().send(request);
+}
+
+// Requests that kept failing and wait for connectivity to come back
+#[tauri::command]
+pub fn get_outbox(app: AppHandle) -> Vec<OutboundRequest> {
+    load_outbox(&app)
+}
+
+// Retry everything in the outbox now rather than at the next check
+#[tauri::command]
+pub fn flush_outbox(app: AppHandle) -> usize {
+    let outbox = take_outbox(&app);
+    let count = outbox.len();
+    for request in outbox {
+        send(&app, request);
+    }
+    count
+}
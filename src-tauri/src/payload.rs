@@ -0,0 +1,66 @@
+use serde::Serialize;
+use serde_json::Value;
+
+// Serializes event payloads straight to JSON text in a buffer that's kept
+// between calls, rather than through a `serde_json::Value` tree. Each goes
+// out as `data` next to `fixed`'s fields, serialized once up front.
+pub struct PayloadWriter {
+    buffer: Vec<u8>,
+    // `{"key":value,...,"data":`
+    prefix: Vec<u8>,
+}
+
+impl PayloadWriter {
+    pub fn new(fixed: &Value) -> Self {
+        let mut prefix = match fixed {
+            Value::Object(fields) if !fields.is_empty() => fixed.to_string().into_bytes(),
+            _ => b"{}".to_vec(),
+        };
+        prefix.pop();
+        if prefix.len() > 1 {
+            prefix.push(b',');
+        }
+        prefix.extend_from_slice(br#""data":"#);
+        Self {
+            buffer: Vec::with_capacity(256),
+            prefix,
+        }
+    }
+
+    pub fn write<S: Serialize>(&mut self, payload: &S) -> serde_json::Result<&str> {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(&self.prefix);
+        serde_json::to_writer(&mut self.buffer, payload)?;
+        self.buffer.push(b'}');
+        Ok(std::str::from_utf8(&self.buffer).expect("serde_json wrote invalid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn wraps_every_payload_next_to_the_fixed_fields() {
+        let mut writer = PayloadWriter::new(&json!({ "api_version": { "major": 1, "minor": 1 } }));
+        let written: Value =
+            serde_json::from_str(writer.write(&json!({ "remaining_secs": 90 })).unwrap()).unwrap();
+        assert_eq!(
+            written,
+            json!({ "api_version": { "major": 1, "minor": 1 }, "data": { "remaining_secs": 90 } })
+        );
+        assert_eq!(
+            writer.write(&None::<i32>).unwrap(),
+            r#"{"api_version":{"major":1,"minor":1},"data":null}"#
+        );
+        assert_eq!(
+            writer.write(&["2024-03-04"]).unwrap(),
+            r#"{"api_version":{"major":1,"minor":1},"data":["2024-03-04"]}"#
+        );
+        assert_eq!(
+            PayloadWriter::new(&json!({})).write(&25).unwrap(),
+            r#"{"data":25}"#
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use std::{
+    sync::{
+        mpsc::{channel, Sender},
+        Mutex,
+    },
+    thread,
+};
+use tauri::{AppHandle, Manager};
+
+use crate::with_store;
+
+// Writes the store file on its own thread, so nothing waits on the disk.
+// Requests that pile up while a write runs are folded into the next one.
+pub struct Writer(Mutex<Sender<()>>);
+
+impl Writer {
+    pub fn spawn(app: AppHandle) -> Self {
+        let (sender, receiver) = channel::<()>();
+        thread::spawn(move || {
+            while receiver.recv().is_ok() {
+                while receiver.try_recv().is_ok() {}
+                with_store(&app, |store| {
+                    if let Err(err) = store.save() {
+                        eprintln!("Failed to save the store: {err}");
+                    }
+                    Ok(())
+                });
+            }
+        });
+        Self(Mutex::new(sender))
+    }
+}
+
+// The store is otherwise only written on a clean exit. Fine to call with
+// the store held.
+pub fn save(app: &AppHandle) {
+    let _ = app.state::<Writer>().0.lock().unwrap().send(());
+}
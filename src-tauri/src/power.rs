@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::{process::Command, sync::Mutex, thread, time::Duration};
+use tauri::{AppHandle, Manager};
+
+use crate::{audio, events, read_settings, Phase, TimePhase};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// How often the countdown is sent out while saving power
+pub const LOW_POWER_TICK_SECS: i32 = 15;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LowBattery {
+    pub enabled: bool,
+    // Cut back once the battery drops to this many percent while unplugged
+    pub threshold_percent: u8,
+}
+
+impl Default for LowBattery {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_percent: 20,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Default, Debug)]
+pub struct PowerState {
+    pub on_battery: bool,
+    // None on machines without a battery
+    pub battery_percent: Option<u8>,
+    // Ambient sound, integrations and frequent countdown updates are off
+    pub low_power: bool,
+}
+
+#[derive(Default)]
+pub struct Power(pub Mutex<PowerState>);
+
+pub fn low_power(app: &AppHandle) -> bool {
+    app.state::<Power>().0.lock().unwrap().low_power
+}
+
+// (on battery, percent)
+fn battery() -> Option<(bool, u8)> {
+    if cfg!(target_os = "linux") {
+        linux_battery()
+    } else if cfg!(target_os = "macos") {
+        macos_battery()
+    } else {
+        #[cfg(windows)]
+        return win32::battery();
+        #[cfg(not(windows))]
+        None
+    }
+}
+
+fn linux_battery() -> Option<(bool, u8)> {
+    let supplies = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    let read = |path: &std::path::Path, name: &str| {
+        std::fs::read_to_string(path.join(name))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut percent = None;
+    let mut on_mains = false;
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        match read(&path, "type").as_str() {
+            "Battery" => percent = read(&path, "capacity").parse().ok().or(percent),
+            "Mains" => on_mains |= read(&path, "online") == "1",
+            _ => {}
+        }
+    }
+    percent.map(|percent| (!on_mains, percent))
+}
+
+// "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=...)	85%; discharging; ..."
+fn macos_battery() -> Option<(bool, u8)> {
+    let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let percent = text
+        .split_whitespace()
+        .find_map(|word| word.strip_suffix("%;"))?
+        .parse()
+        .ok()?;
+    Some((text.contains("'Battery Power'"), percent))
+}
+
+#[cfg(windows)]
+mod win32 {
+    #[repr(C)]
+    #[derive(Default)]
+    struct SystemPowerStatus {
+        ac_line_status: u8,
+        battery_flag: u8,
+        battery_life_percent: u8,
+        system_status_flag: u8,
+        battery_life_time: u32,
+        battery_full_life_time: u32,
+    }
+
+    const NO_SYSTEM_BATTERY: u8 = 128;
+    const UNKNOWN_PERCENT: u8 = 255;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemPowerStatus(status: *mut SystemPowerStatus) -> i32;
+    }
+
+    pub fn battery() -> Option<(bool, u8)> {
+        let mut status = SystemPowerStatus::default();
+        if unsafe { GetSystemPowerStatus(&mut status) } == 0
+            || status.battery_flag & NO_SYSTEM_BATTERY != 0
+            || status.battery_life_percent == UNKNOWN_PERCENT
+        {
+            return None;
+        }
+        Some((status.ac_line_status == 0, status.battery_life_percent))
+    }
+}
+
+fn check(app: &AppHandle) {
+    let config = read_settings(app).low_battery;
+    let battery = battery();
+    let next = PowerState {
+        on_battery: battery.map_or(false, |(on_battery, _)| on_battery),
+        battery_percent: battery.map(|(_, percent)| percent),
+        low_power: config.enabled
+            && battery.map_or(false, |(on_battery, percent)| {
+                on_battery && percent <= config.threshold_percent
+            }),
+    };
+
+    let previous = {
+        let power = app.state::<Power>();
+        let mut state = power.0.lock().unwrap();
+        std::mem::replace(&mut *state, next)
+    };
+    if previous == next {
+        return;
+    }
+    events::emit(app, "power-state", next);
+
+    // Stop or bring back the ambient sound without waiting for the next phase
+    if previous.low_power != next.low_power {
+        let phase = *app.state::<Phase>().0.lock().unwrap();
+        if phase == TimePhase::Work {
+            audio::refresh_ambient(app);
+        }
+    }
+}
+
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || loop {
+        check(&app);
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+#[tauri::command]
+pub fn get_power_state(power: tauri::State<Power>) -> PowerState {
+    *power.0.lock().unwrap()
+}
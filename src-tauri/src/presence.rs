@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{fs, path::Path};
+use tauri::{AppHandle, Manager};
+
+use crate::{events, read_settings, timer, Phase, TimePhase};
+
+// Written to `Settings.presence_file` for other tools on the machine, e.g.
+// backup jobs that hold off until a break
+#[derive(Serialize)]
+struct Presence {
+    schema_version: u32,
+    phase: TimePhase,
+    running: bool,
+    // A running Work session, the one thing most readers check
+    focusing: bool,
+    #[serde(flatten)]
+    times: timer::PhaseTimes,
+    updated_at: DateTime<Utc>,
+}
+
+fn write(path: &Path, presence: &Presence) -> std::io::Result<()> {
+    // Written next to the target and renamed, so readers never see a partial file
+    let partial = path.with_extension("part");
+    fs::write(&partial, serde_json::to_vec_pretty(presence)?)?;
+    fs::rename(&partial, path)
+}
+
+// Call when the phase changes, or the countdown pauses, resumes or moves
+pub fn update(app: &AppHandle) {
+    let Some(path) = read_settings(app).presence_file else {
+        return;
+    };
+    let phase = *app.state::<Phase>().0.lock().unwrap();
+    let running = app.state::<timer::Timer>().0.lock().unwrap().running;
+    let presence = Presence {
+        schema_version: events::SCHEMA_VERSION,
+        phase,
+        running,
+        focusing: running && phase == TimePhase::Work,
+        times: timer::phase_times(app),
+        updated_at: Utc::now(),
+    };
+    if let Err(err) = write(Path::new(&path), &presence) {
+        eprintln!("Failed to write the presence file {path}: {err}");
+    }
+}
+
+// A file left behind after quitting would claim a session that isn't running
+pub fn remove(app: &AppHandle) {
+    if let Some(path) = read_settings(app).presence_file {
+        let _ = fs::remove_file(path);
+    }
+}
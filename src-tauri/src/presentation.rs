@@ -0,0 +1,152 @@
+use serde::Serialize;
+use std::{sync::Mutex, thread, time::Duration};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    audio, events, notification, read_settings, registry, workspace::output, Phase, TimePhase,
+};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+// Zoom runs this helper only while the screen is shared
+const SCREEN_SHARE_PROCESSES: [&str; 1] = ["CptHost"];
+
+#[derive(Serialize, Clone, Copy, PartialEq, Default, Debug)]
+pub struct PresentationState {
+    // A full-screen app or screen sharing was seen on the last check
+    pub detected: bool,
+    // Set by the user, wins over detection until cleared
+    pub forced: Option<bool>,
+    // Notifications, overlays and sounds are held back
+    pub active: bool,
+}
+
+#[derive(Default)]
+pub struct Presentation(Mutex<PresentationState>);
+
+pub fn active(app: &AppHandle) -> bool {
+    app.state::<Presentation>().0.lock().unwrap().active
+}
+
+fn process_running(name: &str) -> bool {
+    if cfg!(windows) {
+        let filter = format!("IMAGENAME eq {name}.exe");
+        output("tasklist", &["/FI", &filter, "/NH"]).map_or(false, |list| {
+            list.to_lowercase().contains(&name.to_lowercase())
+        })
+    } else {
+        output("pgrep", &["-x", name]).is_some()
+    }
+}
+
+// X11 only, like the foreground window lookup
+fn linux_full_screen() -> bool {
+    let Some(window) = output("xdotool", &["getactivewindow"]) else {
+        return false;
+    };
+    output("xprop", &["-id", &window, "_NET_WM_STATE"])
+        .map_or(false, |state| state.contains("_NET_WM_STATE_FULLSCREEN"))
+}
+
+// Needs Automation access to System Events, macOS asks with the usage
+// string from Info.plist
+const MACOS_FULL_SCREEN: &str = r#"tell application "System Events"
+    set frontApp to first application process whose frontmost is true
+    return value of attribute "AXFullScreen" of front window of frontApp
+end tell"#;
+
+fn macos_full_screen() -> bool {
+    output("osascript", &["-e", MACOS_FULL_SCREEN]).as_deref() == Some("true")
+}
+
+#[cfg(windows)]
+mod win32 {
+    // QUERY_USER_NOTIFICATION_STATE values the shell uses to hold back its own toasts
+    const QUNS_BUSY: i32 = 2;
+    const QUNS_RUNNING_D3D_FULL_SCREEN: i32 = 3;
+    const QUNS_PRESENTATION_MODE: i32 = 4;
+
+    #[link(name = "shell32")]
+    extern "system" {
+        fn SHQueryUserNotificationState(state: *mut i32) -> i32;
+    }
+
+    pub fn presenting() -> bool {
+        let mut state = 0;
+        if unsafe { SHQueryUserNotificationState(&mut state) } != 0 {
+            return false;
+        }
+        matches!(
+            state,
+            QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE
+        )
+    }
+}
+
+fn full_screen() -> bool {
+    if cfg!(target_os = "linux") {
+        linux_full_screen()
+    } else if cfg!(target_os = "macos") {
+        macos_full_screen()
+    } else {
+        #[cfg(windows)]
+        return win32::presenting();
+        #[cfg(not(windows))]
+        false
+    }
+}
+
+fn detect() -> bool {
+    full_screen()
+        || SCREEN_SHARE_PROCESSES
+            .iter()
+            .any(|name| process_running(name))
+}
+
+fn update(app: &AppHandle, change: impl FnOnce(&mut PresentationState)) {
+    let (previous, next) = {
+        let presentation = app.state::<Presentation>();
+        let mut state = presentation.0.lock().unwrap();
+        let previous = *state;
+        change(&mut state);
+        state.active = state.forced.unwrap_or(state.detected);
+        (previous, *state)
+    };
+    if previous == next {
+        return;
+    }
+    events::emit(app, "presentation-mode", next);
+
+    // The live countdown would keep updating on screen
+    if next.active && !previous.active {
+        notification::withdraw(app);
+    }
+
+    // Whatever was held back comes back for the rest of the phase
+    if previous.active != next.active {
+        let phase = *app.state::<Phase>().0.lock().unwrap();
+        registry::show_overlays(app, phase);
+        if phase == TimePhase::Work {
+            audio::refresh_ambient(app);
+        }
+    }
+}
+
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || loop {
+        let detected = read_settings(&app).mute_while_presenting && detect();
+        update(&app, |state| state.detected = detected);
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+#[tauri::command]
+pub fn get_presentation_mode(presentation: tauri::State<Presentation>) -> PresentationState {
+    *presentation.0.lock().unwrap()
+}
+
+// true or false overrides what's detected, null goes back to detecting
+#[tauri::command]
+pub fn set_presentation_mode(forced: Option<bool>, app: AppHandle) -> PresentationState {
+    update(&app, |state| state.forced = forced);
+    *app.state::<Presentation>().0.lock().unwrap()
+}
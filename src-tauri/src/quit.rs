@@ -0,0 +1,69 @@
+use serde::Serialize;
+use tauri::{AppHandle, GlobalWindowEvent, Manager, WindowEvent};
+
+use crate::{
+    events, history, show_main_window, timer::Timer, with_store, Phase, TimePhase, MAIN_WINDOW,
+};
+
+#[derive(Serialize, Clone)]
+struct ConfirmQuit {
+    // Would be recorded as a partial session when quitting anyway
+    elapsed_minutes: i32,
+}
+
+// Only losing a Work session in progress is worth asking about
+fn work_running(app: &AppHandle) -> bool {
+    let phase = *app.state::<Phase>().0.lock().unwrap();
+    phase == TimePhase::Work && app.state::<Timer>().0.lock().unwrap().running
+}
+
+// Not credited to the stats, it only keeps the time in the history
+fn save_partial(app: &AppHandle) {
+    let minutes = history::elapsed_minutes(app);
+    with_store(app, |store| {
+        history::record(app, store, TimePhase::Work, minutes, false);
+        Ok(())
+    });
+}
+
+// The tray Quit item and closing the main window both end up here. Without
+// a window to ask in, e.g. `--headless`, the partial session is saved instead.
+pub fn request_quit(app: &AppHandle) {
+    if !work_running(app) {
+        app.exit(0);
+        return;
+    }
+    if app.get_window(MAIN_WINDOW).is_none() {
+        save_partial(app);
+        app.exit(0);
+        return;
+    }
+
+    show_main_window(app);
+    events::emit_once(
+        app,
+        "confirm-quit",
+        ConfirmQuit {
+            elapsed_minutes: history::elapsed_minutes(app),
+        },
+    );
+}
+
+pub fn on_window_event(event: &GlobalWindowEvent) {
+    if let WindowEvent::CloseRequested { api, .. } = event.event() {
+        let app = event.window().app_handle();
+        if event.window().label() == MAIN_WINDOW && work_running(&app) {
+            api.prevent_close();
+            request_quit(&app);
+        }
+    }
+}
+
+// The answer to `confirm-quit`. Not quitting needs no call.
+#[tauri::command]
+pub fn confirm_quit(save_partial_session: bool, app: AppHandle) {
+    if save_partial_session && work_running(&app) {
+        save_partial(&app);
+    }
+    app.exit(0);
+}
@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+use tauri::{
+    AppHandle, GlobalWindowEvent, Manager, Monitor, Window, WindowBuilder, WindowEvent, WindowUrl,
+};
+
+use crate::{presentation, read_settings, timer, Error, Phase, TimePhase, MAIN_WINDOW};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowKind {
+    Main,
+    // Small always-on-top countdown
+    Mini,
+    // Full screen break reminder, one per monitor, hidden during Work
+    Overlay,
+}
+
+impl WindowKind {
+    fn label(&self) -> &'static str {
+        match self {
+            WindowKind::Main => MAIN_WINDOW,
+            WindowKind::Mini => "mini",
+            WindowKind::Overlay => "overlay",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct WindowInfo {
+    pub label: String,
+    pub kind: WindowKind,
+}
+
+// Every open webview and what it's for. All of them get the broadcast
+// state events, this is for the few events aimed at one kind of window.
+#[derive(Default)]
+pub struct WindowRegistry(Mutex<HashMap<String, WindowKind>>);
+
+pub fn register(app: &AppHandle, label: &str, kind: WindowKind) {
+    let registry = app.state::<WindowRegistry>();
+    registry.0.lock().unwrap().insert(label.to_string(), kind);
+}
+
+pub fn windows_of(app: &AppHandle, kind: WindowKind) -> Vec<Window> {
+    let registry = app.state::<WindowRegistry>();
+    let labels: Vec<String> = registry
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, registered)| **registered == kind)
+        .map(|(label, _)| label.clone())
+        .collect();
+    labels
+        .iter()
+        .filter_map(|label| app.get_window(label))
+        .collect()
+}
+
+pub fn emit_to_kind<S: Serialize + Clone>(
+    app: &AppHandle,
+    kind: WindowKind,
+    event: &str,
+    payload: S,
+) {
+    for window in windows_of(app, kind) {
+        let _ = window.emit(event, payload.clone());
+    }
+}
+
+fn build_window(app: &AppHandle, kind: WindowKind, label: &str) -> Result<Window, Error> {
+    // Pages tell which window they are from their label
+    let builder = WindowBuilder::new(app, label, WindowUrl::App("index.html".into()))
+        .title("pomodorio")
+        .decorations(false);
+
+    let window = match kind {
+        WindowKind::Main => builder.inner_size(280.0, 354.0).resizable(false).build()?,
+        WindowKind::Mini => builder
+            .inner_size(200.0, 80.0)
+            .resizable(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .build()?,
+        WindowKind::Overlay => builder
+            .transparent(true)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .visible(false)
+            .build()?,
+    };
+    Ok(window)
+}
+
+// Monitors without a name of their own go by their position in the list
+fn monitor_name(monitor: &Monitor, index: usize) -> String {
+    monitor
+        .name()
+        .cloned()
+        .unwrap_or_else(|| format!("monitor-{index}"))
+}
+
+fn monitors(app: &AppHandle) -> Vec<Monitor> {
+    app.get_window(MAIN_WINDOW)
+        .and_then(|window| window.available_monitors().ok())
+        .unwrap_or_default()
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MonitorInfo {
+    // What `overlay_excluded_monitors` lists
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub excluded: bool,
+}
+
+#[tauri::command]
+pub fn list_monitors(app: AppHandle) -> Vec<MonitorInfo> {
+    let excluded = read_settings(&app).overlay_excluded_monitors;
+    monitors(&app)
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| {
+            let name = monitor_name(monitor, index);
+            MonitorInfo {
+                excluded: excluded.contains(&name),
+                width: monitor.size().width,
+                height: monitor.size().height,
+                name,
+            }
+        })
+        .collect()
+}
+
+// An overlay covering each monitor but the excluded ones. Overlays left
+// on monitors excluded or unplugged since are closed.
+fn open_overlays(app: &AppHandle) -> Result<Vec<Window>, Error> {
+    let excluded = read_settings(app).overlay_excluded_monitors;
+    let mut overlays = Vec::new();
+    for (index, monitor) in monitors(app).iter().enumerate() {
+        if excluded.contains(&monitor_name(monitor, index)) {
+            continue;
+        }
+        let label = format!("{}-{index}", WindowKind::Overlay.label());
+        let overlay = match app.get_window(&label) {
+            Some(overlay) => overlay,
+            None => build_window(app, WindowKind::Overlay, &label)?,
+        };
+        overlay.set_position(*monitor.position())?;
+        overlay.set_size(*monitor.size())?;
+        register(app, &label, WindowKind::Overlay);
+        overlays.push(overlay);
+    }
+
+    for stale in windows_of(app, WindowKind::Overlay) {
+        if !overlays
+            .iter()
+            .any(|overlay| overlay.label() == stale.label())
+        {
+            stale.close()?;
+        }
+    }
+    Ok(overlays)
+}
+
+#[tauri::command]
+pub fn open_window(kind: WindowKind, app: AppHandle) -> Result<(), Error> {
+    // The overlay shows itself when a break starts
+    if kind == WindowKind::Overlay {
+        let overlays = open_overlays(&app)?;
+        if read_settings(&app).pin_on_break.contains(&kind) {
+            let phase = *app.state::<Phase>().0.lock().unwrap();
+            for overlay in &overlays {
+                pin(overlay, phase);
+            }
+        }
+        return Ok(());
+    }
+
+    let window = match app.get_window(kind.label()) {
+        Some(window) => window,
+        None => build_window(&app, kind, kind.label())?,
+    };
+    register(&app, window.label(), kind);
+    let phase = *app.state::<Phase>().0.lock().unwrap();
+    if read_settings(&app).pin_on_break.contains(&kind) {
+        pin(&window, phase);
+    }
+
+    window.show()?;
+    window.set_focus()?;
+    timer::wake(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn close_window(kind: WindowKind, app: AppHandle) -> Result<(), Error> {
+    let windows = match kind {
+        WindowKind::Overlay => windows_of(&app, kind),
+        _ => app.get_window(kind.label()).into_iter().collect(),
+    };
+    for window in windows {
+        window.close()?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_windows(registry: tauri::State<WindowRegistry>) -> Vec<WindowInfo> {
+    registry
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(label, kind)| WindowInfo {
+            label: label.clone(),
+            kind: *kind,
+        })
+        .collect()
+}
+
+pub fn on_window_event(event: GlobalWindowEvent) {
+    match event.event() {
+        WindowEvent::Destroyed => {
+            let app = event.window().app_handle();
+            let registry = app.state::<WindowRegistry>();
+            registry.0.lock().unwrap().remove(event.window().label());
+        }
+        // A window coming up wants the countdown every second again
+        WindowEvent::Focused(true) => timer::wake(&event.window().app_handle()),
+        _ => {}
+    }
+}
+
+// Pinned windows are on top exactly while a break is on
+fn pin(window: &Window, phase: TimePhase) {
+    if let Err(err) = window.set_always_on_top(phase != TimePhase::Work) {
+        eprintln!("Failed to pin {}: {err}", window.label());
+    }
+}
+
+pub fn on_phase_change(app: &AppHandle, phase: TimePhase) {
+    for kind in read_settings(app).pin_on_break {
+        for window in windows_of(app, kind) {
+            pin(&window, phase);
+        }
+    }
+
+    let on_break = phase != TimePhase::Work;
+    // Monitors may have come and gone, or been excluded, since the last break
+    if on_break && !windows_of(app, WindowKind::Overlay).is_empty() {
+        if let Err(err) = open_overlays(app) {
+            eprintln!("Failed to lay out the break overlays: {err}");
+        }
+    }
+    show_overlays(app, phase);
+    timer::wake(app);
+}
+
+// Up during breaks, unless the screen is being presented
+pub fn show_overlays(app: &AppHandle, phase: TimePhase) {
+    let on_break = phase != TimePhase::Work && !presentation::active(app);
+    for overlay in windows_of(app, WindowKind::Overlay) {
+        let _ = if on_break {
+            overlay.show()
+        } else {
+            overlay.hide()
+        };
+    }
+
+    if on_break {
+        emit_to_kind(app, WindowKind::Overlay, "overlay-show", phase);
+    } else {
+        emit_to_kind(app, WindowKind::Overlay, "overlay-hide", ());
+    }
+}
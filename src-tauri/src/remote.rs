@@ -0,0 +1,389 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json, Map, Value};
+use std::{collections::BTreeMap, thread, time::Duration};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::Store;
+
+use crate::{
+    events, get_from_store, read_settings, secrets, settings_history, subsystems, tray, with_store,
+    Error, Settings, Stats,
+};
+
+const SYNC_TIMEOUT: Duration = Duration::from_secs(15);
+// Checked this often, syncing itself follows `interval_mins`
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+// Timestamps of the values as of the last sync, by settings key
+const SYNC_TIMES_KEY: &str = "sync_times";
+const DEVICE_ID_KEY: &str = "device_id";
+// Other machines' stats as of the last sync, by device id
+const REMOTE_STATS_KEY: &str = "remote_stats";
+// Holds the whole Authorization header, "Basic ..." for WebDAV or "Bearer ..."
+const AUTH_SECRET: &str = "sync";
+// What belongs to this machine: paths, apps, monitors, the servers it opens
+// and the sync itself
+const LOCAL_KEYS: [&str; 11] = [
+    "remote_sync",
+    "lan_sync",
+    "presence_file",
+    "countdown_image_path",
+    "allowed_apps",
+    "sounds",
+    "ambient",
+    "share_link",
+    "lan_companion",
+    "control_api",
+    "overlay_excluded_monitors",
+];
+
+// One JSON document at a URL that takes GET and PUT, e.g. a file on a
+// WebDAV share or an object in an S3-compatible bucket
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct RemoteSync {
+    pub url: Option<String>,
+    // Minutes between syncs in the background, 0 only syncs on `sync_now`
+    pub interval_mins: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+struct Stamped {
+    value: Value,
+    at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default)]
+struct Document {
+    settings: BTreeMap<String, Stamped>,
+    // Every machine's stats, each one only ever writes its own
+    stats: BTreeMap<String, Stamped>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SyncReport {
+    // Settings taken over from the remote
+    pub pulled: Vec<String>,
+    pub at: DateTime<Utc>,
+}
+
+// Last writer wins per key. Returns the document to push and the settings
+// keys whose remote value is newer than the local one.
+fn merge(
+    local: &Map<String, Value>,
+    local_times: &BTreeMap<String, DateTime<Utc>>,
+    remote: &Document,
+) -> (Document, Vec<String>) {
+    let mut merged = remote.clone();
+    let mut pulled = Vec::new();
+    let synced = local
+        .iter()
+        .filter(|(key, _)| !LOCAL_KEYS.contains(&key.as_str()));
+    for (key, value) in synced {
+        let at = local_times
+            .get(key)
+            .copied()
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        match remote.settings.get(key) {
+            Some(theirs) if theirs.at > at => {
+                if theirs.value != *value {
+                    pulled.push(key.clone());
+                }
+            }
+            _ => {
+                merged.settings.insert(
+                    key.clone(),
+                    Stamped {
+                        value: value.clone(),
+                        at,
+                    },
+                );
+            }
+        }
+    }
+    (merged, pulled)
+}
+
+// Copies the pulled keys' merged values into `settings`, skipping those the
+// settings wouldn't load with, e.g. a value from another version. Only the
+// keys copied are left in `pulled`.
+fn apply_pulled(settings: &mut Value, merged: &Document, pulled: &mut Vec<String>) {
+    pulled.retain(|key| {
+        let local = settings[key.as_str()].clone();
+        settings[key.as_str()] = merged.settings[key].value.clone();
+        if let Err(err) = from_value::<Settings>(settings.clone()) {
+            eprintln!("Skipped the synced value of {key}: {err}");
+            settings[key.as_str()] = local;
+            return false;
+        }
+        true
+    });
+}
+
+fn device_id(app: &AppHandle) -> String {
+    let mut id = String::new();
+    with_store(app, |store| {
+        match store.get(DEVICE_ID_KEY).and_then(|value| value.as_str()) {
+            Some(stored) => id = stored.to_string(),
+            None => {
+                id = format!("{:016x}", rand::random::<u64>());
+                store.insert(DEVICE_ID_KEY.into(), json!(id))?;
+            }
+        }
+        Ok(())
+    });
+    id
+}
+
+fn request(agent: &ureq::Agent, app: &AppHandle, method: &str, url: &str) -> ureq::Request {
+    let request = agent.request(method, url);
+    match secrets::get(app, AUTH_SECRET) {
+        Some(auth) => request.set("Authorization", &auth),
+        None => request,
+    }
+}
+
+fn fetch(agent: &ureq::Agent, app: &AppHandle, url: &str) -> Result<Document, Error> {
+    match request(agent, app, "GET", url).call() {
+        Ok(response) => Ok(response.into_json()?),
+        // Nothing pushed yet
+        Err(ureq::Error::Status(404, _)) => Ok(Document::default()),
+        Err(err) => Err(Error::Sync(err.to_string())),
+    }
+}
+
+// The local side of the merge. A key's time is its last local change, or
+// the time of the value last synced if that's newer.
+fn merge_into(
+    store: &mut Store<Wry>,
+    remote: &Document,
+    device: &str,
+    now: DateTime<Utc>,
+) -> Result<(Document, Vec<String>), Error> {
+    let settings: Map<String, Value> = get_from_store(store, "settings")?;
+    let mut times: BTreeMap<String, DateTime<Utc>> = store
+        .get(SYNC_TIMES_KEY)
+        .and_then(|value| from_value(value.clone()).ok())
+        .unwrap_or_default();
+    for (key, at) in settings_history::last_local_changes(store) {
+        let time = times.entry(key).or_insert(at);
+        *time = (*time).max(at);
+    }
+    let (mut merged, pulled) = merge(&settings, &times, remote);
+
+    let stats: Stats = get_from_store(store, "stats")?;
+    merged.stats.insert(
+        device.to_string(),
+        Stamped {
+            value: json!(stats),
+            at: now,
+        },
+    );
+    Ok((merged, pulled))
+}
+
+fn sync(app: &AppHandle, agent: &ureq::Agent) -> Result<SyncReport, Error> {
+    let Some(url) = read_settings(app).remote_sync.url else {
+        return Err(Error::Sync("no sync URL set".into()));
+    };
+    let remote = fetch(agent, app, &url)?;
+    let device = device_id(app);
+    let now = Utc::now();
+
+    let mut result = Ok((Document::default(), Vec::new()));
+    with_store(app, |store| {
+        result = merge_into(store, &remote, &device, now);
+        Ok(())
+    });
+    let (merged, mut pulled) = result?;
+
+    request(agent, app, "PUT", &url)
+        .send_json(&merged)
+        .map_err(|err| Error::Sync(err.to_string()))?;
+
+    // Only once the push went through, so a failed sync changes nothing here
+    with_store(app, |store| {
+        let before: Value = store.get("settings").cloned().unwrap_or_default();
+        let mut settings = before.clone();
+        apply_pulled(&mut settings, &merged, &mut pulled);
+        store.insert("settings".into(), settings.clone())?;
+        settings_history::record(store, &before, &settings, "sync");
+
+        let times: BTreeMap<_, _> = merged
+            .settings
+            .iter()
+            .map(|(key, stamped)| (key.clone(), stamped.at))
+            .collect();
+        store.insert(SYNC_TIMES_KEY.into(), json!(times))?;
+        let others: BTreeMap<_, _> = merged
+            .stats
+            .iter()
+            .filter(|(id, _)| **id != device)
+            .map(|(id, stamped)| (id.clone(), stamped.value.clone()))
+            .collect();
+        store.insert(REMOTE_STATS_KEY.into(), json!(others))
+    });
+
+    if !pulled.is_empty() {
+        events::emit(app, "settings-changed", read_settings(app));
+        tray::refresh(app);
+        subsystems::start_enabled(app);
+    }
+    let report = SyncReport { pulled, at: now };
+    events::emit(app, "remote-sync", &report);
+    Ok(report)
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(SYNC_TIMEOUT).build()
+}
+
+#[tauri::command]
+pub fn sync_now(app: AppHandle) -> Result<SyncReport, Error> {
+    sync(&app, &agent())
+}
+
+// The stats other machines pushed, by device id
+#[tauri::command]
+pub fn get_remote_stats(app: AppHandle) -> BTreeMap<String, Stats> {
+    let mut stats = BTreeMap::new();
+    with_store(&app, |store| {
+        stats = store
+            .get(REMOTE_STATS_KEY)
+            .and_then(|value| from_value(value.clone()).ok())
+            .unwrap_or_default();
+        Ok(())
+    });
+    stats
+}
+
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || {
+        let agent = agent();
+        let mut last_sync: Option<DateTime<Utc>> = None;
+        loop {
+            let settings = read_settings(&app).remote_sync;
+            let due = last_sync.map_or(true, |at| {
+                Utc::now() - at >= chrono::Duration::minutes(settings.interval_mins as i64)
+            });
+            if settings.url.is_some() && settings.interval_mins > 0 && due {
+                last_sync = Some(Utc::now());
+                if let Err(err) = sync(&app, &agent) {
+                    eprintln!("Remote sync failed: {err}");
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    fn stamped(value: Value, at_text: &str) -> Stamped {
+        Stamped {
+            value,
+            at: at(at_text),
+        }
+    }
+
+    #[test]
+    fn merge_keeps_the_newest_value_per_key() {
+        let local = json!({
+            "work_time": 30,
+            "short_break_time": 5,
+            "strict_mode": true,
+            "presence_file": "/tmp/presence.json",
+            "sounds": { "work_start": "/home/me/.local/share/pomodorio/sounds/work-start.wav" },
+            "ambient": { "path": null, "volume": 0.5 },
+            "share_link": false,
+            "lan_companion": false,
+            "control_api": false,
+            "overlay_excluded_monitors": ["DP-1"],
+        });
+        let local_times = BTreeMap::from([
+            ("work_time".to_string(), at("2024-03-04T10:00:00Z")),
+            ("short_break_time".to_string(), at("2024-03-01T10:00:00Z")),
+        ]);
+        let remote = Document {
+            settings: BTreeMap::from([
+                (
+                    "work_time".to_string(),
+                    stamped(json!(50), "2024-03-03T10:00:00Z"),
+                ),
+                (
+                    "short_break_time".to_string(),
+                    stamped(json!(10), "2024-03-02T10:00:00Z"),
+                ),
+                (
+                    "long_break_time".to_string(),
+                    stamped(json!(30), "2024-03-02T10:00:00Z"),
+                ),
+                (
+                    "share_link".to_string(),
+                    stamped(json!(true), "2024-03-02T10:00:00Z"),
+                ),
+                (
+                    "sounds".to_string(),
+                    stamped(
+                        json!({ "work_start": "C:\\sounds\\work-start.wav" }),
+                        "2024-03-02T10:00:00Z",
+                    ),
+                ),
+            ]),
+            stats: BTreeMap::new(),
+        };
+
+        let (merged, pulled) = merge(local.as_object().unwrap(), &local_times, &remote);
+
+        // Another machine's servers and sound files stay there
+        assert_eq!(pulled, ["short_break_time"]);
+        assert_eq!(
+            merged.settings["work_time"],
+            stamped(json!(30), "2024-03-04T10:00:00Z")
+        );
+        assert_eq!(merged.settings["short_break_time"].value, json!(10));
+        // Only on the remote, kept for the machines that know it
+        assert_eq!(merged.settings["long_break_time"].value, json!(30));
+        // Never changed here and not on the remote yet, pushed as the oldest possible value
+        assert_eq!(merged.settings["strict_mode"].at, DateTime::<Utc>::MIN_UTC);
+        assert!(!merged.settings.contains_key("presence_file"));
+        for key in [
+            "ambient",
+            "lan_companion",
+            "control_api",
+            "overlay_excluded_monitors",
+        ] {
+            assert!(!merged.settings.contains_key(key));
+        }
+    }
+
+    #[test]
+    fn pulled_values_the_settings_would_not_load_with_are_skipped() {
+        let mut settings = json!({ "work_time": 25, "short_break_time": 5 });
+        let merged = Document {
+            settings: BTreeMap::from([
+                (
+                    "work_time".to_string(),
+                    stamped(json!("soon"), "2024-03-03T10:00:00Z"),
+                ),
+                (
+                    "short_break_time".to_string(),
+                    stamped(json!(10), "2024-03-03T10:00:00Z"),
+                ),
+            ]),
+            stats: BTreeMap::new(),
+        };
+        let mut pulled = vec!["work_time".to_string(), "short_break_time".to_string()];
+
+        apply_pulled(&mut settings, &merged, &mut pulled);
+
+        assert_eq!(pulled, ["short_break_time"]);
+        assert_eq!(settings, json!({ "work_time": 25, "short_break_time": 10 }));
+    }
+}
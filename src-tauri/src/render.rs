@@ -0,0 +1,194 @@
+use std::{fs, path::PathBuf, thread, time::Duration};
+use tauri::{AppHandle, Manager};
+
+use crate::{read_settings, timer::Timer, Phase, TimePhase};
+
+const IMAGE_INTERVAL: Duration = Duration::from_secs(30);
+
+const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+
+// 5x7 bitmap font, one row per byte with the leftmost pixel in bit 4.
+// Only covers what the countdown and badges need to draw.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'N' => [0x11, 0x19, 0x15, 0x13, 0x11, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        _ => [0; GLYPH_HEIGHT as usize],
+    }
+}
+
+pub type Rgba = [u8; 4];
+
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: Rgba) -> Self {
+        let pixels = background
+            .iter()
+            .copied()
+            .cycle()
+            .take((width * height * 4) as usize)
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Rgba) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                let offset = ((py * self.width + px) * 4) as usize;
+                self.pixels[offset..offset + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, cx: u32, cy: u32, radius: u32, color: Rgba) {
+        let radius = radius as i64;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if dx * dx + dy * dy > radius * radius {
+                    continue;
+                }
+                let (x, y) = (cx as i64 + dx, cy as i64 + dy);
+                if x >= 0 && y >= 0 {
+                    self.fill_rect(x as u32, y as u32, 1, 1, color);
+                }
+            }
+        }
+    }
+
+    pub fn text_width(text: &str, scale: u32) -> u32 {
+        let chars = text.chars().count() as u32;
+        // One pixel of spacing between glyphs
+        (chars * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale
+    }
+
+    pub fn draw_text(&mut self, text: &str, x: u32, y: u32, scale: u32, color: Rgba) {
+        for (index, c) in text.chars().enumerate() {
+            let origin = x + index as u32 * (GLYPH_WIDTH + 1) * scale;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (0x10 >> col) != 0 {
+                        self.fill_rect(
+                            origin + col * scale,
+                            y + row as u32 * scale,
+                            scale,
+                            scale,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Draw text horizontally centered on the canvas
+    pub fn draw_text_centered(&mut self, text: &str, y: u32, scale: u32, color: Rgba) {
+        let width = Self::text_width(text, scale);
+        let x = self.width.saturating_sub(width) / 2;
+        self.draw_text(text, x, y, scale, color);
+    }
+
+    pub fn encode_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&self.pixels)?;
+        }
+        Ok(bytes)
+    }
+}
+
+pub fn phase_color(phase: TimePhase) -> Rgba {
+    match phase {
+        TimePhase::Work => [229, 72, 77, 255],
+        TimePhase::ShortBreak => [70, 167, 88, 255],
+        TimePhase::LongBreak => [62, 99, 221, 255],
+    }
+}
+
+pub fn render_countdown(
+    phase: TimePhase,
+    remaining_secs: i32,
+    running: bool,
+) -> Result<Vec<u8>, png::EncodingError> {
+    let mut canvas = Canvas::new(480, 200, [24, 24, 27, 255]);
+    let color = phase_color(phase);
+
+    canvas.fill_rect(0, 0, canvas.width, 8, color);
+    canvas.draw_text_centered(&phase.label().to_uppercase(), 32, 4, color);
+
+    let time = format!("{:02}:{:02}", remaining_secs / 60, remaining_secs % 60);
+    canvas.draw_text_centered(&time, 76, 12, [250, 250, 250, 255]);
+
+    if !running {
+        canvas.draw_text_centered("PAUSED", 172, 2, [161, 161, 170, 255]);
+    }
+    canvas.encode_png()
+}
+
+fn write_countdown_image(app: &AppHandle, path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let phase = *app.state::<Phase>().0.lock().unwrap();
+    let (remaining_secs, running) = {
+        let timer = app.state::<Timer>();
+        let state = timer.0.lock().unwrap();
+        (state.remaining_secs_now(), state.running)
+    };
+    let png = render_countdown(phase, remaining_secs, running)?;
+
+    // Write next to the target and rename, so readers never see a partial file
+    let partial = path.with_extension("png.part");
+    fs::write(&partial, png)?;
+    fs::rename(&partial, &path)?;
+    Ok(())
+}
+
+// Periodically render the countdown to `Settings.countdown_image_path`
+// for desktop widgets, conky or second-screen dashboards
+pub fn spawn_countdown_image(app: AppHandle) {
+    thread::spawn(move || loop {
+        if let Some(path) = read_settings(&app).countdown_image_path {
+            if let Err(err) = write_countdown_image(&app, PathBuf::from(&path)) {
+                eprintln!("Failed to write countdown image to {path}: {err}");
+            }
+        }
+        thread::sleep(IMAGE_INTERVAL);
+    });
+}
@@ -0,0 +1,162 @@
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::Serialize;
+use serde_json::{from_value, json, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    aligned_break_minutes, config, cycle, effective_settings, get_from_store, timebox,
+    timer::Timer, with_store, Error, Phase, SessionNumber, Settings, TimePhase,
+};
+
+// Enough for a long day at the shortest lengths
+const MAX_UPCOMING: usize = 200;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct UpcomingPhase {
+    pub phase: TimePhase,
+    pub session_number: i32,
+    pub minutes: i32,
+    // Estimated, assuming the timer keeps running and nothing is skipped
+    pub start: DateTime<Utc>,
+}
+
+impl UpcomingPhase {
+    fn end(&self) -> DateTime<Utc> {
+        self.start + Duration::minutes(self.minutes as i64)
+    }
+}
+
+// The cycle from `session_number` on, every length picked the way starting
+// that phase would: breaks aligned to the clock, and any break debt paid
+// off by the first break
+fn project(
+    settings: &Settings,
+    mut session_number: i32,
+    mut start: DateTime<Utc>,
+    mut debt: i32,
+) -> impl Iterator<Item = UpcomingPhase> + '_ {
+    std::iter::from_fn(move || {
+        let phase = cycle::phase_for_session(session_number, settings.long_break_interval);
+        let mut minutes = settings.minutes_for(phase);
+        if phase != TimePhase::Work {
+            if let Some(step) = settings.align_breaks_to {
+                minutes = aligned_break_minutes(start.with_timezone(&Local), minutes, step);
+            }
+            minutes += std::mem::take(&mut debt);
+        }
+
+        let upcoming = UpcomingPhase {
+            phase,
+            session_number,
+            minutes,
+            start,
+        };
+        start = upcoming.end();
+        session_number += 1;
+        Some(upcoming)
+    })
+}
+
+// The next `count` phases after the current one, for a timeline of the rest
+// of the day. A running timebox ends the cycle at its deadline.
+#[tauri::command]
+pub fn get_upcoming_phases(count: usize, app: AppHandle) -> Result<Vec<UpcomingPhase>, Error> {
+    let session_number = *app.state::<SessionNumber>().0.lock().unwrap();
+    let left_secs = {
+        let timer = app.state::<Timer>();
+        let state = timer.0.lock().unwrap();
+        state.remaining_secs_now() + state.grace_secs.unwrap_or(0)
+    };
+
+    let mut settings = Ok(Settings::default());
+    let mut debt = 0;
+    with_store(&app, |store| {
+        settings = effective_settings(&app, store);
+        debt = get_from_store(store, "break_debt").unwrap_or_default();
+        Ok(())
+    });
+    let settings = settings?;
+    // Already paid if the current phase is the break
+    let phase = *app.state::<Phase>().0.lock().unwrap();
+    if phase != TimePhase::Work {
+        debt = 0;
+    }
+
+    let start = Utc::now() + Duration::seconds(left_secs as i64);
+    let deadline = timebox::deadline(&app);
+    Ok(project(&settings, session_number + 1, start, debt)
+        .take_while(|upcoming| deadline.map_or(true, |deadline| upcoming.start < deadline))
+        .map(|mut upcoming| {
+            if let Some(deadline) = deadline {
+                let left = (deadline - upcoming.start).num_minutes() as i32;
+                upcoming.minutes = upcoming.minutes.min(left);
+            }
+            upcoming
+        })
+        .take(count.min(MAX_UPCOMING))
+        .collect())
+}
+
+#[derive(Serialize, Debug)]
+pub struct SimulatedSchedule {
+    // Up to the end of the last Work session, the break after it isn't needed
+    pub phases: Vec<UpcomingPhase>,
+    pub finish: DateTime<Utc>,
+    // Whether `finish` is before the deadline, when there is one
+    pub fits: Option<bool>,
+}
+
+// When `pomodoros` Work sessions starting at `start_time` would be done,
+// e.g. "can I fit 4 more before 6 PM?". `settings` is laid over the current
+// ones for the calculation only, nothing is stored or changed.
+#[tauri::command]
+pub fn simulate_schedule(
+    start_time: DateTime<Utc>,
+    pomodoros: usize,
+    settings: Option<Value>,
+    deadline: Option<DateTime<Utc>>,
+    app: AppHandle,
+) -> Result<SimulatedSchedule, Error> {
+    let mut current = Ok(Settings::default());
+    with_store(&app, |store| {
+        current = effective_settings(&app, store);
+        Ok(())
+    });
+    let mut merged = json!(current?);
+    if let Some(overrides) = &settings {
+        config::merge(&mut merged, overrides);
+    }
+    let settings: Settings = from_value(merged)?;
+
+    // Picks the cycle up where it is, so the long breaks land where they would
+    let mut session_number = *app.state::<SessionNumber>().0.lock().unwrap();
+    if cycle::phase_for_session(session_number, settings.long_break_interval) != TimePhase::Work {
+        session_number += 1;
+    }
+
+    let pomodoros = pomodoros.min(MAX_UPCOMING / 2);
+    let mut work_sessions = 0;
+    let phases: Vec<UpcomingPhase> = project(&settings, session_number, start_time, 0)
+        .take_while(|upcoming| {
+            let more = work_sessions < pomodoros;
+            if upcoming.phase == TimePhase::Work {
+                work_sessions += 1;
+            }
+            more
+        })
+        .collect();
+    let phases = match phases
+        .iter()
+        .rposition(|upcoming| upcoming.phase == TimePhase::Work)
+    {
+        Some(last_work) => phases[..=last_work].to_vec(),
+        None => Vec::new(),
+    };
+
+    let finish = phases.last().map_or(start_time, UpcomingPhase::end);
+    Ok(SimulatedSchedule {
+        phases,
+        finish,
+        fits: deadline.map(|deadline| finish <= deadline),
+    })
+}
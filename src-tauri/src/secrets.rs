@@ -0,0 +1,61 @@
+use keyring::Entry;
+use tauri::AppHandle;
+
+use crate::{subsystems, Error};
+
+// Integration tokens and passwords live in the OS keychain (Keychain,
+// Credential Manager, Secret Service), never in the store file. Entries are
+// named after the integration: "slack", "toggl", "jira", "smtp", "ntfy",
+// "caldav", "sync" for the remote settings sync and "lan" for LAN sync.
+pub const SECRET_NAMES: [&str; 8] = [
+    "slack", "toggl", "jira", "smtp", "ntfy", "caldav", "sync", "lan",
+];
+
+fn entry(app: &AppHandle, name: &str) -> keyring::Result<Entry> {
+    Entry::new(&app.config().tauri.bundle.identifier, name)
+}
+
+// What integrations call to get their credentials
+pub fn get(app: &AppHandle, name: &str) -> Option<String> {
+    match entry(app, name).and_then(|entry| entry.get_password()) {
+        Ok(secret) => Some(secret),
+        Err(keyring::Error::NoEntry) => None,
+        Err(err) => {
+            eprintln!("Failed to read secret {name}: {err}");
+            None
+        }
+    }
+}
+
+#[tauri::command]
+pub fn store_secret(name: String, value: String, app: AppHandle) -> Result<(), Error> {
+    // Anything else would outlive erasing all data
+    if !SECRET_NAMES.contains(&name.as_str()) {
+        return Err(Error::UnknownSecret(name));
+    }
+    entry(&app, &name)?.set_password(&value)?;
+    // LAN sync waits for its secret
+    if name == "lan" {
+        subsystems::start_enabled(&app);
+    }
+    Ok(())
+}
+
+// Deleting a secret that isn't set is fine
+pub fn delete(app: &AppHandle, name: &str) -> Result<(), Error> {
+    match entry(app, name)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[tauri::command]
+pub fn delete_secret(name: String, app: AppHandle) -> Result<(), Error> {
+    delete(&app, &name)
+}
+
+// The frontend only ever learns whether a secret is set, never its value
+#[tauri::command]
+pub fn has_secret(name: String, app: AppHandle) -> bool {
+    get(&app, &name).is_some()
+}
@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json, Value};
+use std::collections::HashMap;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::Store;
+
+use crate::{events, read_settings, subsystems, tray, with_store, Error, Settings};
+
+const HISTORY_KEY: &str = "settings_history";
+const HISTORY_LEN: usize = 50;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SettingChange {
+    pub key: String,
+    pub from: Value,
+    pub to: Value,
+    // Wasn't in the stored settings before, reverting removes it again
+    #[serde(default)]
+    pub added: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SettingsVersion {
+    pub version: u64,
+    pub at: DateTime<Utc>,
+    // "ui", "command", "config-file", "revert" or "sync"
+    pub source: String,
+    pub changes: Vec<SettingChange>,
+}
+
+fn load(store: &Store<Wry>) -> Vec<SettingsVersion> {
+    store
+        .get(HISTORY_KEY)
+        .and_then(|value| from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+// Top level settings that differ, nested ones count as a whole
+fn diff(before: &Value, after: &Value) -> Vec<SettingChange> {
+    let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+        return Vec::new();
+    };
+    after
+        .iter()
+        .filter(|(key, value)| before.get(*key) != Some(*value))
+        .map(|(key, value)| SettingChange {
+            key: key.clone(),
+            from: before.get(key).cloned().unwrap_or(Value::Null),
+            to: value.clone(),
+            added: !before.contains_key(key),
+        })
+        .collect()
+}
+
+pub fn record(store: &mut Store<Wry>, before: &Value, after: &Value, source: &str) {
+    let changes = diff(before, after);
+    if changes.is_empty() {
+        return;
+    }
+
+    let mut history = load(store);
+    let version = history.last().map_or(1, |last| last.version + 1);
+    history.push(SettingsVersion {
+        version,
+        at: Utc::now(),
+        source: source.to_string(),
+        changes,
+    });
+    if history.len() > HISTORY_LEN {
+        history.drain(..history.len() - HISTORY_LEN);
+    }
+    let _ = store.insert(HISTORY_KEY.into(), json!(history));
+}
+
+// When each stored setting was last changed on this machine, as far back as
+// the history goes. Config file changes never reach the store and changes
+// pulled by the remote sync aren't local, so both are left out.
+pub fn last_local_changes(store: &Store<Wry>) -> HashMap<String, DateTime<Utc>> {
+    let mut changed = HashMap::new();
+    let local = load(store)
+        .into_iter()
+        .filter(|entry| entry.source != "config-file" && entry.source != "sync");
+    for entry in local {
+        for change in entry.changes {
+            changed.insert(change.key, entry.at);
+        }
+    }
+    changed
+}
+
+#[tauri::command]
+pub fn get_settings_history(app: AppHandle) -> Vec<SettingsVersion> {
+    let mut history = Vec::new();
+    with_store(&app, |store| {
+        history = load(store);
+        Ok(())
+    });
+    history
+}
+
+// Puts the stored settings back the way they were right after `version`, by
+// undoing every later change. Config file changes are only listed, they live
+// in the file and are undone by editing it.
+#[tauri::command]
+pub fn revert_settings(version: u64, app: AppHandle) -> Result<Settings, Error> {
+    let mut result = Ok(());
+    with_store(&app, |store| {
+        let history = load(store);
+        if !history.iter().any(|entry| entry.version == version) {
+            result = Err(Error::UnknownSettingsVersion(version));
+            return Ok(());
+        }
+
+        let before: Value = store.get("settings").cloned().unwrap_or_default();
+        let mut settings = before.clone();
+        let later = history
+            .iter()
+            .rev()
+            .take_while(|entry| entry.version > version)
+            .filter(|entry| entry.source != "config-file");
+        for entry in later {
+            for change in &entry.changes {
+                match settings.as_object_mut() {
+                    Some(fields) if change.added => {
+                        fields.remove(&change.key);
+                    }
+                    _ => settings[change.key.as_str()] = change.from.clone(),
+                }
+            }
+        }
+        // Left as they are if the result wouldn't load
+        if let Err(err) = from_value::<Settings>(settings.clone()) {
+            result = Err(err.into());
+            return Ok(());
+        }
+
+        store.insert("settings".into(), settings.clone())?;
+        record(store, &before, &settings, "revert");
+        Ok(())
+    });
+    result?;
+
+    let settings = read_settings(&app);
+    events::emit(&app, "settings-changed", &settings);
+    tray::refresh(&app);
+    subsystems::start_enabled(&app);
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_marks_keys_that_were_not_stored_before() {
+        let changes = diff(
+            &json!({ "work_time": 25 }),
+            &json!({ "work_time": 30, "control_api": true }),
+        );
+        let changes: Vec<(&str, bool)> = changes
+            .iter()
+            .map(|change| (change.key.as_str(), change.added))
+            .collect();
+        assert_eq!(changes, [("control_api", true), ("work_time", false)]);
+    }
+}
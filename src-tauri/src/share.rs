@@ -0,0 +1,199 @@
+#[cfg(feature = "http-api")]
+use serde::Serialize;
+use serde_json::json;
+use std::net::{Ipv4Addr, UdpSocket};
+#[cfg(feature = "http-api")]
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+use tauri::AppHandle;
+
+use crate::with_store;
+#[cfg(feature = "http-api")]
+use crate::{
+    companion,
+    control::{read_request, respond, status_snapshot},
+    read_settings, TimePhase,
+};
+
+// Reachable from the LAN, unlike the control API, and read-only
+pub const SHARE_PORT: u16 = 47_834;
+const TOKEN_KEY: &str = "share_token";
+
+// What a guest gets to see: no tag, no stats
+#[cfg(feature = "http-api")]
+#[derive(Serialize, Clone, Debug)]
+struct GuestStatus {
+    phase: TimePhase,
+    label: &'static str,
+    remaining_secs: i32,
+    running: bool,
+}
+
+#[cfg(feature = "http-api")]
+fn guest_status(app: &AppHandle) -> GuestStatus {
+    let status = status_snapshot(app);
+    GuestStatus {
+        phase: status.phase,
+        label: status.phase.label(),
+        remaining_secs: status.remaining_secs,
+        running: status.running,
+    }
+}
+
+fn new_token() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+// The token in the link is the only thing keeping the page private, so it
+// survives restarts and can be rotated to revoke old links
+pub fn stored_token(app: &AppHandle, key: &str) -> String {
+    let mut token = String::new();
+    with_store(app, |store| {
+        match store.get(key).and_then(|value| value.as_str()) {
+            Some(stored) => token = stored.to_string(),
+            None => {
+                token = new_token();
+                store.insert(key.into(), json!(token))?;
+            }
+        }
+        Ok(())
+    });
+    token
+}
+
+fn share_token(app: &AppHandle) -> String {
+    stored_token(app, TOKEN_KEY)
+}
+
+// Address other machines can reach us on. Connecting a UDP socket sends
+// nothing, it only makes the OS pick the outgoing interface.
+pub fn lan_address() -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 168, 0, 1), 9)).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        std::net::IpAddr::V4(ip) if !ip.is_loopback() => Some(ip),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "http-api")]
+fn page(status: &GuestStatus) -> String {
+    let message = match (status.phase, status.running) {
+        (TimePhase::Work, true) => "Focusing, please don't interrupt",
+        (TimePhase::Work, false) => "Paused",
+        _ => "On a break, free to talk",
+    };
+    format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><meta http-equiv=\"refresh\" content=\"15\">\
+         <meta name=\"viewport\" content=\"width=device-width\"><title>Pomodorio</title></head>\
+         <body style=\"font-family:sans-serif;text-align:center;margin-top:20vh\">\
+         <h1>{} · {:02}:{:02}</h1><p>{message}</p></body></html>",
+        status.label,
+        status.remaining_secs / 60,
+        status.remaining_secs % 60
+    )
+}
+
+// Path of the request without reading it, for handlers that take over the stream
+#[cfg(feature = "http-api")]
+fn peek_path(stream: &TcpStream) -> std::io::Result<String> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let mut buffer = [0; 512];
+    let read = stream.peek(&mut buffer)?;
+    let line = String::from_utf8_lossy(&buffer[..read]);
+    Ok(line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string())
+}
+
+#[cfg(feature = "http-api")]
+fn handle_connection(
+    app: &AppHandle,
+    token: &str,
+    guest: bool,
+    companion: bool,
+    mut stream: TcpStream,
+) -> std::io::Result<()> {
+    let path = peek_path(&stream)?;
+    if companion && path.starts_with("/companion/") {
+        return companion::handle(app, &path, stream);
+    }
+
+    let (method, path) = read_request(&mut stream)?;
+    let page_path = format!("/share/{token}");
+    let json_path = format!("{page_path}.json");
+
+    if method != "GET" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            "Read only",
+        );
+    }
+    if guest && path == page_path {
+        respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            &page(&guest_status(app)),
+        )
+    } else if guest && path == json_path {
+        let body = json!(guest_status(app)).to_string();
+        respond(&mut stream, "200 OK", "application/json", &body)
+    } else {
+        respond(&mut stream, "404 Not Found", "text/plain", "Not found")
+    }
+}
+
+// One server for the guest page and the companion. Which of them answers is
+// read per request, turning both off leaves every path not found.
+#[cfg(feature = "http-api")]
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || {
+        let listener = match TcpListener::bind((Ipv4Addr::UNSPECIFIED, SHARE_PORT)) {
+            Ok(listener) => listener,
+            Err(err) => {
+                eprintln!("Share link disabled, cannot bind port {SHARE_PORT}: {err}");
+                return;
+            }
+        };
+
+        for stream in listener.incoming().flatten() {
+            // Read per request, so rotating the token revokes old links right away
+            let token = share_token(&app);
+            let settings = read_settings(&app);
+            let (guest, companion) = (settings.share_link, settings.lan_companion);
+            if let Err(err) = handle_connection(&app, &token, guest, companion, stream) {
+                eprintln!("Share request failed: {err}");
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_share_link(app: AppHandle) -> Option<String> {
+    // Nothing would answer it
+    if !cfg!(feature = "http-api") {
+        return None;
+    }
+    let host = lan_address()?;
+    Some(format!(
+        "http://{host}:{SHARE_PORT}/share/{}",
+        share_token(&app)
+    ))
+}
+
+#[tauri::command]
+pub fn reset_share_link(app: AppHandle) -> Option<String> {
+    with_store(&app, |store| {
+        store.insert(TOKEN_KEY.into(), json!(new_token()))?;
+        Ok(())
+    });
+    get_share_link(app)
+}
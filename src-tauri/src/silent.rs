@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::{events, tray, Error};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Boundary {
+    PhaseEnd,
+}
+
+// Either a time or "phase-end"
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum SilentUntil {
+    Time(DateTime<Utc>),
+    Boundary(Boundary),
+}
+
+// Our own sounds and notifications only, the OS's Do Not Disturb is left alone
+#[derive(Default)]
+pub struct SilentMode(Mutex<Option<SilentUntil>>);
+
+pub fn until(app: &AppHandle) -> Option<SilentUntil> {
+    let silent = *app.state::<SilentMode>().0.lock().unwrap();
+    match silent {
+        Some(SilentUntil::Time(time)) if time <= Utc::now() => None,
+        silent => silent,
+    }
+}
+
+pub fn active(app: &AppHandle) -> bool {
+    until(app).is_some()
+}
+
+// Doesn't touch the tray, it may run with the store held
+fn set(app: &AppHandle, silent: Option<SilentUntil>) -> bool {
+    let previous = std::mem::replace(&mut *app.state::<SilentMode>().0.lock().unwrap(), silent);
+    if previous == silent {
+        return false;
+    }
+    events::emit(app, "silent-mode", silent);
+    true
+}
+
+// From `enter_phase`, before the new phase's notification goes out
+pub fn on_phase_boundary(app: &AppHandle) {
+    if until(app) == Some(SilentUntil::Boundary(Boundary::PhaseEnd)) {
+        set(app, None);
+    }
+}
+
+// Lets go of a time that has passed
+pub fn on_tick(app: &AppHandle) {
+    let expired = app.state::<SilentMode>().0.lock().unwrap().is_some() && !active(app);
+    if expired && set(app, None) {
+        tray::refresh(app);
+    }
+}
+
+pub fn set_silent(app: &AppHandle, silent: Option<SilentUntil>) {
+    if set(app, silent) {
+        tray::refresh(app);
+    }
+}
+
+// `until` is required when enabling, a time in the past is refused
+#[tauri::command]
+pub fn set_silent_mode(
+    enabled: bool,
+    until: Option<SilentUntil>,
+    app: AppHandle,
+) -> Result<Option<SilentUntil>, Error> {
+    let silent = match (enabled, until) {
+        (false, _) => None,
+        (true, Some(SilentUntil::Time(time))) if time <= Utc::now() => {
+            return Err(Error::InvalidSilentMode(
+                "that time has already passed".into(),
+            ))
+        }
+        (true, Some(until)) => Some(until),
+        (true, None) => {
+            return Err(Error::InvalidSilentMode(
+                "say until when, a time or \"phase-end\"".into(),
+            ))
+        }
+    };
+    set_silent(&app, silent);
+    Ok(silent)
+}
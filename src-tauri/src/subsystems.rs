@@ -0,0 +1,66 @@
+use std::{collections::BTreeSet, sync::Mutex};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    commands, control, health, lan, noise, presentation, read_settings, remote, render, share,
+};
+
+// Optional parts that cost a thread, a port or a device. Each is started the
+// first time it's found enabled, at launch or after a settings change, and
+// runs until quit. From then on it follows the current settings itself, so
+// turning it off leaves it idle and other changes apply right away.
+#[derive(Default)]
+pub struct Started(Mutex<BTreeSet<&'static str>>);
+
+fn start_once(app: &AppHandle, name: &'static str, start: impl FnOnce()) {
+    let first = app.state::<Started>().0.lock().unwrap().insert(name);
+    if first {
+        start();
+    }
+}
+
+// Headless launches serve status bars, so they always get it
+pub fn start_control_api(app: &AppHandle) {
+    if cfg!(feature = "http-api") {
+        start_once(app, "control", || control::spawn(app.clone(), true));
+    }
+}
+
+// At launch and after the settings change
+pub fn start_enabled(app: &AppHandle) {
+    let settings = read_settings(app);
+    // Not a subsystem, but read on every command call, too often to read the settings for
+    commands::set_logging(app, settings.log_commands);
+    if cfg!(feature = "http-api") && settings.control_api {
+        start_once(app, "control", || control::spawn(app.clone(), false));
+    }
+    if settings.countdown_image_path.is_some() {
+        start_once(app, "countdown-image", || {
+            render::spawn_countdown_image(app.clone())
+        });
+    }
+    if settings.lan_sync.enabled {
+        start_once(app, "lan", || lan::spawn(app.clone()));
+    }
+    lan::refresh(app, &settings.lan_sync);
+    #[cfg(feature = "http-api")]
+    if settings.share_link || settings.lan_companion {
+        start_once(app, "share", || share::spawn(app.clone()));
+    }
+    if settings.remote_sync.url.is_some() {
+        start_once(app, "remote", || remote::spawn(app.clone()));
+    }
+    #[cfg(feature = "integrations-slack")]
+    let slack = settings.integrations.slack_status;
+    #[cfg(not(feature = "integrations-slack"))]
+    let slack = false;
+    if !settings.integrations.webhooks.is_empty() || slack {
+        start_once(app, "health", || health::spawn(app.clone()));
+    }
+    if settings.mute_while_presenting {
+        start_once(app, "presentation", || presentation::spawn(app.clone()));
+    }
+    if cfg!(feature = "audio") && settings.noise_check.enabled {
+        start_once(app, "noise", || noise::spawn(app.clone()));
+    }
+}
@@ -0,0 +1,75 @@
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, sync::Mutex};
+use tauri::{AppHandle, Manager};
+
+use crate::read_settings;
+
+// How many recent suggestions are kept out of the draw
+const NO_REPEAT: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BreakSuggestion {
+    pub text: String,
+    // Relative chance of being picked, 0 disables it
+    pub weight: u32,
+}
+
+fn suggestion(text: &str, weight: u32) -> BreakSuggestion {
+    BreakSuggestion {
+        text: text.to_string(),
+        weight,
+    }
+}
+
+pub fn default_suggestions() -> Vec<BreakSuggestion> {
+    vec![
+        suggestion("Stand up and stretch.", 3),
+        suggestion("Drink a glass of water.", 3),
+        suggestion("Take a short walk.", 2),
+        suggestion("Look at something far away for 20 seconds.", 2),
+        suggestion("Take a few slow, deep breaths.", 1),
+    ]
+}
+
+#[derive(Default)]
+pub struct RecentSuggestions(Mutex<VecDeque<String>>);
+
+pub fn pick(app: &AppHandle, suggestions: &[BreakSuggestion]) -> Option<String> {
+    let recent = app.state::<RecentSuggestions>();
+    let mut recent = recent.0.lock().unwrap();
+
+    let enabled: Vec<&BreakSuggestion> = suggestions.iter().filter(|s| s.weight > 0).collect();
+    let fresh: Vec<&BreakSuggestion> = enabled
+        .iter()
+        .copied()
+        .filter(|s| !recent.contains(&s.text))
+        .collect();
+    // Short lists can't avoid all of the last few, so only skip the last one
+    let candidates = if fresh.is_empty() {
+        enabled
+            .iter()
+            .copied()
+            .filter(|s| enabled.len() == 1 || recent.back() != Some(&s.text))
+            .collect()
+    } else {
+        fresh
+    };
+
+    let picked = candidates
+        .choose_weighted(&mut rand::thread_rng(), |s| s.weight)
+        .ok()?
+        .text
+        .clone();
+
+    if recent.len() == NO_REPEAT {
+        recent.pop_front();
+    }
+    recent.push_back(picked.clone());
+    Some(picked)
+}
+
+#[tauri::command]
+pub fn get_break_suggestion(app: AppHandle) -> Option<String> {
+    pick(&app, &read_settings(&app).break_suggestions)
+}
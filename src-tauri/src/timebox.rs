@@ -0,0 +1,163 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    caldav, cycle, effective_settings, events, reset_phase, timer, with_store, Error,
+    SessionNumber, Settings, TimePhase,
+};
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PlannedPhase {
+    pub phase: TimePhase,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    // Cut short to fit before the deadline
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct TimeboxPlan {
+    pub end: DateTime<Utc>,
+    pub phases: Vec<PlannedPhase>,
+    pub work_sessions: usize,
+}
+
+struct ActiveTimebox {
+    end: DateTime<Utc>,
+    phases_left: usize,
+}
+
+#[derive(Default)]
+pub struct Timebox(Mutex<Option<ActiveTimebox>>);
+
+fn minutes_until(end: DateTime<Utc>, from: DateTime<Utc>) -> i32 {
+    (end - from).num_minutes() as i32
+}
+
+// Lay out phases from `session_number` on until `end`. A break that would
+// run into the deadline is left out, there's nothing after it to rest for.
+fn plan(
+    settings: &Settings,
+    session_number: i32,
+    now: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<PlannedPhase> {
+    let mut phases = Vec::new();
+    let mut start = now;
+    let mut session_number = session_number;
+
+    loop {
+        let phase = cycle::phase_for_session(session_number, settings.long_break_interval);
+        let minutes = settings.minutes_for(phase);
+        let left = minutes_until(end, start);
+        if left <= 0 || (phase != TimePhase::Work && left <= minutes) {
+            break;
+        }
+
+        let length = minutes.min(left);
+        let stop = start + Duration::minutes(length as i64);
+        phases.push(PlannedPhase {
+            phase,
+            start,
+            end: stop,
+            truncated: length < minutes,
+        });
+        if length < minutes {
+            break;
+        }
+        start = stop;
+        session_number += 1;
+    }
+    phases
+}
+
+pub fn deadline(app: &AppHandle) -> Option<DateTime<Utc>> {
+    app.state::<Timebox>()
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|timebox| timebox.end)
+}
+
+// No phase in a timebox may run past its deadline
+pub fn cap_minutes(app: &AppHandle, minutes: i32) -> i32 {
+    match &*app.state::<Timebox>().0.lock().unwrap() {
+        Some(timebox) => minutes.min(minutes_until(timebox.end, Utc::now()).max(0)),
+        None => minutes,
+    }
+}
+
+// Restarts the current phase and runs the cycle until `end_time`, stopping there
+#[tauri::command]
+pub fn start_timebox(end_time: DateTime<Utc>, app: AppHandle) -> Result<TimeboxPlan, Error> {
+    let now = Utc::now();
+    if end_time <= now {
+        return Err(Error::InvalidTimebox(
+            "The end time has already passed".into(),
+        ));
+    }
+
+    let session_number = *app.state::<SessionNumber>().0.lock().unwrap();
+    let mut settings = Err(Error::InvalidTimebox("No settings".into()));
+    with_store(&app, |store| {
+        settings = effective_settings(&app, store);
+        Ok(())
+    });
+    let phases = plan(&settings?, session_number, now, end_time);
+    if phases.is_empty() {
+        return Err(Error::InvalidTimebox(
+            "Not enough time for a session".into(),
+        ));
+    }
+
+    let plan = TimeboxPlan {
+        end: end_time,
+        work_sessions: phases.iter().filter(|p| p.phase == TimePhase::Work).count(),
+        phases,
+    };
+    *app.state::<Timebox>().0.lock().unwrap() = Some(ActiveTimebox {
+        end: end_time,
+        phases_left: plan.phases.len(),
+    });
+
+    reset_phase(app.clone());
+    timer::set_running(&app, true);
+    events::emit(&app, "timebox-plan", Some(&plan));
+    caldav::publish(&app, &plan);
+    Ok(plan)
+}
+
+fn finish(app: &AppHandle) {
+    *app.state::<Timebox>().0.lock().unwrap() = None;
+    events::emit(app, "timebox-plan", None::<TimeboxPlan>);
+    caldav::withdraw(app);
+}
+
+#[tauri::command]
+pub fn cancel_timebox(app: AppHandle) {
+    finish(&app);
+}
+
+// Each phase change uses up one planned phase, the last one stops the timer
+pub fn on_phase_change(app: &AppHandle) {
+    let done = {
+        let timebox = app.state::<Timebox>();
+        let mut timebox = timebox.0.lock().unwrap();
+        let Some(active) = timebox.as_mut() else {
+            return;
+        };
+        active.phases_left = active.phases_left.saturating_sub(1);
+        active.phases_left == 0 || minutes_until(active.end, Utc::now()) <= 0
+    };
+
+    if done {
+        finish(app);
+        timer::set_running(app, false);
+        // The phase just entered was capped by the deadline, give it back its full length
+        reset_phase(app.clone());
+        events::emit_once(app, "timebox-finished", ());
+    }
+}
@@ -0,0 +1,403 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    sync::{Condvar, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    advance_phase, announce, audio, crash, events, history, lan, notification, power, presence,
+    read_settings, silent, tray, Error, Phase, TimePhase,
+};
+
+#[derive(Default)]
+pub struct TimerState {
+    pub running: bool,
+    pub remaining_secs: i32,
+    // Minutes the phase was given when it was entered, what completing it credits
+    pub given_minutes: i32,
+    // Counts down after the phase ran out, until it's extended or skipped
+    pub grace_secs: Option<i32>,
+    // Time up to here is already taken off the countdown, None while paused
+    counted_until: Option<Counted>,
+}
+
+// The same moment on both clocks
+#[derive(Clone, Copy)]
+struct Counted {
+    monotonic: Instant,
+    wall: SystemTime,
+}
+
+impl Counted {
+    fn now() -> Self {
+        Self {
+            monotonic: Instant::now(),
+            wall: SystemTime::now(),
+        }
+    }
+
+    // The monotonic clock can't be set, so clock changes and DST don't move
+    // the countdown. It stops while the machine sleeps on Linux and macOS
+    // though, so a wall clock well ahead of it is counted too.
+    fn elapsed(&self, now: Counted) -> Duration {
+        let monotonic = now.monotonic.saturating_duration_since(self.monotonic);
+        let wall = now.wall.duration_since(self.wall).unwrap_or_default();
+        let suspended = wall
+            .checked_sub(monotonic)
+            .filter(|gap| *gap > SUSPEND_GAP)
+            .unwrap_or_default();
+        monotonic + suspended
+    }
+}
+
+// Wall clock time beyond this, that the monotonic clock didn't see, was
+// spent asleep. Anything less is NTP slewing the wall clock.
+const SUSPEND_GAP: Duration = Duration::from_secs(5);
+
+impl TimerState {
+    // Whole seconds since the last count, the fraction carries over. A
+    // stalled thread catches up when it gets to run again.
+    fn take_elapsed(&mut self) -> i32 {
+        let Some(since) = self.counted_until else {
+            return 0;
+        };
+        let now = Counted::now();
+        let elapsed = since.elapsed(now);
+        let carry = elapsed - Duration::from_secs(elapsed.as_secs());
+        self.counted_until = Some(Counted {
+            monotonic: now.monotonic.checked_sub(carry).unwrap_or(now.monotonic),
+            wall: now.wall.checked_sub(carry).unwrap_or(now.wall),
+        });
+        elapsed.as_secs() as i32
+    }
+
+    pub fn set_running(&mut self, running: bool) {
+        if running == self.running {
+            return;
+        }
+        if !running && self.grace_secs.is_none() {
+            let secs = self.take_elapsed();
+            self.remaining_secs = (self.remaining_secs - secs).max(0);
+        }
+        self.running = running;
+        self.restart_clock();
+    }
+
+    // For when the countdown is set rather than counted down
+    pub fn restart_clock(&mut self) {
+        self.counted_until = self.running.then(Counted::now);
+    }
+
+    // Where the countdown lands, grace period included, if it keeps running
+    fn ends_at(&self) -> Option<DateTime<Utc>> {
+        let left = self.remaining_secs_now() + self.grace_secs.unwrap_or(0);
+        self.running
+            .then(|| Utc::now() + chrono::Duration::seconds(left as i64))
+    }
+
+    // Includes the time since the timer last woke up, which can be a while
+    // when nothing's on screen, counted as the next tick will count it
+    pub fn remaining_secs_now(&self) -> i32 {
+        let pending = match (self.counted_until, self.grace_secs) {
+            (Some(since), None) => since.elapsed(Counted::now()).as_secs() as i32,
+            _ => 0,
+        };
+        (self.remaining_secs - pending).max(0)
+    }
+}
+
+// Backend countdown, so the cycle keeps going without a webview driving it.
+// The condvar wakes the timer thread early when the countdown changes.
+pub struct Timer(pub Mutex<TimerState>, Condvar);
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self(Mutex::new(TimerState::default()), Condvar::new())
+    }
+}
+
+// Absolute times for the current phase, so frontends and integrations can
+// show "ends at 14:05" without running their own countdown
+#[derive(Serialize, Clone, Debug)]
+pub struct PhaseTimes {
+    pub phase_started_at: DateTime<Utc>,
+    // None while paused
+    pub phase_ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct Tick {
+    schema_version: u32,
+    remaining_secs: i32,
+    #[serde(flatten)]
+    times: PhaseTimes,
+}
+
+fn times_of(app: &AppHandle, state: &TimerState) -> PhaseTimes {
+    PhaseTimes {
+        phase_started_at: history::phase_started_at(app),
+        phase_ends_at: state.ends_at(),
+    }
+}
+
+pub fn phase_times(app: &AppHandle) -> PhaseTimes {
+    times_of(app, &app.state::<Timer>().0.lock().unwrap())
+}
+
+// Takes the state rather than locking it, callers are in the middle of changing it
+pub fn emit_tick(app: &AppHandle, state: &TimerState) {
+    let tick = Tick {
+        schema_version: events::SCHEMA_VERSION,
+        remaining_secs: state.remaining_secs,
+        times: times_of(app, state),
+    };
+    events::emit(app, "tick", tick);
+}
+
+// Counts every second while something shows the countdown. Otherwise it
+// only wakes up this often, or right when the phase or grace period ends.
+const VISIBLE_TICK_SECS: u64 = 1;
+const HIDDEN_TICK_SECS: u64 = 20;
+
+pub fn wake(app: &AppHandle) {
+    app.state::<Timer>().1.notify_one();
+}
+
+// Restart the countdown for a phase lasting `minutes`
+pub fn reset(app: &AppHandle, minutes: i32) {
+    let timer = app.state::<Timer>();
+    let mut state = timer.0.lock().unwrap();
+    state.remaining_secs = minutes * 60;
+    state.given_minutes = minutes;
+    state.restart_clock();
+    if state.grace_secs.take().is_some() {
+        events::emit(app, "grace-period", None::<i32>);
+    }
+    emit_tick(app, &state);
+    timer.1.notify_one();
+}
+
+pub fn set_running(app: &AppHandle, running: bool) {
+    let timer = app.state::<Timer>();
+    {
+        let mut state = timer.0.lock().unwrap();
+        state.set_running(running);
+        // Pausing and resuming moves the end time
+        emit_tick(app, &state);
+    }
+    events::emit(app, "timer-running", running);
+    timer.1.notify_one();
+    presence::update(app);
+    crash::update(app);
+}
+
+fn next_wakeup(state: &TimerState, visible: bool) -> Duration {
+    let Some(since) = state.counted_until.filter(|_| state.running) else {
+        return Duration::from_secs(HIDDEN_TICK_SECS);
+    };
+    let secs = if visible {
+        VISIBLE_TICK_SECS
+    } else {
+        let left = state.grace_secs.unwrap_or(state.remaining_secs).max(1) as u64;
+        left.min(HIDDEN_TICK_SECS)
+    };
+    (since.monotonic + Duration::from_secs(secs)).saturating_duration_since(Instant::now())
+}
+
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || loop {
+        let visible = app
+            .windows()
+            .values()
+            .any(|window| window.is_visible().unwrap_or(false));
+        {
+            // Held from working out the wait until waiting, so a wake can't slip in between
+            let timer = app.state::<Timer>();
+            let state = timer.0.lock().unwrap();
+            let wait = next_wakeup(&state, visible);
+            drop(timer.1.wait_timeout(state, wait).unwrap());
+        }
+        tick(&app);
+    });
+}
+
+enum Step {
+    Counting { from: i32, to: i32 },
+    Grace(i32),
+    GraceOver,
+}
+
+fn tick(app: &AppHandle) {
+    let step = {
+        let timer = app.state::<Timer>();
+        let mut state = timer.0.lock().unwrap();
+        if !state.running {
+            return;
+        }
+        let secs = state.take_elapsed();
+        if secs == 0 {
+            return;
+        }
+        match state.grace_secs {
+            Some(grace) if grace <= secs => {
+                state.grace_secs = None;
+                Step::GraceOver
+            }
+            Some(grace) => {
+                state.grace_secs = Some(grace - secs);
+                Step::Grace(grace - secs)
+            }
+            None => {
+                let from = state.remaining_secs;
+                let to = (from - secs).max(0);
+                state.remaining_secs = to;
+                // Coarser updates on low battery, but every second of the last minute
+                let step = power::LOW_POWER_TICK_SECS;
+                if to <= 60 || from / step != to / step || !power::low_power(app) {
+                    emit_tick(app, &state);
+                }
+                Step::Counting { from, to }
+            }
+        }
+    };
+
+    // The lock is released before switching, which resets the countdown
+    match step {
+        Step::Counting { from, to } => {
+            let phase = *app.state::<Phase>().0.lock().unwrap();
+            // Straight to where the count landed, however long it slept. The
+            // hooks fire once for each of their moments that was passed.
+            let passed = to..from;
+            announce::on_tick(app, phase, &passed);
+            audio::on_tick(app, phase, &passed);
+            notification::on_tick(app, &passed);
+            tray::on_tick(app, to);
+            silent::on_tick(app);
+            // Keeps what a crash would lose to under a minute
+            if phase == TimePhase::Work && from / 60 != to / 60 {
+                crash::update(app);
+            }
+
+            if to == 0 {
+                let grace = read_settings(app).grace_period_secs;
+                if grace > 0 {
+                    app.state::<Timer>().0.lock().unwrap().grace_secs = Some(grace);
+                    events::emit(app, "grace-period", Some(grace));
+                } else {
+                    advance_phase(app, false);
+                }
+            }
+        }
+        Step::Grace(grace) => events::emit(app, "grace-period", Some(grace)),
+        Step::GraceOver => {
+            events::emit(app, "grace-period", None::<i32>);
+            advance_phase(app, false);
+        }
+    }
+}
+
+// Keep going for a few more minutes instead of switching, during the grace period or before
+#[tauri::command]
+pub fn extend_phase(minutes: i32, app: AppHandle) {
+    {
+        let timer = app.state::<Timer>();
+        let mut state = timer.0.lock().unwrap();
+        state.remaining_secs += minutes.max(1) * 60;
+        if state.grace_secs.take().is_some() {
+            state.restart_clock();
+            events::emit(&app, "grace-period", None::<i32>);
+        }
+        emit_tick(&app, &state);
+        timer.1.notify_one();
+    }
+    presence::update(&app);
+    lan::local_change(&app);
+}
+
+// Switch right away. The phase did run out, so a Work session still counts.
+#[tauri::command]
+pub fn skip_grace_period(app: AppHandle) {
+    let in_grace = app
+        .state::<Timer>()
+        .0
+        .lock()
+        .unwrap()
+        .grace_secs
+        .take()
+        .is_some();
+    if in_grace {
+        events::emit(&app, "grace-period", None::<i32>);
+        advance_phase(&app, false);
+    }
+}
+
+#[tauri::command]
+pub fn start_timer(app: AppHandle) {
+    set_running(&app, true);
+    lan::local_change(&app);
+}
+
+#[tauri::command]
+pub fn pause_timer(app: AppHandle) {
+    set_running(&app, false);
+    lan::local_change(&app);
+}
+
+#[derive(Serialize, Debug)]
+pub struct TimingReport {
+    pub samples: u32,
+    // How far each one second sleep was off, on average and at worst
+    pub mean_jitter_ms: f64,
+    pub max_jitter_ms: f64,
+    // What a countdown that trusted every sleep to be exactly a second
+    // would be behind by at the end, positive when the sleeps ran long
+    pub sleep_drift_ms: f64,
+    // How much further the wall clock moved than the monotonic one, from
+    // NTP corrections or the machine sleeping during the test
+    pub wall_clock_drift_ms: f64,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn measure_timing(samples: u32) -> TimingReport {
+    let interval = Duration::from_secs(1);
+    let started = Counted::now();
+    let mut last = started.monotonic;
+    let mut total_jitter = Duration::ZERO;
+    let mut max_jitter = Duration::ZERO;
+    for _ in 0..samples {
+        thread::sleep(interval);
+        let now = Instant::now();
+        let slept = now - last;
+        last = now;
+        let jitter = slept.saturating_sub(interval) + interval.saturating_sub(slept);
+        total_jitter += jitter;
+        max_jitter = max_jitter.max(jitter);
+    }
+
+    let monotonic = millis(last - started.monotonic);
+    let wall = SystemTime::now()
+        .duration_since(started.wall)
+        .map_or(0.0, millis);
+    TimingReport {
+        samples,
+        mean_jitter_ms: millis(total_jitter) / samples as f64,
+        max_jitter_ms: millis(max_jitter),
+        sleep_drift_ms: monotonic - millis(interval * samples),
+        wall_clock_drift_ms: wall - monotonic,
+    }
+}
+
+// Sleeps a second at a time the way the timer thread does, for `secs`
+// seconds clamped to 2 to 60 and 10 when unset, and measures how it went,
+// for bug reports about a slow or fast timer
+#[tauri::command]
+pub async fn run_timing_selftest(secs: Option<u32>) -> Result<TimingReport, Error> {
+    let samples = secs.unwrap_or(10).clamp(2, 60);
+    Ok(tauri::async_runtime::spawn_blocking(move || measure_timing(samples)).await?)
+}
@@ -0,0 +1,183 @@
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{
+    analytics::Range,
+    history::{self, SessionRecord},
+    read_settings, with_store, TimePhase,
+};
+
+// Entries without a tag are filed under this project
+const NO_PROJECT: &str = "Pomodorio";
+const DESCRIPTION: &str = "Pomodoro";
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Schema {
+    Toggl,
+    Harvest,
+    Clockify,
+}
+
+// Who the entries belong to, the importers match on it
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct TimesheetIdentity {
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+}
+
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn duration(minutes: i32) -> String {
+    format!("{:02}:{:02}:00", minutes / 60, minutes % 60)
+}
+
+fn header(schema: Schema) -> &'static str {
+    match schema {
+        Schema::Toggl => "Email,Start date,Start time,Duration,Project,Description,Tags",
+        Schema::Harvest => "Date,Client,Project,Task,Notes,Hours,First name,Last name",
+        Schema::Clockify => {
+            "Project,Client,Description,Task,Email,Tags,Billable,Start Date,Start Time,Duration (h)"
+        }
+    }
+}
+
+fn row<Tz: TimeZone>(
+    schema: Schema,
+    record: &SessionRecord,
+    identity: &TimesheetIdentity,
+    tz: &Tz,
+) -> Vec<String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let start: DateTime<Tz> = record.start.with_timezone(tz);
+    let project = record.tag.as_deref().unwrap_or(NO_PROJECT);
+    match schema {
+        Schema::Toggl => vec![
+            identity.email.clone(),
+            start.format("%Y-%m-%d").to_string(),
+            start.format("%H:%M:%S").to_string(),
+            duration(record.minutes),
+            project.to_string(),
+            DESCRIPTION.to_string(),
+            "pomodoro".to_string(),
+        ],
+        Schema::Harvest => vec![
+            start.format("%Y-%m-%d").to_string(),
+            String::new(),
+            project.to_string(),
+            DESCRIPTION.to_string(),
+            String::new(),
+            format!("{:.2}", record.minutes as f64 / 60.0),
+            identity.first_name.clone(),
+            identity.last_name.clone(),
+        ],
+        Schema::Clockify => vec![
+            project.to_string(),
+            String::new(),
+            DESCRIPTION.to_string(),
+            String::new(),
+            identity.email.clone(),
+            "pomodoro".to_string(),
+            "No".to_string(),
+            start.format("%m/%d/%Y").to_string(),
+            start.format("%I:%M %p").to_string(),
+            duration(record.minutes),
+        ],
+    }
+}
+
+// Completed Work sessions, oldest first, with the times in `tz`
+fn timesheet<Tz: TimeZone>(
+    records: &[SessionRecord],
+    schema: Schema,
+    identity: &TimesheetIdentity,
+    tz: &Tz,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let mut lines = vec![header(schema).to_string()];
+    let completed = records
+        .iter()
+        .filter(|record| record.completed && record.phase == TimePhase::Work);
+    for record in completed {
+        let fields: Vec<String> = row(schema, record, identity, tz)
+            .iter()
+            .map(|value| field(value))
+            .collect();
+        lines.push(fields.join(","));
+    }
+    lines.push(String::new());
+    lines.join("\r\n")
+}
+
+// CSV for the time tracker's bulk import, one entry per pomodoro and the
+// tag as the project. The frontend saves it wherever the user picks.
+#[tauri::command]
+pub fn export_timesheet(range: Range, schema: Schema, app: AppHandle) -> String {
+    let today = Utc::now().date_naive();
+    let mut records = Vec::new();
+    with_store(&app, |store| {
+        records = history::load(store);
+        Ok(())
+    });
+    records.retain(|record| range.contains(record.end.date_naive(), today));
+    timesheet(&records, schema, &read_settings(&app).timesheet, &Local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn work(start: &str, minutes: i32, tag: Option<&str>) -> SessionRecord {
+        SessionRecord {
+            tag: tag.map(str::to_string),
+            ..SessionRecord::started(TimePhase::Work, start, minutes)
+        }
+    }
+
+    #[test]
+    fn timesheet_lays_out_each_schema() {
+        let records = [
+            work("2024-03-04T09:00:00Z", 25, Some("writing, docs")),
+            work("2024-03-04T13:30:00Z", 90, None),
+            SessionRecord {
+                completed: false,
+                ..work("2024-03-04T15:00:00Z", 10, None)
+            },
+        ];
+        let identity = TimesheetIdentity {
+            email: "me@example.com".into(),
+            first_name: "Sam".into(),
+            last_name: "Doe".into(),
+        };
+
+        assert_eq!(
+            timesheet(&records, Schema::Toggl, &identity, &Utc),
+            "Email,Start date,Start time,Duration,Project,Description,Tags\r\n\
+             me@example.com,2024-03-04,09:00:00,00:25:00,\"writing, docs\",Pomodoro,pomodoro\r\n\
+             me@example.com,2024-03-04,13:30:00,01:30:00,Pomodorio,Pomodoro,pomodoro\r\n"
+        );
+        assert_eq!(
+            timesheet(&records[1..], Schema::Harvest, &identity, &Utc),
+            "Date,Client,Project,Task,Notes,Hours,First name,Last name\r\n\
+             2024-03-04,,Pomodorio,Pomodoro,,1.50,Sam,Doe\r\n"
+        );
+        assert_eq!(
+            timesheet(&records[1..], Schema::Clockify, &identity, &Utc),
+            "Project,Client,Description,Task,Email,Tags,Billable,Start Date,Start Time,Duration (h)\r\n\
+             Pomodorio,,Pomodoro,,me@example.com,pomodoro,No,03/04/2024,01:30 PM,01:30:00\r\n"
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use chrono::{Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    sync::{Condvar, Mutex, OnceLock},
+    thread,
+    time::Duration,
+};
+use tauri::{
+    AppHandle, CustomMenuItem, Icon, Manager, SystemTrayMenu, SystemTrayMenuItem, SystemTraySubmenu,
+};
+
+use crate::{
+    events, get_from_store, modify_settings, read_settings,
+    render::{self, Canvas},
+    silent::{self, Boundary, SilentUntil},
+    timer::Timer,
+    with_store, ActiveTag, Phase, Settings, Stats,
+};
+
+const TRAY_ICON: &[u8] = include_bytes!("../icons/pomodoro.png");
+// Share of the icon the badge covers, from the bottom right corner
+const BADGE_FRACTION: u32 = 2;
+const BADGE_TEXT: render::Rgba = [255, 255, 255, 255];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TrayDisplay {
+    // The countdown next to the icon, macOS only since other trays have no title
+    #[default]
+    Title,
+    // Pomodoros completed today, drawn onto the icon
+    Badge,
+    // Just the icon
+    Icon,
+}
+
+// Optional parts of the tray menu, silent mode, Quit and Hide are always there
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TrayMenu {
+    // Today's pomodoros and minutes, as a disabled line at the top
+    pub stats: bool,
+    // The tags named in `tag_overrides` and `tag_rules`, to switch between
+    pub tags: bool,
+    // One click Work lengths, taken from `preset_minutes`
+    pub presets: bool,
+    pub preset_minutes: Vec<i32>,
+}
+
+impl Default for TrayMenu {
+    fn default() -> Self {
+        Self {
+            stats: true,
+            tags: false,
+            presets: false,
+            preset_minutes: vec![15, 25, 50],
+        }
+    }
+}
+
+// What the last refresh found in the settings, so the per-second title update
+// doesn't read them. Every settings change refreshes the tray.
+#[derive(Default)]
+pub struct CurrentDisplay(Mutex<TrayDisplay>);
+
+// Refreshes asked for within this long of the first one are drawn once, a
+// skip changes the phase, the stats and the tag in quick succession
+const COALESCE: Duration = Duration::from_millis(250);
+
+// Set while a refresh is waiting to be drawn
+#[derive(Default)]
+pub struct PendingRefresh(Mutex<bool>, Condvar);
+
+const TAG_PREFIX: &str = "tag:";
+const NO_TAG: &str = "tag-none";
+const PRESET_PREFIX: &str = "preset:";
+const SILENT_OFF: &str = "silent-off";
+const SILENT_PHASE: &str = "silent-phase";
+const SILENT_HOUR: &str = "silent-hour";
+
+fn item(id: impl Into<String>, title: impl Into<String>, selected: bool) -> CustomMenuItem {
+    let item = CustomMenuItem::new(id, title);
+    if selected {
+        item.selected()
+    } else {
+        item
+    }
+}
+
+fn tag_names(settings: &Settings) -> BTreeSet<&str> {
+    let overrides = settings.tag_overrides.keys().map(String::as_str);
+    let rules = settings.tag_rules.iter().map(|rule| rule.tag.as_str());
+    overrides.chain(rules).collect()
+}
+
+fn build_menu(app: &AppHandle, settings: &Settings, stats: Option<Stats>) -> SystemTrayMenu {
+    let options = &settings.tray_menu;
+    let mut menu = SystemTrayMenu::new();
+
+    if options.stats {
+        let line = stats.map_or("No stats yet".to_string(), |stats| {
+            format!(
+                "Today: {} pomodoros, {} min",
+                stats.today.sessions, stats.today.minutes
+            )
+        });
+        menu = menu
+            .add_item(CustomMenuItem::new("stats", line).disabled())
+            .add_native_item(SystemTrayMenuItem::Separator);
+    }
+
+    let tags = tag_names(settings);
+    if options.tags && !tags.is_empty() {
+        let active = app.state::<ActiveTag>().0.lock().unwrap().clone();
+        let mut submenu = SystemTrayMenu::new().add_item(item(NO_TAG, "No tag", active.is_none()));
+        for tag in tags {
+            let selected = active.as_deref() == Some(tag);
+            submenu = submenu.add_item(item(format!("{TAG_PREFIX}{tag}"), tag, selected));
+        }
+        menu = menu.add_submenu(SystemTraySubmenu::new("Tag", submenu));
+    }
+
+    if options.presets && !options.preset_minutes.is_empty() {
+        for &minutes in &options.preset_minutes {
+            let selected = settings.work_time == minutes;
+            menu = menu.add_item(item(
+                format!("{PRESET_PREFIX}{minutes}"),
+                format!("Work for {minutes} min"),
+                selected,
+            ));
+        }
+    }
+
+    if options.tags || options.presets {
+        menu = menu.add_native_item(SystemTrayMenuItem::Separator);
+    }
+
+    menu = match silent::until(app) {
+        Some(until) => {
+            let line = match until {
+                SilentUntil::Time(time) => {
+                    format!(
+                        "Silent until {}",
+                        time.with_timezone(&Local).format("%H:%M")
+                    )
+                }
+                SilentUntil::Boundary(Boundary::PhaseEnd) => {
+                    "Silent until the phase ends".to_string()
+                }
+            };
+            menu.add_item(CustomMenuItem::new("silent", line).disabled())
+                .add_item(CustomMenuItem::new(SILENT_OFF, "Unsilence"))
+        }
+        None => menu
+            .add_item(CustomMenuItem::new(
+                SILENT_PHASE,
+                "Silence until the phase ends",
+            ))
+            .add_item(CustomMenuItem::new(SILENT_HOUR, "Silence for an hour")),
+    };
+    menu.add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("quit", "Quit"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("hide", "Hide"))
+}
+
+// Handles the items `build_menu` adds, returns false for anything else
+pub fn on_menu_click(app: &AppHandle, id: &str) -> bool {
+    if id == NO_TAG {
+        crate::set_active_tag(app, None);
+    } else if let Some(tag) = id.strip_prefix(TAG_PREFIX) {
+        crate::set_active_tag(app, Some(tag.to_string()));
+    } else if id == SILENT_OFF {
+        silent::set_silent(app, None);
+    } else if id == SILENT_PHASE {
+        silent::set_silent(app, Some(SilentUntil::Boundary(Boundary::PhaseEnd)));
+    } else if id == SILENT_HOUR {
+        let until = Utc::now() + chrono::Duration::hours(1);
+        silent::set_silent(app, Some(SilentUntil::Time(until)));
+    } else if let Some(minutes) = id.strip_prefix(PRESET_PREFIX) {
+        let Ok(minutes) = minutes.parse::<i32>() else {
+            return false;
+        };
+        // Like a settings change from the UI, the next Work session uses it
+        if let Err(err) = modify_settings(app, |settings| settings.work_time = minutes) {
+            eprintln!("Couldn't apply the {minutes} minute preset: {err}");
+            return true;
+        }
+        events::emit(app, "settings-changed", read_settings(app));
+        refresh(app);
+    } else {
+        return false;
+    }
+    true
+}
+
+// Decoded once, every badge is drawn over a fresh copy
+fn base_icon() -> Option<&'static Canvas> {
+    static BASE: OnceLock<Option<Canvas>> = OnceLock::new();
+    BASE.get_or_init(|| {
+        let decoder = png::Decoder::new(TRAY_ICON);
+        let mut reader = decoder.read_info().ok()?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).ok()?;
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            eprintln!("The tray icon isn't 8-bit RGBA, badges are off");
+            return None;
+        }
+        pixels.truncate(info.buffer_size());
+        Some(Canvas {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    })
+    .as_ref()
+}
+
+fn icon_of(canvas: Canvas) -> Icon {
+    Icon::Rgba {
+        rgba: canvas.pixels,
+        width: canvas.width,
+        height: canvas.height,
+    }
+}
+
+fn plain_icon() -> Option<Canvas> {
+    let base = base_icon()?;
+    Some(Canvas {
+        width: base.width,
+        height: base.height,
+        pixels: base.pixels.clone(),
+    })
+}
+
+fn badge_icon(count: i32, color: render::Rgba) -> Option<Canvas> {
+    let mut canvas = plain_icon()?;
+
+    let text = if count > 99 {
+        "99+".to_string()
+    } else {
+        count.to_string()
+    };
+    let size = canvas.width / BADGE_FRACTION;
+    let (x, y) = (canvas.width - size, canvas.height - size);
+    canvas.fill_circle(x + size / 2, y + size / 2, size / 2, color);
+
+    // As large as fits in the circle, leaving a margin
+    let scale = (size * 2 / 3 / Canvas::text_width(&text, 1)).clamp(1, size / 10);
+    let text_x = x + size.saturating_sub(Canvas::text_width(&text, scale)) / 2;
+    let text_y = y + size.saturating_sub(render::GLYPH_HEIGHT * scale) / 2;
+    canvas.draw_text(&text, text_x, text_y, scale, BADGE_TEXT);
+    Some(canvas)
+}
+
+fn countdown(remaining_secs: i32) -> String {
+    format!("{:02}:{:02}", remaining_secs / 60, remaining_secs % 60)
+}
+
+fn set_title(app: &AppHandle, title: &str) {
+    #[cfg(target_os = "macos")]
+    let _ = app.tray_handle().set_title(title);
+    #[cfg(not(target_os = "macos"))]
+    let _ = (app, title);
+}
+
+// Redraws the tray icon and rebuilds its menu, after the phase, the tag,
+// today's count or the settings changed. Happens on the tray thread, up to
+// `COALESCE` later.
+pub fn refresh(app: &AppHandle) {
+    let pending = app.state::<PendingRefresh>();
+    *pending.0.lock().unwrap() = true;
+    pending.1.notify_one();
+}
+
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || loop {
+        {
+            let pending = app.state::<PendingRefresh>();
+            let mut requested = pending.0.lock().unwrap();
+            while !*requested {
+                requested = pending.1.wait(requested).unwrap();
+            }
+        }
+        thread::sleep(COALESCE);
+        *app.state::<PendingRefresh>().0.lock().unwrap() = false;
+        redraw(&app);
+    });
+}
+
+fn redraw(app: &AppHandle) {
+    let settings = read_settings(app);
+    let display = settings.tray_display;
+    *app.state::<CurrentDisplay>().0.lock().unwrap() = display;
+    let tray = app.tray_handle();
+
+    let mut stats = None;
+    with_store(app, |store| {
+        stats = get_from_store::<Stats>(store, "stats").ok();
+        Ok(())
+    });
+    let sessions = stats.as_ref().map_or(0, |stats| stats.today.sessions);
+    if let Err(err) = tray.set_menu(build_menu(app, &settings, stats)) {
+        eprintln!("Failed to rebuild the tray menu: {err}");
+    }
+
+    let badge = match display {
+        TrayDisplay::Badge => {
+            let phase = *app.state::<Phase>().0.lock().unwrap();
+            badge_icon(sessions, render::phase_color(phase))
+        }
+        _ => None,
+    };
+    // A colored badge mustn't be turned into a template silhouette
+    #[cfg(target_os = "macos")]
+    let _ = tray.set_icon_as_template(badge.is_none());
+    if let Some(icon) = badge.or_else(plain_icon) {
+        let _ = tray.set_icon(icon_of(icon));
+    }
+
+    let title = match display {
+        TrayDisplay::Title => {
+            countdown(app.state::<Timer>().0.lock().unwrap().remaining_secs_now())
+        }
+        _ => String::new(),
+    };
+    set_title(app, &title);
+}
+
+pub fn on_tick(app: &AppHandle, remaining_secs: i32) {
+    if *app.state::<CurrentDisplay>().0.lock().unwrap() == TrayDisplay::Title {
+        set_title(app, &countdown(remaining_secs));
+    }
+}
@@ -0,0 +1,194 @@
+use std::process::Command;
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// `pactl get-sink-volume` prints "Volume: front-left: 42598 /  65% / -11.23 dB, ..."
+fn parse_pactl_volume(output: &str) -> Option<f32> {
+    let percent = output.find('%')?;
+    let digits = output[..percent]
+        .rsplit(|c: char| !c.is_ascii_digit())
+        .next()?;
+    digits.parse::<f32>().ok().map(|percent| percent / 100.0)
+}
+
+// System output volume, from 0.0 to 1.0
+pub fn output_volume() -> Option<f32> {
+    if cfg!(target_os = "linux") {
+        parse_pactl_volume(&output("pactl", &["get-sink-volume", "@DEFAULT_SINK@"])?)
+    } else if cfg!(target_os = "macos") {
+        let volume = output(
+            "osascript",
+            &["-e", "output volume of (get volume settings)"],
+        )?;
+        volume
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .map(|percent| percent / 100.0)
+    } else {
+        #[cfg(windows)]
+        return endpoint::output_volume();
+        #[cfg(not(windows))]
+        None
+    }
+}
+
+pub fn set_output_volume(level: f32) {
+    let percent = (level.clamp(0.0, 1.0) * 100.0).round() as u32;
+
+    let result = if cfg!(target_os = "linux") {
+        output(
+            "pactl",
+            &["set-sink-volume", "@DEFAULT_SINK@", &format!("{percent}%")],
+        )
+    } else if cfg!(target_os = "macos") {
+        output(
+            "osascript",
+            &["-e", &format!("set volume output volume {percent}")],
+        )
+    } else {
+        // The Windows endpoint reports its own failures
+        #[cfg(windows)]
+        endpoint::set_output_volume(level);
+        Some(String::new())
+    };
+    if result.is_none() {
+        eprintln!("Failed to set output volume to {percent}%");
+    }
+}
+
+// Master volume of the default render device, via IAudioEndpointVolume
+#[cfg(windows)]
+mod endpoint {
+    use std::{ffi::c_void, ptr};
+
+    #[repr(C)]
+    struct Guid(u32, u16, u16, [u8; 8]);
+
+    const CLSID_MM_DEVICE_ENUMERATOR: Guid = Guid(
+        0xBCDE_0395,
+        0xE52F,
+        0x467C,
+        [0x8E, 0x3D, 0xC4, 0x57, 0x92, 0x91, 0x69, 0x2E],
+    );
+    const IID_IMM_DEVICE_ENUMERATOR: Guid = Guid(
+        0xA956_64D2,
+        0x9614,
+        0x4F35,
+        [0xA7, 0x46, 0xDE, 0x8D, 0xB6, 0x36, 0x17, 0xE6],
+    );
+    const IID_IAUDIO_ENDPOINT_VOLUME: Guid = Guid(
+        0x5CDF_2C82,
+        0x841E,
+        0x4546,
+        [0x97, 0x22, 0x0C, 0xF7, 0x40, 0x78, 0x22, 0x9A],
+    );
+    const CLSCTX_ALL: u32 = 0x17;
+    const E_RENDER: i32 = 0;
+    const E_CONSOLE: i32 = 0;
+
+    // A COM object is a pointer to its vtable
+    type Object = *mut *const usize;
+
+    #[link(name = "ole32")]
+    extern "system" {
+        fn CoInitializeEx(reserved: *const c_void, co_init: u32) -> i32;
+        fn CoCreateInstance(
+            clsid: *const Guid,
+            outer: *mut c_void,
+            cls_context: u32,
+            iid: *const Guid,
+            object: *mut Object,
+        ) -> i32;
+    }
+
+    unsafe fn method<F: Copy>(object: Object, index: usize) -> F {
+        std::mem::transmute_copy(&*(*object).add(index))
+    }
+
+    unsafe fn release(object: Object) {
+        let release: extern "system" fn(Object) -> u32 = method(object, 2);
+        release(object);
+    }
+
+    unsafe fn endpoint_volume() -> Option<Object> {
+        // Called from short-lived helper threads, the result only says whether it was already initialized
+        CoInitializeEx(ptr::null(), 0);
+
+        let mut enumerator = ptr::null_mut();
+        let status = CoCreateInstance(
+            &CLSID_MM_DEVICE_ENUMERATOR,
+            ptr::null_mut(),
+            CLSCTX_ALL,
+            &IID_IMM_DEVICE_ENUMERATOR,
+            &mut enumerator,
+        );
+        if status < 0 {
+            return None;
+        }
+
+        let get_default_audio_endpoint: extern "system" fn(Object, i32, i32, *mut Object) -> i32 =
+            method(enumerator, 4);
+        let mut device = ptr::null_mut();
+        let status = get_default_audio_endpoint(enumerator, E_RENDER, E_CONSOLE, &mut device);
+        release(enumerator);
+        if status < 0 {
+            return None;
+        }
+
+        let activate: extern "system" fn(
+            Object,
+            *const Guid,
+            u32,
+            *const c_void,
+            *mut Object,
+        ) -> i32 = method(device, 3);
+        let mut volume = ptr::null_mut();
+        let status = activate(
+            device,
+            &IID_IAUDIO_ENDPOINT_VOLUME,
+            CLSCTX_ALL,
+            ptr::null(),
+            &mut volume,
+        );
+        release(device);
+        (status >= 0).then_some(volume)
+    }
+
+    pub fn output_volume() -> Option<f32> {
+        unsafe {
+            let volume = endpoint_volume()?;
+            let get_master_volume_level_scalar: extern "system" fn(Object, *mut f32) -> i32 =
+                method(volume, 9);
+            let mut level = 0.0;
+            let status = get_master_volume_level_scalar(volume, &mut level);
+            release(volume);
+            (status >= 0).then_some(level)
+        }
+    }
+
+    pub fn set_output_volume(level: f32) {
+        unsafe {
+            let Some(volume) = endpoint_volume() else {
+                eprintln!("No default audio endpoint");
+                return;
+            };
+            let set_master_volume_level_scalar: extern "system" fn(
+                Object,
+                f32,
+                *const Guid,
+            ) -> i32 = method(volume, 7);
+            let status = set_master_volume_level_scalar(volume, level, ptr::null());
+            release(volume);
+            if status < 0 {
+                eprintln!("Failed to set output volume: HRESULT {status:#x}");
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Manager, Window};
+
+use crate::events;
+
+// Pages call `heartbeat` every couple of seconds, this much silence means it's gone
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Pulse {
+    last: Instant,
+    stale: bool,
+}
+
+// Last heartbeat of each webview, keyed by window label
+#[derive(Default)]
+pub struct Heartbeats(Mutex<HashMap<String, Pulse>>);
+
+#[tauri::command]
+pub fn heartbeat(window: Window, heartbeats: tauri::State<Heartbeats>) {
+    let now = Instant::now();
+    let gap = {
+        let mut heartbeats = heartbeats.0.lock().unwrap();
+        let pulse = heartbeats
+            .entry(window.label().to_string())
+            .or_insert(Pulse {
+                last: now,
+                stale: false,
+            });
+        let gap = pulse.stale.then(|| now.duration_since(pulse.last));
+        pulse.last = now;
+        pulse.stale = false;
+        gap
+    };
+
+    // The page may have missed anything emitted while it was gone
+    if let Some(gap) = gap {
+        eprintln!(
+            "Frontend '{}' is back after {}s, re-pushing state",
+            window.label(),
+            gap.as_secs()
+        );
+        events::replay(&window);
+    }
+}
+
+// The timer lives in the backend and keeps going regardless, this only
+// notices and logs when a frontend stops answering
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+
+        let heartbeats = app.state::<Heartbeats>();
+        let mut heartbeats = heartbeats.0.lock().unwrap();
+        // Closed windows don't come back
+        heartbeats.retain(|label, _| app.get_window(label).is_some());
+        for (label, pulse) in heartbeats.iter_mut() {
+            if !pulse.stale && pulse.last.elapsed() > HEARTBEAT_TIMEOUT {
+                pulse.stale = true;
+                eprintln!("Frontend '{label}' stopped responding, the timer keeps running");
+            }
+        }
+    });
+}
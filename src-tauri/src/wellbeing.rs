@@ -0,0 +1,201 @@
+use chrono::{Days, Local, NaiveDate, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use tauri::AppHandle;
+
+use crate::{
+    events,
+    history::{self, SessionRecord},
+    read_settings, with_store, TimePhase,
+};
+
+const ALERTED_KEY: &str = "wellbeing_alerted";
+// Sessions a day that, kept up every day for a week, is a lot
+const HEAVY_DAY_SESSIONS: i32 = 10;
+const HEAVY_DAYS: u64 = 7;
+// Out of the most recent long breaks, how many skipped ones is a habit
+const RECENT_LONG_BREAKS: usize = 5;
+const SKIPPED_LONG_BREAKS: usize = 4;
+// Local hours counted as late night, and on how many of the last week's days
+const LATE_FROM_HOUR: u32 = 23;
+const LATE_UNTIL_HOUR: u32 = 5;
+const LATE_NIGHTS: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Pattern {
+    HeavyWeek,
+    SkippedLongBreaks,
+    LateNights,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct WellbeingAlert {
+    pub pattern: Pattern,
+    // The number that tripped it, against the threshold it crossed
+    pub metric: i32,
+    pub threshold: i32,
+    pub message: String,
+}
+
+// Days are UTC days like the stats, hours are local so "late" means late
+// where the user is
+fn alerts<Tz: TimeZone>(
+    records: &[SessionRecord],
+    today: NaiveDate,
+    tz: &Tz,
+) -> Vec<WellbeingAlert> {
+    let mut alerts = Vec::new();
+    let week_start = today - Days::new(HEAVY_DAYS - 1);
+    let completed_work: Vec<_> = records
+        .iter()
+        .filter(|record| record.completed && record.phase == TimePhase::Work)
+        .collect();
+
+    let mut per_day: BTreeMap<NaiveDate, i32> = BTreeMap::new();
+    for record in &completed_work {
+        let date = record.end.date_naive();
+        if date >= week_start && date <= today {
+            *per_day.entry(date).or_default() += 1;
+        }
+    }
+    let lightest = week_start
+        .iter_days()
+        .take(HEAVY_DAYS as usize)
+        .map(|date| per_day.get(&date).copied().unwrap_or(0))
+        .min()
+        .unwrap_or(0);
+    if lightest >= HEAVY_DAY_SESSIONS {
+        alerts.push(WellbeingAlert {
+            pattern: Pattern::HeavyWeek,
+            metric: lightest,
+            threshold: HEAVY_DAY_SESSIONS,
+            message: format!(
+                "At least {lightest} pomodoros every day this week. Maybe plan a lighter day?"
+            ),
+        });
+    }
+
+    let skipped = records
+        .iter()
+        .filter(|record| record.phase == TimePhase::LongBreak)
+        .rev()
+        .take(RECENT_LONG_BREAKS)
+        .filter(|record| !record.completed)
+        .count();
+    if skipped >= SKIPPED_LONG_BREAKS {
+        alerts.push(WellbeingAlert {
+            pattern: Pattern::SkippedLongBreaks,
+            metric: skipped as i32,
+            threshold: SKIPPED_LONG_BREAKS as i32,
+            message: format!(
+                "{skipped} of your last {RECENT_LONG_BREAKS} long breaks were skipped. They're there to recharge."
+            ),
+        });
+    }
+
+    let late_nights: BTreeSet<NaiveDate> = completed_work
+        .iter()
+        .filter(|record| record.end.date_naive() >= week_start)
+        .filter(|record| {
+            let hour = record.end.with_timezone(tz).hour();
+            hour >= LATE_FROM_HOUR || hour < LATE_UNTIL_HOUR
+        })
+        .map(|record| record.end.date_naive())
+        .collect();
+    if late_nights.len() >= LATE_NIGHTS {
+        alerts.push(WellbeingAlert {
+            pattern: Pattern::LateNights,
+            metric: late_nights.len() as i32,
+            threshold: LATE_NIGHTS as i32,
+            message: format!(
+                "You worked late on {} nights this week. Sleep helps focus too.",
+                late_nights.len()
+            ),
+        });
+    }
+    alerts
+}
+
+// After a phase change. Each pattern is brought up at most once a day.
+pub fn check(app: &AppHandle) {
+    if !read_settings(app).wellbeing_alerts {
+        return;
+    }
+    let today = Utc::now().date_naive();
+    let mut new_alerts = Vec::new();
+    with_store(app, |store| {
+        let mut alerted: BTreeMap<Pattern, NaiveDate> = store
+            .get(ALERTED_KEY)
+            .and_then(|value| from_value(value.clone()).ok())
+            .unwrap_or_default();
+        for alert in alerts(&history::load(store), today, &Local) {
+            if alerted.get(&alert.pattern) != Some(&today) {
+                alerted.insert(alert.pattern, today);
+                new_alerts.push(alert);
+            }
+        }
+        if new_alerts.is_empty() {
+            return Ok(());
+        }
+        store.insert(ALERTED_KEY.into(), json!(alerted))
+    });
+    for alert in new_alerts {
+        events::emit_once(app, "wellbeing-alert", alert);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(phase: TimePhase, end: &str, completed: bool) -> SessionRecord {
+        SessionRecord {
+            completed,
+            ..SessionRecord::ended(phase, end, 25)
+        }
+    }
+
+    #[test]
+    fn alerts_flag_heavy_weeks_skipped_long_breaks_and_late_nights() {
+        let mut records = Vec::new();
+        for day in 4..=10 {
+            for hour in 8..18 {
+                let end = format!("2024-03-{day:02}T{hour:02}:00:00Z");
+                records.push(record(TimePhase::Work, &end, true));
+            }
+        }
+        for (day, completed) in [(6, true), (7, false), (8, false), (9, false), (10, false)] {
+            let end = format!("2024-03-{day:02}T12:30:00Z");
+            records.push(record(TimePhase::LongBreak, &end, completed));
+        }
+        for day in [5, 8, 9] {
+            let end = format!("2024-03-{day:02}T23:30:00Z");
+            records.push(record(TimePhase::Work, &end, true));
+        }
+
+        let found: Vec<_> = alerts(&records, "2024-03-10".parse().unwrap(), &Utc)
+            .iter()
+            .map(|alert| (alert.pattern, alert.metric))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Pattern::HeavyWeek, 10),
+                (Pattern::SkippedLongBreaks, 4),
+                (Pattern::LateNights, 3),
+            ]
+        );
+
+        // One day off breaks the streak
+        let day_off: NaiveDate = "2024-03-07".parse().unwrap();
+        records
+            .retain(|record| record.phase != TimePhase::Work || record.end.date_naive() != day_off);
+        let patterns: Vec<_> = alerts(&records, "2024-03-10".parse().unwrap(), &Utc)
+            .iter()
+            .map(|alert| alert.pattern)
+            .collect();
+        assert_eq!(patterns, [Pattern::SkippedLongBreaks, Pattern::LateNights]);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    audio, events, get_remaining, presence, publish_state, read_settings, secrets, subsystems,
+    timer, tray, with_store, ActiveProfile, ActiveTag, ActiveTask, Error, LastCredit,
+    NotificationHistory, Phase, SessionNumber, TimePhase, STORE_PATH,
+};
+
+// A token from `request_erase_token` has to be used within this long
+const TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+
+// The last token handed out, and when
+#[derive(Default)]
+pub struct EraseToken(Mutex<Option<(String, Instant)>>);
+
+// First half of the wipe: the frontend asks, makes sure the user means it,
+// then passes the token back to `erase_all_data`
+#[tauri::command]
+pub fn request_erase_token(app: AppHandle) -> String {
+    let token = format!("{:032x}", rand::random::<u128>());
+    *app.state::<EraseToken>().0.lock().unwrap() = Some((token.clone(), Instant::now()));
+    token
+}
+
+// Overwrites the file with zeros before removing it, so the old contents
+// aren't just unlinked
+fn shred(path: &Path) -> io::Result<()> {
+    let len = fs::metadata(path)?.len();
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.write_all(&vec![0; len as usize])?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)
+}
+
+fn shred_dir(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            shred_dir(&path)?;
+        } else {
+            shred(&path)?;
+        }
+    }
+    fs::remove_dir(dir)
+}
+
+fn ignore_missing(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+// Stats, history, settings and every other store key, copied sounds,
+// the presence file and the keychain secrets. The app carries on from a
+// fresh first launch. The config file is the user's own and stays.
+#[tauri::command]
+pub fn erase_all_data(confirm_token: String, app: AppHandle) -> Result<(), Error> {
+    let issued = app.state::<EraseToken>().0.lock().unwrap().take();
+    match issued {
+        Some((token, at)) if token == confirm_token && at.elapsed() < TOKEN_LIFETIME => {}
+        _ => return Err(Error::InvalidEraseToken),
+    }
+
+    timer::set_running(&app, false);
+    // Read while the settings still name the file
+    presence::remove(&app);
+    for name in secrets::SECRET_NAMES {
+        secrets::delete(&app, name)?;
+    }
+
+    if let Some(dir) = app.path_resolver().app_data_dir() {
+        ignore_missing(shred(&dir.join(STORE_PATH)))?;
+        ignore_missing(shred_dir(&dir.join(audio::SOUND_DIR)))?;
+    }
+    reset(&app)
+}
+
+// Back to a first launch's defaults, in the store and in memory
+pub fn reset(app: &AppHandle) -> Result<(), Error> {
+    let mut result = Ok(());
+    with_store(app, |store| {
+        store.reset()?;
+        result = store.save();
+        Ok(())
+    });
+    result?;
+
+    *app.state::<Phase>().0.lock().unwrap() = TimePhase::default();
+    *app.state::<SessionNumber>().0.lock().unwrap() = 0;
+    *app.state::<ActiveTag>().0.lock().unwrap() = None;
+    *app.state::<ActiveTask>().0.lock().unwrap() = None;
+    *app.state::<ActiveProfile>().0.lock().unwrap() = None;
+    *app.state::<LastCredit>().0.lock().unwrap() = None;
+    app.state::<NotificationHistory>().0.lock().unwrap().clear();
+    with_store(app, |store| {
+        let remaining = get_remaining(app, store).unwrap_or_default();
+        timer::reset(app, remaining);
+        Ok(())
+    });
+
+    events::emit(app, "settings-changed", read_settings(app));
+    publish_state(app);
+    tray::refresh(app);
+    subsystems::start_enabled(app);
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use tauri::AppHandle;
+
+use crate::{modify_settings, read_settings, set_active_tag, Error, TimePhase};
+
+// Tag to use when the foreground application name or window title
+// contains `pattern`, ignoring case
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TagRule {
+    pub pattern: String,
+    pub tag: String,
+}
+
+#[derive(Default, Debug)]
+struct ForegroundWindow {
+    app: String,
+    title: String,
+}
+
+pub fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+const MACOS_FRONT_WINDOW: &str = r#"tell application "System Events"
+    set frontApp to first application process whose frontmost is true
+    set appName to name of frontApp
+    try
+        set windowTitle to name of front window of frontApp
+    on error
+        set windowTitle to ""
+    end try
+end tell
+return appName & linefeed & windowTitle"#;
+
+// Only X11 exposes the active window to other clients, Wayland has no equivalent
+fn foreground_window() -> Option<ForegroundWindow> {
+    if cfg!(target_os = "linux") {
+        Some(ForegroundWindow {
+            app: output("xdotool", &["getactivewindow", "getwindowclassname"]).unwrap_or_default(),
+            title: output("xdotool", &["getactivewindow", "getwindowname"])?,
+        })
+    } else if cfg!(target_os = "macos") {
+        let output = output("osascript", &["-e", MACOS_FRONT_WINDOW])?;
+        let (app, title) = output.split_once('\n').unwrap_or((&output, ""));
+        Some(ForegroundWindow {
+            app: app.to_string(),
+            title: title.to_string(),
+        })
+    } else {
+        #[cfg(windows)]
+        return win32::foreground_window();
+        #[cfg(not(windows))]
+        None
+    }
+}
+
+#[cfg(windows)]
+mod win32 {
+    use std::{ffi::c_void, path::Path};
+
+    use super::ForegroundWindow;
+
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetForegroundWindow() -> *mut c_void;
+        fn GetWindowTextW(hwnd: *mut c_void, text: *mut u16, max_count: i32) -> i32;
+        fn GetWindowThreadProcessId(hwnd: *mut c_void, process_id: *mut u32) -> u32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(access: u32, inherit: i32, process_id: u32) -> *mut c_void;
+        fn QueryFullProcessImageNameW(
+            process: *mut c_void,
+            flags: u32,
+            name: *mut u16,
+            size: *mut u32,
+        ) -> i32;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+
+    // File name of the executable, e.g. "idea64.exe"
+    fn process_name(process_id: u32) -> Option<String> {
+        let mut buffer = [0u16; 1024];
+        let mut size = buffer.len() as u32;
+        let ok = unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+            if process.is_null() {
+                return None;
+            }
+            let ok = QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut size);
+            CloseHandle(process);
+            ok
+        };
+        if ok == 0 {
+            return None;
+        }
+        let path = String::from_utf16_lossy(&buffer[..size as usize]);
+        Path::new(&path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+
+    pub fn foreground_window() -> Option<ForegroundWindow> {
+        let mut title = [0u16; 512];
+        let mut process_id = 0u32;
+        let length = unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_null() {
+                return None;
+            }
+            GetWindowThreadProcessId(hwnd, &mut process_id);
+            GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32)
+        };
+        Some(ForegroundWindow {
+            app: process_name(process_id).unwrap_or_default(),
+            title: String::from_utf16_lossy(&title[..length.max(0) as usize]),
+        })
+    }
+}
+
+fn matching_tag<'a>(rules: &'a [TagRule], window: &ForegroundWindow) -> Option<&'a str> {
+    let app = window.app.to_lowercase();
+    let title = window.title.to_lowercase();
+    rules
+        .iter()
+        .filter(|rule| !rule.pattern.is_empty())
+        .find(|rule| {
+            let pattern = rule.pattern.to_lowercase();
+            app.contains(&pattern) || title.contains(&pattern)
+        })
+        .map(|rule| rule.tag.as_str())
+}
+
+// Tag the Work session after whatever is in front when it starts. Being
+// picked at the start, the tag's duration overrides apply from the next phase.
+pub fn on_phase_change(app: &AppHandle, phase: TimePhase) {
+    if phase != TimePhase::Work {
+        return;
+    }
+    let rules = read_settings(app).tag_rules;
+    if rules.is_empty() {
+        return;
+    }
+
+    let Some(window) = foreground_window() else {
+        return;
+    };
+    if let Some(tag) = matching_tag(&rules, &window) {
+        set_active_tag(app, Some(tag.to_string()));
+    }
+}
+
+#[tauri::command]
+pub fn set_tag_rules(rules: Vec<TagRule>, app: AppHandle) -> Result<(), Error> {
+    modify_settings(&app, |settings| settings.tag_rules = rules)
+}
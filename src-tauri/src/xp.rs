@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, json};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::Store;
+
+use crate::{events, with_store, TimePhase};
+
+const XP_KEY: &str = "xp";
+const XP_PER_MINUTE: f64 = 10.0;
+// Each completed session in a row adds this much, up to `MAX_STREAK_BONUS`
+const STREAK_BONUS: f64 = 0.1;
+const MAX_STREAK_BONUS: f64 = 1.0;
+// For the Work session after a long break that ran out on its own
+const RESTED_BONUS: f64 = 0.5;
+// Level n takes LEVEL_STEP * n * (n + 1) / 2 XP in total: 100, 300, 600, ...
+const LEVEL_STEP: i64 = 100;
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct Progress {
+    xp: i64,
+    // Work sessions completed since the last skipped one
+    streak: i32,
+    // Whether the last long break was taken in full
+    rested: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Level {
+    pub level: i64,
+    pub xp: i64,
+    // Total XP at which the current level and the next one start
+    pub level_xp: i64,
+    pub next_level_xp: i64,
+    pub streak: i32,
+    pub multiplier: f64,
+}
+
+#[derive(Serialize, Clone)]
+struct XpGained {
+    amount: i64,
+    multiplier: f64,
+    leveled_up: bool,
+    level: Level,
+}
+
+fn threshold(level: i64) -> i64 {
+    LEVEL_STEP * level * (level + 1) / 2
+}
+
+fn level_for(xp: i64) -> i64 {
+    let mut level = 0;
+    while threshold(level + 1) <= xp {
+        level += 1;
+    }
+    level
+}
+
+impl Progress {
+    fn multiplier(&self) -> f64 {
+        let streak = (self.streak as f64 * STREAK_BONUS).min(MAX_STREAK_BONUS);
+        let rested = if self.rested { RESTED_BONUS } else { 0.0 };
+        1.0 + streak + rested
+    }
+
+    fn level(&self) -> Level {
+        let level = level_for(self.xp);
+        Level {
+            level,
+            xp: self.xp,
+            level_xp: threshold(level),
+            next_level_xp: threshold(level + 1),
+            streak: self.streak,
+            multiplier: self.multiplier(),
+        }
+    }
+}
+
+fn load(store: &Store<Wry>) -> Progress {
+    store
+        .get(XP_KEY)
+        .and_then(|value| from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+// When a phase ends outside practice mode. `credited` is the minutes of a
+// Work session that counted, `completed` whether the phase ran out on its own.
+pub fn on_phase_end(
+    app: &AppHandle,
+    store: &mut Store<Wry>,
+    phase: TimePhase,
+    completed: bool,
+    credited: Option<i32>,
+) {
+    let mut progress = load(store);
+    match (phase, credited) {
+        (TimePhase::Work, Some(minutes)) => {
+            let multiplier = progress.multiplier();
+            let amount = (minutes as f64 * XP_PER_MINUTE * multiplier).round() as i64;
+            let before = level_for(progress.xp);
+            progress.xp += amount;
+            progress.streak += 1;
+            progress.rested = false;
+
+            let level = progress.level();
+            events::emit_once(
+                app,
+                "xp-gained",
+                XpGained {
+                    amount,
+                    multiplier,
+                    leveled_up: level.level > before,
+                    level,
+                },
+            );
+        }
+        (TimePhase::Work, None) => progress.streak = 0,
+        (TimePhase::LongBreak, _) => progress.rested = completed,
+        (TimePhase::ShortBreak, _) => {}
+    }
+    if let Err(err) = store.insert(XP_KEY.into(), json!(progress)) {
+        eprintln!("Failed to save XP: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn get_level(app: AppHandle) -> Level {
+    let mut level = Progress::default().level();
+    with_store(&app, |store| {
+        level = load(store).level();
+        Ok(())
+    });
+    level
+}